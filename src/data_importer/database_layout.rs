@@ -0,0 +1,96 @@
+// Placement of several Firestore databases in one PostgreSQL database
+use crate::data_importer::transformer::TableRow;
+use crate::types::{ColumnDefinition, FirestoreDocument, IndexDefinition, NormalizedSchema, PostgreSQLType};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// Column naming the Firestore database of each row in the
+/// [`DatabaseLayout::DiscriminatorColumn`] layout
+pub const DATABASE_COLUMN: &str = "firestore_database";
+
+/// How documents of several Firestore databases (or projects) share one
+/// PostgreSQL database
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DatabaseLayout {
+    /// Documents of every database go into the same tables
+    #[default]
+    Shared,
+    /// One PostgreSQL schema per Firestore database (see [`DatabaseId::schema_name`])
+    SchemaPerDatabase,
+    /// Shared tables with a [`DATABASE_COLUMN`] holding each row's database
+    DiscriminatorColumn,
+}
+
+/// A Firestore database, identified by its project and database ID
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DatabaseId {
+    pub project: String,
+    pub database: String,
+}
+
+impl DatabaseId {
+    /// Database of a document, when its metadata names both project and database
+    pub fn of(document: &FirestoreDocument) -> Option<Self> {
+        Some(Self {
+            project: document.metadata.project.clone()?,
+            database: document.metadata.database.clone()?,
+        })
+    }
+
+    /// `project/database`, as stored in [`DATABASE_COLUMN`]
+    pub fn label(&self) -> String {
+        format!("{}/{}", self.project, self.database)
+    }
+
+    /// PostgreSQL schema for the database, e.g. `my_app_default` for the
+    /// `(default)` database of `my-app` and `my_app_orders` for `orders`.
+    /// Names not starting with a letter get a `db_` prefix, so that they can
+    /// be used unquoted (`db_2024_archive_default`).
+    pub fn schema_name(&self) -> String {
+        let database = self.database.trim_start_matches('(').trim_end_matches(')');
+        let name: String = format!("{}_{}", self.project, database)
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+            .collect();
+        if name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            name
+        } else {
+            format!("db_{}", name)
+        }
+    }
+}
+
+/// Split documents by database, in database order. Documents whose database
+/// is unknown (bare paths, Realtime Database exports) are grouped under `None`,
+/// which comes first.
+pub fn group_by_database(documents: Vec<FirestoreDocument>) -> Vec<(Option<DatabaseId>, Vec<FirestoreDocument>)> {
+    let mut groups: BTreeMap<Option<DatabaseId>, Vec<FirestoreDocument>> = BTreeMap::new();
+    for document in documents {
+        groups.entry(DatabaseId::of(&document)).or_default().push(document);
+    }
+    groups.into_iter().collect()
+}
+
+/// Add the [`DATABASE_COLUMN`] (and an index on it) to every table of a schema
+pub fn add_database_column(schema: &mut NormalizedSchema) {
+    for table in &mut schema.tables {
+        if table.columns.iter().any(|column| column.name == DATABASE_COLUMN) {
+            continue;
+        }
+        table.add_column(ColumnDefinition::new(DATABASE_COLUMN.to_string(), PostgreSQLType::Text));
+        table.indexes.push(IndexDefinition {
+            name: format!("idx_{}_{}", table.name, DATABASE_COLUMN),
+            columns: vec![DATABASE_COLUMN.to_string()],
+            unique: false,
+            index_type: None,
+        });
+    }
+}
+
+/// Set the [`DATABASE_COLUMN`] of every transformed row
+pub fn tag_rows(table_data: &mut HashMap<String, Vec<TableRow>>, database: Option<&DatabaseId>) {
+    let value = database.map_or(Value::Null, |database| Value::String(database.label()));
+    for row in table_data.values_mut().flatten() {
+        row.columns.insert(DATABASE_COLUMN.to_string(), value.clone());
+    }
+}
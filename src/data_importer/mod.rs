@@ -3,10 +3,11 @@ pub mod importer;
 pub mod transformer;
 pub mod type_mapper;
 pub mod sql_generator;
+pub mod database_layout;
 
 #[cfg(test)]
 mod tests;
 
 // Re-export main types and traits
-pub use importer::{PostgreSQLImporter, ConnectionConfig, FullImportResult};
+pub use importer::{PostgreSQLImporter, ConnectionConfig, FullImportResult, TableImportSpec};
 pub use transformer::*;
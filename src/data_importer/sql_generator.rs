@@ -1,8 +1,8 @@
 use crate::error::FireupError;
 use crate::types::{TableDefinition, NormalizedSchema};
-use crate::data_importer::transformer::{TableRow, TransformationResult};
+use crate::data_importer::transformer::{tables_in_dependency_order, TableRow, TransformationResult};
 use serde_json::Value;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 /// Configuration for SQL generation
@@ -257,44 +257,20 @@ impl SQLGenerator {
         warnings: &mut Vec<String>,
     ) -> Result<Vec<SQLStatement>, FireupError> {
         let mut statements = Vec::new();
-        let mut processed_tables = HashSet::new();
-        let mut remaining_tables: Vec<&TableDefinition> = schema.tables.iter().collect();
 
         // Process tables in dependency order
-        while !remaining_tables.is_empty() {
-            let mut progress_made = false;
-
-            remaining_tables.retain(|table| {
-                // Check if all referenced tables have been processed
-                let can_process = table.foreign_keys.iter().all(|fk| {
-                    processed_tables.contains(&fk.referenced_table) || fk.referenced_table == table.name
-                });
-
-                if can_process {
-                    if let Some(rows) = table_data.get(&table.name) {
-                        if !rows.is_empty() {
-                            match self.generate_table_insert_statements(table, rows, warnings) {
-                                Ok(table_statements) => {
-                                    statements.extend(table_statements);
-                                }
-                                Err(e) => {
-                                    warnings.push(format!("Failed to generate INSERT for table {}: {}", table.name, e));
-                                }
-                            }
+        for table in tables_in_dependency_order(schema)? {
+            if let Some(rows) = table_data.get(&table.name) {
+                if !rows.is_empty() {
+                    match self.generate_table_insert_statements(table, rows, warnings) {
+                        Ok(table_statements) => {
+                            statements.extend(table_statements);
+                        }
+                        Err(e) => {
+                            warnings.push(format!("Failed to generate INSERT for table {}: {}", table.name, e));
                         }
                     }
-                    processed_tables.insert(table.name.clone());
-                    progress_made = true;
-                    false // Remove from remaining_tables
-                } else {
-                    true // Keep in remaining_tables
                 }
-            });
-
-            if !progress_made && !remaining_tables.is_empty() {
-                return Err(FireupError::TypeMapping(
-                    "Circular dependency detected in table relationships".to_string()
-                ));
             }
         }
 
@@ -1,11 +1,47 @@
 use crate::error::FireupError;
-use crate::types::{FirestoreDocument, NormalizedSchema, TableDefinition, ColumnDefinition, PostgreSQLType};
+use crate::types::{relative_document_path, FirestoreDocument, FirestoreValue, NormalizedSchema, TableDefinition, ColumnDefinition, PostgreSQLType};
 use crate::data_importer::type_mapper::DataTypeMapper;
-use serde_json::{Value, Map};
+use serde_json::Value;
 use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 use chrono::DateTime;
 
+/// Tables of a schema ordered so that every table comes after the tables its
+/// foreign keys reference (self-references aside). Fails on a reference cycle.
+pub fn tables_in_dependency_order(schema: &NormalizedSchema) -> Result<Vec<&TableDefinition>, FireupError> {
+    let mut ordered = Vec::new();
+    let mut processed_tables = HashSet::new();
+    let mut remaining_tables: Vec<&TableDefinition> = schema.tables.iter().collect();
+
+    while !remaining_tables.is_empty() {
+        let mut progress_made = false;
+
+        remaining_tables.retain(|table| {
+            // Check if all referenced tables have been processed
+            let can_process = table.foreign_keys.iter().all(|fk| {
+                processed_tables.contains(&fk.referenced_table) || fk.referenced_table == table.name
+            });
+
+            if can_process {
+                ordered.push(*table);
+                processed_tables.insert(table.name.clone());
+                progress_made = true;
+                false // Remove from remaining_tables
+            } else {
+                true // Keep in remaining_tables
+            }
+        });
+
+        if !progress_made && !remaining_tables.is_empty() {
+            return Err(FireupError::TypeMapping(
+                "Circular dependency detected in table relationships".to_string()
+            ));
+        }
+    }
+
+    Ok(ordered)
+}
+
 /// Result of document transformation process
 #[derive(Debug, Clone)]
 pub struct TransformationResult {
@@ -77,6 +113,8 @@ pub struct DocumentTransformer {
     config: TransformationConfig,
     /// Cache for generated UUIDs to maintain consistency
     id_cache: HashMap<String, Uuid>,
+    /// Keys already inserted into each lookup table
+    lookup_keys: HashMap<String, HashSet<String>>,
 }
 
 impl DocumentTransformer {
@@ -86,6 +124,7 @@ impl DocumentTransformer {
             type_mapper: DataTypeMapper::new(),
             config: TransformationConfig::default(),
             id_cache: HashMap::new(),
+            lookup_keys: HashMap::new(),
         }
     }
 
@@ -95,6 +134,7 @@ impl DocumentTransformer {
             type_mapper: DataTypeMapper::new(),
             config,
             id_cache: HashMap::new(),
+            lookup_keys: HashMap::new(),
         }
     }
 
@@ -104,6 +144,7 @@ impl DocumentTransformer {
             type_mapper,
             config,
             id_cache: HashMap::new(),
+            lookup_keys: HashMap::new(),
         }
     }
 
@@ -168,22 +209,31 @@ impl DocumentTransformer {
     ) -> Result<Vec<String>, FireupError> {
         let mut warnings = Vec::new();
 
-        // Find the main table for this document's collection
+        // Find the main table for this document's collection, preferring the
+        // qualified name for documents in subcollections
+        let qualified_collection = document.qualified_collection();
         let main_table = schema.tables.iter()
-            .find(|t| t.name == document.collection || t.name == format!("{}_main", document.collection))
+            .find(|t| t.name == qualified_collection)
+            .or_else(|| schema.tables.iter()
+                .find(|t| t.name == document.collection || t.name == format!("{}_main", document.collection)))
             .ok_or_else(|| FireupError::TypeMapping(
-                format!("No table found for collection: {}", document.collection)
+                format!("No table found for collection: {}", qualified_collection)
             ))?;
 
         // Transform the main document
         let main_row = self.transform_document_to_row(document, main_table, &mut warnings)?;
         
+        // Fields extracted into lookup tables keyed by one of the row's columns
+        self.transform_lookup_rows(document, main_table, &main_row, schema, table_data, &mut warnings)?;
+        
+        // Rows extracted from the document reference it by its primary key
+        let parent_key = main_row.primary_key.clone();
         if let Some(rows) = table_data.get_mut(&main_table.name) {
             rows.push(main_row);
         }
 
         // Handle nested structures and arrays that require normalization
-        self.transform_nested_structures(document, schema, table_data, &mut warnings)?;
+        self.transform_nested_structures(document, &parent_key, schema, table_data, &mut warnings)?;
 
         // Handle subcollections
         for subcollection in &document.subcollections {
@@ -206,9 +256,9 @@ impl DocumentTransformer {
 
         // Generate or use existing primary key
         let primary_key = if document.data.contains_key("id") {
-            document.data["id"].clone()
+            document.data["id"].to_json()
         } else if self.config.generate_missing_ids {
-            let uuid = self.get_or_generate_uuid(&document.id);
+            let uuid = self.get_or_generate_uuid(&document.document_path());
             Value::String(uuid.to_string())
         } else {
             Value::String(document.id.clone())
@@ -221,6 +271,14 @@ impl DocumentTransformer {
             }
         }
 
+        // Subcollection documents link to their parent document's row, found
+        // by its full path since IDs are only unique within a collection
+        let parent_link = document.parent_reference()
+            .zip(document.metadata.parent_path.as_deref())
+            .map(|((parent_collection, _), parent_path)| {
+                (format!("{}_id", parent_collection), relative_document_path(parent_path).to_string())
+            });
+
         // Transform each field according to the table schema
         for column in &table.columns {
             if let Some(ref pk) = table.primary_key {
@@ -229,12 +287,20 @@ impl DocumentTransformer {
                 }
             }
 
-            let field_value = self.extract_field_value(&document.data, &column.name, &column.column_type)?;
+            let field_value = match &parent_link {
+                Some((parent_column, parent_path)) if *parent_column == column.name => {
+                    Some(FirestoreValue::String(parent_path.clone()))
+                }
+                _ => self.extract_field_value(&document.data, &column.name, &column.column_type)?,
+            };
             
             match field_value {
                 Some(value) => {
-                    // Check if this is a foreign key
-                    if let Some(fk) = table.foreign_keys.iter().find(|fk| fk.column == column.name) {
+                    // Check if this is a foreign key to a document table; keys
+                    // of lookup tables keep their values
+                    if let Some(fk) = table.foreign_keys.iter()
+                        .find(|fk| fk.column == column.name && fk.referenced_column == "id")
+                    {
                         let fk_value = self.transform_foreign_key_value(&value, &fk.referenced_table)?;
                         foreign_keys.insert(column.name.clone(), fk_value.clone());
                         columns.insert(column.name.clone(), fk_value);
@@ -267,13 +333,58 @@ impl DocumentTransformer {
         })
     }
 
+    /// Add a row to each lookup table a row references by a column other
+    /// than `id`, the first time its key value is seen, and in turn to the
+    /// lookup tables that lookup table references
+    fn transform_lookup_rows(
+        &mut self,
+        document: &FirestoreDocument,
+        table: &TableDefinition,
+        row: &TableRow,
+        schema: &NormalizedSchema,
+        table_data: &mut HashMap<String, Vec<TableRow>>,
+        warnings: &mut Vec<String>,
+    ) -> Result<(), FireupError> {
+        for fk in table.foreign_keys.iter().filter(|fk| fk.referenced_column != "id") {
+            let Some(key) = row.columns.get(&fk.column).filter(|key| !key.is_null()) else {
+                continue;
+            };
+            let Some(lookup_table) = schema.tables.iter().find(|t| t.name == fk.referenced_table) else {
+                continue;
+            };
+            if !self.lookup_keys.entry(lookup_table.name.clone()).or_default().insert(key.to_string()) {
+                continue;
+            }
+
+            let mut columns = HashMap::from([(fk.referenced_column.clone(), key.clone())]);
+            for column in lookup_table.columns.iter().filter(|c| c.name != fk.referenced_column) {
+                let value = match self.extract_field_value(&document.data, &column.name, &column.column_type)? {
+                    Some(value) => self.transform_value_for_column(&value, column, warnings)?,
+                    None => Value::Null,
+                };
+                columns.insert(column.name.clone(), value);
+            }
+            let lookup_row = TableRow {
+                columns,
+                primary_key: key.clone(),
+                foreign_keys: HashMap::new(),
+            };
+            self.transform_lookup_rows(document, lookup_table, &lookup_row, schema, table_data, warnings)?;
+            if let Some(rows) = table_data.get_mut(&lookup_table.name) {
+                rows.push(lookup_row);
+            }
+        }
+
+        Ok(())
+    }
+
     /// Extract field value from document data, handling nested paths
     fn extract_field_value(
         &self,
-        data: &HashMap<String, Value>,
+        data: &HashMap<String, FirestoreValue>,
         field_path: &str,
         _expected_type: &PostgreSQLType,
-    ) -> Result<Option<Value>, FireupError> {
+    ) -> Result<Option<FirestoreValue>, FireupError> {
         let path_parts: Vec<&str> = field_path.split('.').collect();
         
         if path_parts.len() == 1 {
@@ -285,10 +396,10 @@ impl DocumentTransformer {
             
             for part in &path_parts[1..] {
                 match current_value {
-                    Some(Value::Object(obj)) => {
+                    Some(FirestoreValue::Map(obj)) => {
                         current_value = obj.get(*part);
                     }
-                    Some(Value::Array(arr)) => {
+                    Some(FirestoreValue::Array(arr)) => {
                         // Handle array index access
                         if let Ok(index) = part.parse::<usize>() {
                             current_value = arr.get(index);
@@ -307,22 +418,29 @@ impl DocumentTransformer {
     /// Transform a value to match the expected column type
     fn transform_value_for_column(
         &self,
-        value: &Value,
+        value: &FirestoreValue,
         column: &ColumnDefinition,
         warnings: &mut Vec<String>,
     ) -> Result<Value, FireupError> {
         match (&column.column_type, value) {
             // Direct type matches
-            (PostgreSQLType::Boolean, Value::Bool(_)) => Ok(value.clone()),
-            (PostgreSQLType::Integer, Value::Number(n)) if n.is_i64() => Ok(value.clone()),
-            (PostgreSQLType::BigInt, Value::Number(n)) if n.is_i64() => Ok(value.clone()),
-            (PostgreSQLType::Numeric(_, _), Value::Number(_)) => Ok(value.clone()),
-            (PostgreSQLType::Text, Value::String(_)) => Ok(value.clone()),
-            (PostgreSQLType::Varchar(_), Value::String(_)) => Ok(value.clone()),
-            (PostgreSQLType::Jsonb, _) => Ok(value.clone()),
+            (PostgreSQLType::Boolean, FirestoreValue::Boolean(_)) => Ok(value.to_json()),
+            (PostgreSQLType::Integer, FirestoreValue::Integer(_)) => Ok(value.to_json()),
+            (PostgreSQLType::BigInt, FirestoreValue::Integer(_)) => Ok(value.to_json()),
+            (PostgreSQLType::Numeric(_, _), FirestoreValue::Integer(_) | FirestoreValue::Double(_)) => Ok(value.to_json()),
+            (PostgreSQLType::Text, FirestoreValue::String(_) | FirestoreValue::Reference(_)) => Ok(value.to_json()),
+            (PostgreSQLType::Varchar(_), FirestoreValue::String(_) | FirestoreValue::Reference(_)) => Ok(value.to_json()),
+            (PostgreSQLType::Jsonb, _) => Ok(value.to_json()),
+            // Keep the full nanosecond precision; PostgreSQL rounds to microseconds
+            (PostgreSQLType::Timestamp, FirestoreValue::Timestamp(_)) => Ok(value.to_json()),
+            // PostgreSQL hex format for BYTEA input
+            (PostgreSQLType::Bytea, FirestoreValue::Bytes(bytes)) => {
+                let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+                Ok(Value::String(format!("\\x{}", hex)))
+            }
             
             // Type conversions
-            (PostgreSQLType::Uuid, Value::String(s)) => {
+            (PostgreSQLType::Uuid, FirestoreValue::String(s)) => {
                 if let Ok(uuid) = Uuid::parse_str(s) {
                     Ok(Value::String(uuid.to_string()))
                 } else {
@@ -331,18 +449,18 @@ impl DocumentTransformer {
                 }
             }
             
-            (PostgreSQLType::Timestamp, Value::String(s)) => {
+            (PostgreSQLType::Timestamp, FirestoreValue::String(s)) => {
                 // Try to parse timestamp string
                 if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
                     Ok(Value::String(dt.to_rfc3339()))
                 } else {
                     warnings.push(format!("Invalid timestamp format: {}", s));
-                    Ok(value.clone())
+                    Ok(value.to_json())
                 }
             }
             
             // String to number conversions
-            (PostgreSQLType::Integer, Value::String(s)) => {
+            (PostgreSQLType::Integer, FirestoreValue::String(s)) => {
                 if let Ok(num) = s.parse::<i32>() {
                     Ok(Value::Number(serde_json::Number::from(num)))
                 } else {
@@ -352,7 +470,7 @@ impl DocumentTransformer {
             }
             
             // Array handling
-            (PostgreSQLType::Array(_), Value::Array(_)) => Ok(value.clone()),
+            (PostgreSQLType::Array(_), FirestoreValue::Array(_)) => Ok(value.to_json()),
             
             // Fallback to JSONB for complex types
             _ => {
@@ -360,76 +478,89 @@ impl DocumentTransformer {
                     "Type mismatch for column '{}': expected {:?}, got {:?}. Using JSONB.",
                     column.name, column.column_type, value
                 ));
-                Ok(value.clone())
+                Ok(value.to_json())
             }
         }
     }
 
-    /// Transform foreign key value to appropriate format
+    /// Transform foreign key value to appropriate format. References and
+    /// paths resolve to the UUID of the document at that path; bare IDs to
+    /// the document of that ID in the referenced table.
     pub fn transform_foreign_key_value(
         &mut self,
-        value: &Value,
-        _referenced_table: &str,
+        value: &FirestoreValue,
+        referenced_table: &str,
     ) -> Result<Value, FireupError> {
         match value {
-            Value::String(s) => {
-                // Check if it's a Firestore reference path
-                if s.contains("/documents/") {
-                    // Extract document ID from reference path
-                    let parts: Vec<&str> = s.split('/').collect();
-                    if let Some(doc_id) = parts.last() {
-                        let uuid = self.get_or_generate_uuid(doc_id);
-                        Ok(Value::String(uuid.to_string()))
-                    } else {
-                        Err(FireupError::TypeMapping(format!("Invalid reference format: {}", s)))
+            FirestoreValue::String(s) | FirestoreValue::Reference(s) => {
+                // Check if it's a Firestore reference path; document IDs
+                // cannot contain '/', so any string with one is a path
+                if s.contains('/') || matches!(value, FirestoreValue::Reference(_)) {
+                    let path = relative_document_path(s.trim_start_matches('/'));
+                    if path.is_empty() {
+                        return Err(FireupError::TypeMapping(format!("Invalid reference format: {}", s)));
                     }
+                    let uuid = self.get_or_generate_uuid(path);
+                    Ok(Value::String(uuid.to_string()))
                 } else if Uuid::parse_str(s).is_ok() {
                     // Already a UUID
-                    Ok(value.clone())
+                    Ok(Value::String(s.clone()))
                 } else {
                     // Generate UUID for string ID
-                    let uuid = self.get_or_generate_uuid(s);
+                    let uuid = self.get_or_generate_uuid(&format!("{}/{}", referenced_table, s));
                     Ok(Value::String(uuid.to_string()))
                 }
             }
             _ => {
                 // Convert other types to string and generate UUID
-                let string_val = value.to_string();
-                let uuid = self.get_or_generate_uuid(&string_val);
+                let string_val = match value.to_json() {
+                    Value::String(s) => s,
+                    other => other.to_string(),
+                };
+                let uuid = self.get_or_generate_uuid(&format!("{}/{}", referenced_table, string_val));
                 Ok(Value::String(uuid.to_string()))
             }
         }
     }
 
-    /// Handle nested structures that require normalization
+    /// Handle nested structures that require normalization. `parent_key` is
+    /// the primary key of the document's own row.
     fn transform_nested_structures(
         &mut self,
         document: &FirestoreDocument,
+        parent_key: &Value,
         schema: &NormalizedSchema,
         table_data: &mut HashMap<String, Vec<TableRow>>,
         warnings: &mut Vec<String>,
     ) -> Result<(), FireupError> {
-        // Find tables that are normalized from this document's collection
+        // Find tables that are normalized from this document's collection;
+        // subcollection tables are named after the qualified collection
+        let parent_table = document.qualified_collection();
+        let prefix = format!("{}_", parent_table);
         let normalized_tables: Vec<&TableDefinition> = schema.tables.iter()
-            .filter(|t| t.name.starts_with(&format!("{}_", document.collection)) && 
-                       t.name != document.collection)
+            .filter(|t| t.name.starts_with(&prefix))
             .collect();
 
         for table in normalized_tables {
             // Extract the field name from the table name
-            let field_name = table.name.strip_prefix(&format!("{}_", document.collection))
+            let field_name = table.name.strip_prefix(&prefix)
                 .unwrap_or(&table.name);
 
             if let Some(field_value) = document.data.get(field_name) {
                 match field_value {
-                    Value::Array(arr) => {
+                    FirestoreValue::Array(arr) if is_junction_table(table) => {
+                        self.transform_array_to_junction_table(
+                            arr, table, document, parent_key, table_data, warnings
+                        )?;
+                    }
+                    FirestoreValue::Array(arr) => {
                         self.transform_array_to_normalized_table(
-                            arr, table, &document.id, table_data, warnings
+                            arr, table, document, parent_key, table_data, warnings
                         )?;
                     }
-                    Value::Object(obj) => {
+                    FirestoreValue::Map(obj) => {
                         self.transform_object_to_normalized_table(
-                            obj, table, &document.id, table_data, warnings
+                            obj, table, document, parent_key, table_data, warnings
                         )?;
                     }
                     _ => {
@@ -448,13 +579,15 @@ impl DocumentTransformer {
     /// Transform an array into a normalized table
     fn transform_array_to_normalized_table(
         &mut self,
-        array: &[Value],
+        array: &[FirestoreValue],
         table: &TableDefinition,
-        parent_id: &str,
+        parent: &FirestoreDocument,
+        parent_key: &Value,
         table_data: &mut HashMap<String, Vec<TableRow>>,
         warnings: &mut Vec<String>,
     ) -> Result<(), FireupError> {
-        let parent_uuid = self.get_or_generate_uuid(parent_id);
+        let parent_table = parent.qualified_collection();
+        let parent_id = parent.document_path();
 
         for (index, item) in array.iter().enumerate() {
             let mut columns = HashMap::new();
@@ -471,14 +604,14 @@ impl DocumentTransformer {
             }
 
             // Add parent foreign key
-            if let Some(parent_fk) = table.foreign_keys.iter().find(|fk| fk.referenced_column == "id") {
-                foreign_keys.insert(parent_fk.column.clone(), Value::String(parent_uuid.to_string()));
-                columns.insert(parent_fk.column.clone(), Value::String(parent_uuid.to_string()));
+            if let Some(parent_fk) = table.foreign_keys.iter().find(|fk| fk.referenced_table == parent_table) {
+                foreign_keys.insert(parent_fk.column.clone(), parent_key.clone());
+                columns.insert(parent_fk.column.clone(), parent_key.clone());
             }
 
             // Transform the array item based on its type
             match item {
-                Value::Object(obj) => {
+                FirestoreValue::Map(obj) => {
                     // Object array item - map each field to table columns
                     for column in &table.columns {
                         let is_primary_key = table.primary_key.as_ref()
@@ -517,16 +650,73 @@ impl DocumentTransformer {
         Ok(())
     }
 
+    /// Fill a many-to-many junction table with one row per distinct document
+    /// an array of references points at
+    fn transform_array_to_junction_table(
+        &mut self,
+        array: &[FirestoreValue],
+        table: &TableDefinition,
+        parent: &FirestoreDocument,
+        parent_key: &Value,
+        table_data: &mut HashMap<String, Vec<TableRow>>,
+        warnings: &mut Vec<String>,
+    ) -> Result<(), FireupError> {
+        let parent_table = parent.qualified_collection();
+        let parent_id = parent.document_path();
+
+        // The owning side comes first, so an array referencing its own
+        // collection still tells the two keys apart
+        let parent_fk = table.foreign_keys.iter().find(|fk| fk.referenced_table == parent_table);
+        let target_fk = table.foreign_keys.iter()
+            .find(|fk| parent_fk.is_some_and(|parent_fk| fk.column != parent_fk.column));
+        let (Some(parent_fk), Some(target_fk)) = (parent_fk, target_fk) else {
+            return Err(FireupError::TypeMapping(format!(
+                "Junction table {} has no foreign keys to {} and another table", table.name, parent_table
+            )));
+        };
+
+        let mut targets = HashSet::new();
+        for item in array {
+            if !matches!(item, FirestoreValue::Reference(_) | FirestoreValue::String(_)) {
+                warnings.push(format!(
+                    "Skipping non-reference element {:?} of {} for document '{}'", item, table.name, parent_id
+                ));
+                continue;
+            }
+            let target_uuid = self.transform_foreign_key_value(item, &target_fk.referenced_table)?;
+            if !targets.insert(target_uuid.to_string()) {
+                continue;
+            }
+
+            let keys = HashMap::from([
+                (parent_fk.column.clone(), parent_key.clone()),
+                (target_fk.column.clone(), target_uuid.clone()),
+            ]);
+            let row = TableRow {
+                columns: keys.clone(),
+                primary_key: Value::Array(vec![parent_key.clone(), target_uuid]),
+                foreign_keys: keys,
+            };
+            if let Some(rows) = table_data.get_mut(&table.name) {
+                rows.push(row);
+            }
+        }
+
+        Ok(())
+    }
+
     /// Transform an object into a normalized table
     fn transform_object_to_normalized_table(
         &mut self,
-        object: &Map<String, Value>,
+        object: &HashMap<String, FirestoreValue>,
         table: &TableDefinition,
-        parent_id: &str,
+        parent: &FirestoreDocument,
+        parent_key: &Value,
         table_data: &mut HashMap<String, Vec<TableRow>>,
         warnings: &mut Vec<String>,
     ) -> Result<(), FireupError> {
-        let parent_uuid = self.get_or_generate_uuid(parent_id);
+        let parent_table = parent.qualified_collection();
+        let parent_id = parent.document_path();
         let mut columns = HashMap::new();
         let mut foreign_keys = HashMap::new();
 
@@ -540,9 +730,9 @@ impl DocumentTransformer {
         }
 
         // Add parent foreign key
-        if let Some(parent_fk) = table.foreign_keys.iter().find(|fk| fk.referenced_column == "id") {
-            foreign_keys.insert(parent_fk.column.clone(), Value::String(parent_uuid.to_string()));
-            columns.insert(parent_fk.column.clone(), Value::String(parent_uuid.to_string()));
+        if let Some(parent_fk) = table.foreign_keys.iter().find(|fk| fk.referenced_table == parent_table) {
+            foreign_keys.insert(parent_fk.column.clone(), parent_key.clone());
+            columns.insert(parent_fk.column.clone(), parent_key.clone());
         }
 
         // Map object fields to table columns
@@ -582,37 +772,11 @@ impl DocumentTransformer {
         let mut statements = Vec::new();
 
         // Generate statements in dependency order (tables with no foreign keys first)
-        let mut processed_tables = HashSet::new();
-        let mut remaining_tables: Vec<&TableDefinition> = schema.tables.iter().collect();
-
-        while !remaining_tables.is_empty() {
-            let mut progress_made = false;
-
-            remaining_tables.retain(|table| {
-                // Check if all referenced tables have been processed
-                let can_process = table.foreign_keys.iter().all(|fk| {
-                    processed_tables.contains(&fk.referenced_table) || fk.referenced_table == table.name
-                });
-
-                if can_process {
-                    if let Some(rows) = table_data.get(&table.name) {
-                        if !rows.is_empty() {
-                            let statement = self.generate_insert_statement(table, rows);
-                            statements.push(statement);
-                        }
-                    }
-                    processed_tables.insert(table.name.clone());
-                    progress_made = true;
-                    false // Remove from remaining_tables
-                } else {
-                    true // Keep in remaining_tables
+        for table in tables_in_dependency_order(schema)? {
+            if let Some(rows) = table_data.get(&table.name) {
+                if !rows.is_empty() {
+                    statements.push(self.generate_insert_statement(table, rows));
                 }
-            });
-
-            if !progress_made && !remaining_tables.is_empty() {
-                return Err(FireupError::TypeMapping(
-                    "Circular dependency detected in table relationships".to_string()
-                ));
             }
         }
 
@@ -662,7 +826,9 @@ impl DocumentTransformer {
         }
     }
 
-    /// Get or generate a consistent UUID for a given ID
+    /// Get or generate a consistent UUID for a given key: a document path
+    /// (`users/u1/orders/o1`) for documents, or a path-derived key for rows
+    /// extracted from a document
     fn get_or_generate_uuid(&mut self, id: &str) -> Uuid {
         if let Some(uuid) = self.id_cache.get(id) {
             *uuid
@@ -674,6 +840,15 @@ impl DocumentTransformer {
     }
 }
 
+/// Whether a table is a junction table: its primary key is made of two or
+/// more columns, all of them foreign keys
+fn is_junction_table(table: &TableDefinition) -> bool {
+    table.primary_key.as_ref().is_some_and(|pk| {
+        pk.columns.len() > 1
+            && pk.columns.iter().all(|column| table.foreign_keys.iter().any(|fk| fk.column == *column))
+    })
+}
+
 impl Default for DocumentTransformer {
     fn default() -> Self {
         Self::new()
@@ -774,7 +949,7 @@ mod tests {
         // Test Firestore reference transformation
         let reference = "projects/test/databases/(default)/documents/users/user123";
         let result = transformer.transform_foreign_key_value(
-            &json!(reference), 
+            &FirestoreValue::Reference(reference.to_string()), 
             "users"
         ).unwrap();
 
@@ -792,7 +967,7 @@ mod tests {
 
         let column = ColumnDefinition::new("age".to_string(), PostgreSQLType::Integer);
         let result = transformer.transform_value_for_column(
-            &json!("not_a_number"), 
+            &json!("not_a_number").into(), 
             &column, 
             &mut warnings
         ).unwrap();
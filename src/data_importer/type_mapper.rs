@@ -1,9 +1,7 @@
 use crate::error::FireupError;
-use crate::types::PostgreSQLType;
-use serde_json::Value;
+use crate::types::{FirestoreValue, PostgreSQLType};
 use std::collections::HashMap;
 use uuid::Uuid;
-use chrono::DateTime;
 
 /// Maps Firestore data types to appropriate PostgreSQL types
 pub struct DataTypeMapper {
@@ -81,7 +79,7 @@ impl DataTypeMapper {
     }
 
     /// Map a Firestore value to a PostgreSQL type
-    pub fn map_value_type(&self, value: &Value, field_path: &str) -> Result<TypeMappingResult, FireupError> {
+    pub fn map_value_type(&self, value: &FirestoreValue, field_path: &str) -> Result<TypeMappingResult, FireupError> {
         // Check for custom mappings first
         if let Some(custom_type) = self.custom_mappings.get(field_path) {
             return Ok(TypeMappingResult {
@@ -100,9 +98,9 @@ impl DataTypeMapper {
     }
 
     /// Internal method to map value types
-    fn map_value_type_internal(&self, value: &Value, field_path: &str) -> Result<TypeMappingResult, FireupError> {
+    fn map_value_type_internal(&self, value: &FirestoreValue, field_path: &str) -> Result<TypeMappingResult, FireupError> {
         match value {
-            Value::Null => Ok(TypeMappingResult {
+            FirestoreValue::Null => Ok(TypeMappingResult {
                 postgres_type: PostgreSQLType::Text,
                 requires_normalization: false,
                 metadata: TypeMappingMetadata {
@@ -113,7 +111,7 @@ impl DataTypeMapper {
                 },
             }),
 
-            Value::Bool(_) => Ok(TypeMappingResult {
+            FirestoreValue::Boolean(_) => Ok(TypeMappingResult {
                 postgres_type: PostgreSQLType::Boolean,
                 requires_normalization: false,
                 metadata: TypeMappingMetadata {
@@ -124,38 +122,13 @@ impl DataTypeMapper {
                 },
             }),
 
-            Value::Number(n) => {
-                if n.is_i64() {
-                    let int_val = n.as_i64().unwrap();
-                    if int_val >= i32::MIN as i64 && int_val <= i32::MAX as i64 {
-                        Ok(TypeMappingResult {
-                            postgres_type: PostgreSQLType::Integer,
-                            requires_normalization: false,
-                            metadata: TypeMappingMetadata {
-                                original_type: "integer".to_string(),
-                                confidence: 1.0,
-                                warnings: vec![],
-                                inferred: false,
-                            },
-                        })
-                    } else {
-                        Ok(TypeMappingResult {
-                            postgres_type: PostgreSQLType::BigInt,
-                            requires_normalization: false,
-                            metadata: TypeMappingMetadata {
-                                original_type: "bigint".to_string(),
-                                confidence: 1.0,
-                                warnings: vec![],
-                                inferred: false,
-                            },
-                        })
-                    }
-                } else if n.is_f64() {
+            FirestoreValue::Integer(int_val) => {
+                if *int_val >= i32::MIN as i64 && *int_val <= i32::MAX as i64 {
                     Ok(TypeMappingResult {
-                        postgres_type: PostgreSQLType::Numeric(Some(15), Some(6)),
+                        postgres_type: PostgreSQLType::Integer,
                         requires_normalization: false,
                         metadata: TypeMappingMetadata {
-                            original_type: "float".to_string(),
+                            original_type: "integer".to_string(),
                             confidence: 1.0,
                             warnings: vec![],
                             inferred: false,
@@ -163,20 +136,75 @@ impl DataTypeMapper {
                     })
                 } else {
                     Ok(TypeMappingResult {
-                        postgres_type: PostgreSQLType::Numeric(None, None),
+                        postgres_type: PostgreSQLType::BigInt,
                         requires_normalization: false,
                         metadata: TypeMappingMetadata {
-                            original_type: "number".to_string(),
-                            confidence: 0.8,
-                            warnings: vec!["Unknown number format, using generic NUMERIC".to_string()],
-                            inferred: true,
+                            original_type: "bigint".to_string(),
+                            confidence: 1.0,
+                            warnings: vec![],
+                            inferred: false,
                         },
                     })
                 }
             },
 
-            Value::String(s) => {
-                // Check for special string formats
+            FirestoreValue::Double(_) => Ok(TypeMappingResult {
+                postgres_type: PostgreSQLType::Numeric(Some(15), Some(6)),
+                requires_normalization: false,
+                metadata: TypeMappingMetadata {
+                    original_type: "float".to_string(),
+                    confidence: 1.0,
+                    warnings: vec![],
+                    inferred: false,
+                },
+            }),
+
+            FirestoreValue::Timestamp(_) => Ok(TypeMappingResult {
+                postgres_type: PostgreSQLType::Timestamp,
+                requires_normalization: false,
+                metadata: TypeMappingMetadata {
+                    original_type: "timestamp".to_string(),
+                    confidence: 1.0,
+                    warnings: vec![],
+                    inferred: false,
+                },
+            }),
+
+            FirestoreValue::Bytes(_) => Ok(TypeMappingResult {
+                postgres_type: PostgreSQLType::Bytea,
+                requires_normalization: false,
+                metadata: TypeMappingMetadata {
+                    original_type: "bytes".to_string(),
+                    confidence: 1.0,
+                    warnings: vec![],
+                    inferred: false,
+                },
+            }),
+
+            FirestoreValue::Reference(_) => Ok(TypeMappingResult {
+                postgres_type: PostgreSQLType::Uuid,
+                requires_normalization: false,
+                metadata: TypeMappingMetadata {
+                    original_type: "reference".to_string(),
+                    confidence: 1.0,
+                    warnings: vec!["Firestore reference mapped to UUID".to_string()],
+                    inferred: false,
+                },
+            }),
+
+            FirestoreValue::GeoPoint { .. } => Ok(TypeMappingResult {
+                postgres_type: PostgreSQLType::Jsonb,
+                requires_normalization: false,
+                metadata: TypeMappingMetadata {
+                    original_type: "geopoint".to_string(),
+                    confidence: 1.0,
+                    warnings: vec!["Geo point stored as JSONB with latitude and longitude".to_string()],
+                    inferred: false,
+                },
+            }),
+
+            FirestoreValue::String(s) => {
+                // Strings keep their type; only canonical UUIDs are refined
                 if self.is_uuid_string(s) {
                     Ok(TypeMappingResult {
                         postgres_type: PostgreSQLType::Uuid,
@@ -188,28 +216,6 @@ impl DataTypeMapper {
                             inferred: true,
                         },
                     })
-                } else if self.is_timestamp_string(s) {
-                    Ok(TypeMappingResult {
-                        postgres_type: PostgreSQLType::Timestamp,
-                        requires_normalization: false,
-                        metadata: TypeMappingMetadata {
-                            original_type: "timestamp_string".to_string(),
-                            confidence: 0.9,
-                            warnings: vec!["String appears to be timestamp, consider parsing".to_string()],
-                            inferred: true,
-                        },
-                    })
-                } else if self.is_reference_string(s) {
-                    Ok(TypeMappingResult {
-                        postgres_type: PostgreSQLType::Uuid,
-                        requires_normalization: false,
-                        metadata: TypeMappingMetadata {
-                            original_type: "reference".to_string(),
-                            confidence: 0.8,
-                            warnings: vec!["Firestore reference mapped to UUID".to_string()],
-                            inferred: true,
-                        },
-                    })
                 } else {
                     // Regular string - determine appropriate VARCHAR length or use TEXT
                     let postgres_type = if s.len() <= 255 {
@@ -233,7 +239,7 @@ impl DataTypeMapper {
                 }
             },
 
-            Value::Array(arr) => {
+            FirestoreValue::Array(arr) => {
                 if arr.is_empty() {
                     return Ok(TypeMappingResult {
                         postgres_type: PostgreSQLType::Jsonb,
@@ -307,7 +313,7 @@ impl DataTypeMapper {
                 }
             },
 
-            Value::Object(obj) => {
+            FirestoreValue::Map(obj) => {
                 if obj.is_empty() {
                     return Ok(TypeMappingResult {
                         postgres_type: PostgreSQLType::Jsonb,
@@ -352,7 +358,7 @@ impl DataTypeMapper {
     }
 
     /// Analyze the types of elements in an array
-    fn analyze_array_element_types(&self, arr: &[Value], field_path: &str) -> Result<Vec<TypeMappingResult>, FireupError> {
+    fn analyze_array_element_types(&self, arr: &[FirestoreValue], field_path: &str) -> Result<Vec<TypeMappingResult>, FireupError> {
         let mut unique_types = HashMap::new();
         
         for (i, element) in arr.iter().enumerate() {
@@ -366,7 +372,7 @@ impl DataTypeMapper {
     }
 
     /// Determine if an object should be normalized to a separate table
-    fn should_normalize_object(&self, obj: &serde_json::Map<String, Value>) -> bool {
+    fn should_normalize_object(&self, obj: &HashMap<String, FirestoreValue>) -> bool {
         // Normalize if object has more than 3 fields or contains nested objects/arrays
         if obj.len() > 3 {
             return true;
@@ -374,7 +380,7 @@ impl DataTypeMapper {
 
         for value in obj.values() {
             match value {
-                Value::Object(_) | Value::Array(_) => return true,
+                FirestoreValue::Map(_) | FirestoreValue::Array(_) => return true,
                 _ => continue,
             }
         }
@@ -387,40 +393,16 @@ impl DataTypeMapper {
         Uuid::parse_str(s).is_ok()
     }
 
-    /// Check if a string looks like a timestamp
-    fn is_timestamp_string(&self, s: &str) -> bool {
-        // Try parsing common timestamp formats
-        DateTime::parse_from_rfc3339(s).is_ok() ||
-        DateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").is_ok() ||
-        DateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.fZ").is_ok()
-    }
-
-    /// Check if a string looks like a Firestore reference
-    fn is_reference_string(&self, s: &str) -> bool {
-        // Firestore references typically follow pattern: projects/{project}/databases/{database}/documents/{collection}/{document}
-        s.starts_with("projects/") && s.contains("/databases/") && s.contains("/documents/")
-    }
-
     /// Detect the Firestore type name for a value
-    fn detect_firestore_type(&self, value: &Value) -> String {
+    fn detect_firestore_type(&self, value: &FirestoreValue) -> String {
         match value {
-            Value::Null => "null".to_string(),
-            Value::Bool(_) => "boolean".to_string(),
-            Value::Number(n) => {
-                if n.is_i64() {
-                    "integer".to_string()
-                } else {
-                    "number".to_string()
-                }
-            },
-            Value::String(_) => "string".to_string(),
-            Value::Array(_) => "array".to_string(),
-            Value::Object(_) => "map".to_string(),
+            FirestoreValue::Map(_) => "map".to_string(),
+            _ => value.type_name().to_string(),
         }
     }
 
     /// Map multiple values to determine the best common type
-    pub fn map_multiple_values(&self, values: &[&Value], field_path: &str) -> Result<TypeMappingResult, FireupError> {
+    pub fn map_multiple_values(&self, values: &[&FirestoreValue], field_path: &str) -> Result<TypeMappingResult, FireupError> {
         if values.is_empty() {
             return Err(FireupError::TypeMapping("No values provided for type mapping".to_string()));
         }
@@ -513,15 +495,15 @@ mod tests {
         let mapper = DataTypeMapper::new();
 
         // Test boolean
-        let result = mapper.map_value_type(&json!(true), "test.bool").unwrap();
+        let result = mapper.map_value_type(&json!(true).into(), "test.bool").unwrap();
         assert!(matches!(result.postgres_type, PostgreSQLType::Boolean));
 
         // Test integer
-        let result = mapper.map_value_type(&json!(42), "test.int").unwrap();
+        let result = mapper.map_value_type(&json!(42).into(), "test.int").unwrap();
         assert!(matches!(result.postgres_type, PostgreSQLType::Integer));
 
         // Test string
-        let result = mapper.map_value_type(&json!("hello"), "test.string").unwrap();
+        let result = mapper.map_value_type(&json!("hello").into(), "test.string").unwrap();
         assert!(matches!(result.postgres_type, PostgreSQLType::Varchar(_)));
     }
 
@@ -530,7 +512,7 @@ mod tests {
         let mapper = DataTypeMapper::new();
         let uuid_str = "550e8400-e29b-41d4-a716-446655440000";
         
-        let result = mapper.map_value_type(&json!(uuid_str), "test.uuid").unwrap();
+        let result = mapper.map_value_type(&json!(uuid_str).into(), "test.uuid").unwrap();
         assert!(matches!(result.postgres_type, PostgreSQLType::Uuid));
     }
 
@@ -539,11 +521,11 @@ mod tests {
         let mapper = DataTypeMapper::new();
         
         // Homogeneous array
-        let result = mapper.map_value_type(&json!([1, 2, 3]), "test.array").unwrap();
+        let result = mapper.map_value_type(&json!([1, 2, 3]).into(), "test.array").unwrap();
         assert!(matches!(result.postgres_type, PostgreSQLType::Array(_)));
 
         // Heterogeneous array
-        let result = mapper.map_value_type(&json!([1, "hello", true]), "test.mixed_array").unwrap();
+        let result = mapper.map_value_type(&json!([1, "hello", true]).into(), "test.mixed_array").unwrap();
         assert!(matches!(result.postgres_type, PostgreSQLType::Jsonb));
     }
 
@@ -552,7 +534,7 @@ mod tests {
         let mapper = DataTypeMapper::new();
         
         // Simple object
-        let result = mapper.map_value_type(&json!({"name": "test"}), "test.simple").unwrap();
+        let result = mapper.map_value_type(&json!({"name": "test"}).into(), "test.simple").unwrap();
         assert!(matches!(result.postgres_type, PostgreSQLType::Jsonb));
         assert!(!result.requires_normalization);
 
@@ -564,7 +546,7 @@ mod tests {
             "field4": "value4",
             "nested": {"inner": "value"}
         });
-        let result = mapper.map_value_type(&complex_obj, "test.complex").unwrap();
+        let result = mapper.map_value_type(&complex_obj.into(), "test.complex").unwrap();
         assert!(result.requires_normalization);
     }
 
@@ -573,16 +555,16 @@ mod tests {
         let mut mapper = DataTypeMapper::new();
         mapper.add_custom_mapping("user.id".to_string(), PostgreSQLType::Uuid);
 
-        let result = mapper.map_value_type(&json!("some-string"), "user.id").unwrap();
+        let result = mapper.map_value_type(&json!("some-string").into(), "user.id").unwrap();
         assert!(matches!(result.postgres_type, PostgreSQLType::Uuid));
     }
 
     #[test]
     fn test_multiple_values_mapping() {
         let mapper = DataTypeMapper::new();
-        let val1 = json!(42);
-        let val2 = json!(100);
-        let val3 = json!(999);
+        let val1 = FirestoreValue::from(json!(42));
+        let val2 = FirestoreValue::from(json!(100));
+        let val3 = FirestoreValue::from(json!(999));
         let values = vec![&val1, &val2, &val3];
 
         let result = mapper.map_multiple_values(&values, "test.numbers").unwrap();
@@ -592,9 +574,9 @@ mod tests {
     #[test]
     fn test_type_conflict_resolution() {
         let mapper = DataTypeMapper::new();
-        let val1 = json!(42);
-        let val2 = json!("hello");
-        let val3 = json!(true);
+        let val1 = FirestoreValue::from(json!(42));
+        let val2 = FirestoreValue::from(json!("hello"));
+        let val3 = FirestoreValue::from(json!(true));
         let values = vec![&val1, &val2, &val3];
 
         let result = mapper.map_multiple_values(&values, "test.mixed").unwrap();
@@ -0,0 +1,187 @@
+// Decoding of native Firestore export records (google.datastore.v1.Entity protobufs)
+use crate::error::{FireupError, ErrorContext};
+use crate::protos::google::datastore::v1::{key, value, Entity, Key, Value};
+use crate::types::{string_storage_size, DocumentMetadata, FirestoreDocument, FirestoreValue};
+use prost::Message;
+use std::collections::HashMap;
+use tracing::debug;
+
+/// Decodes `google.datastore.v1.Entity` protobufs into Firestore documents
+pub struct EntityDecoder;
+
+impl EntityDecoder {
+    /// Create a new entity decoder
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Decode a complete log record as an entity.
+    ///
+    /// Returns `Ok(None)` when the bytes decode but do not describe a keyed
+    /// entity, which is how non-document records (and most non-protobuf
+    /// payloads) surface.
+    pub fn decode(&self, record_data: &[u8], record_index: usize) -> Result<Option<FirestoreDocument>, FireupError> {
+        let entity = Entity::decode(record_data).map_err(|e| FireupError::leveldb_parse(
+            format!("Failed to decode entity protobuf: {}", e),
+            ErrorContext {
+                operation: "decode_entity".to_string(),
+                metadata: HashMap::from([
+                    ("record_index".to_string(), record_index.to_string()),
+                    ("record_size".to_string(), record_data.len().to_string()),
+                ]),
+                timestamp: chrono::Utc::now(),
+                call_path: vec!["leveldb_parser::entity_decoder::EntityDecoder".to_string()],
+            }
+        ))?;
+
+        Ok(self.entity_to_document(&entity))
+    }
+
+    /// Convert a decoded entity into a Firestore document
+    pub fn entity_to_document(&self, entity: &Entity) -> Option<FirestoreDocument> {
+        let key = entity.key.as_ref()?;
+        let last = key.path.last()?;
+        if last.kind.is_empty() {
+            return None;
+        }
+        let doc_id = Self::path_element_id(last)?;
+
+        let data = entity.properties.iter()
+            .map(|(name, value)| (name.clone(), self.convert_value(value)))
+            .collect();
+
+        let mut document = FirestoreDocument {
+            id: doc_id,
+            collection: last.kind.clone(),
+            data,
+            subcollections: Vec::new(),
+            metadata: DocumentMetadata {
+                created_at: None,
+                updated_at: None,
+                path: Self::key_path(key),
+                parent_path: Self::parent_key_path(key),
+                namespace: Self::namespace(key),
+                project: key.partition_id.as_ref()
+                    .map(|partition| partition.project_id.clone())
+                    .filter(|project| !project.is_empty()),
+                database: key.partition_id.as_ref()
+                    .map(|partition| partition.database_id.clone())
+                    .filter(|database| !database.is_empty()),
+                size_bytes: None,
+            },
+        };
+
+        document.metadata.size_bytes = Some(Self::key_name_size(key) + document.fields_storage_size() + 32);
+
+        debug!("Decoded entity: {}", document.metadata.path);
+        Some(document)
+    }
+
+    /// Convert a Datastore value into a typed Firestore value
+    pub fn convert_value(&self, value: &Value) -> FirestoreValue {
+        let Some(value_type) = &value.value_type else {
+            return FirestoreValue::Null;
+        };
+
+        match value_type {
+            value::ValueType::NullValue(_) => FirestoreValue::Null,
+            value::ValueType::BooleanValue(b) => FirestoreValue::Boolean(*b),
+            value::ValueType::IntegerValue(i) => FirestoreValue::Integer(*i),
+            value::ValueType::DoubleValue(d) => FirestoreValue::Double(*d),
+            value::ValueType::TimestampValue(ts) => {
+                match chrono::DateTime::from_timestamp(ts.seconds, ts.nanos.max(0) as u32) {
+                    Some(dt) => FirestoreValue::Timestamp(dt),
+                    None => FirestoreValue::Null,
+                }
+            }
+            value::ValueType::StringValue(s) => FirestoreValue::String(s.clone()),
+            value::ValueType::BlobValue(bytes) => FirestoreValue::Bytes(bytes.clone()),
+            value::ValueType::KeyValue(key) => FirestoreValue::Reference(Self::reference_name(key)),
+            value::ValueType::GeoPointValue(point) => FirestoreValue::GeoPoint {
+                latitude: point.latitude,
+                longitude: point.longitude,
+            },
+            value::ValueType::EntityValue(entity) => FirestoreValue::Map(
+                entity.properties.iter()
+                    .map(|(name, value)| (name.clone(), self.convert_value(value)))
+                    .collect()
+            ),
+            value::ValueType::ArrayValue(array) => FirestoreValue::Array(
+                array.values.iter().map(|v| self.convert_value(v)).collect()
+            ),
+        }
+    }
+
+    /// Slash-separated `kind/id` path of a key, e.g. `users/u1/orders/o1`
+    pub fn key_path(key: &Key) -> String {
+        key.path.iter()
+            .map(|element| format!(
+                "{}/{}",
+                element.kind,
+                Self::path_element_id(element).unwrap_or_default()
+            ))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Path of the parent entity in the key's ancestor chain, if any
+    pub fn parent_key_path(key: &Key) -> Option<String> {
+        if key.path.len() < 2 {
+            return None;
+        }
+        let parent = Key {
+            partition_id: None,
+            path: key.path[..key.path.len() - 1].to_vec(),
+        };
+        Some(Self::key_path(&parent))
+    }
+
+    /// Namespace of a key's partition; `None` for the default namespace
+    pub fn namespace(key: &Key) -> Option<String> {
+        key.partition_id.as_ref()
+            .map(|partition| partition.namespace_id.clone())
+            .filter(|namespace| !namespace.is_empty())
+    }
+
+    /// Render a key value as a Firestore reference. The full resource name is
+    /// used when the partition carries a project, otherwise the bare path.
+    pub fn reference_name(key: &Key) -> String {
+        let path = Self::key_path(key);
+        match key.partition_id.as_ref().filter(|p| !p.project_id.is_empty()) {
+            Some(partition) => format!(
+                "projects/{}/databases/{}/documents/{}",
+                partition.project_id,
+                if partition.database_id.is_empty() { "(default)" } else { &partition.database_id },
+                path
+            ),
+            None => path,
+        }
+    }
+
+    /// Storage size of the document name of a key. Unlike
+    /// [`crate::types::document_name_size`] on the rendered path, numeric IDs take 8 bytes.
+    pub fn key_name_size(key: &Key) -> u64 {
+        key.path.iter()
+            .map(|element| string_storage_size(&element.kind) + match element.id_type.as_ref() {
+                Some(key::path_element::IdType::Name(name)) => string_storage_size(name),
+                Some(key::path_element::IdType::Id(_)) => 8,
+                None => 0,
+            })
+            .sum::<u64>()
+            + 16
+    }
+
+    /// Document ID of a key path element (string name or numeric ID)
+    fn path_element_id(element: &key::PathElement) -> Option<String> {
+        match element.id_type.as_ref()? {
+            key::path_element::IdType::Name(name) => Some(name.clone()),
+            key::path_element::IdType::Id(id) => Some(id.to_string()),
+        }
+    }
+}
+
+impl Default for EntityDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,484 @@
+// Discovery of multi-shard Firestore export directories
+use crate::error::{FireupError, ErrorContext};
+use crate::leveldb_parser::parser::BackupFormat;
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use std::fs as stdfs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+/// Suffix of the top-level metadata file written by `gcloud firestore export`
+const OVERALL_METADATA_SUFFIX: &str = "overall_export_metadata";
+/// Suffix of the per-kind metadata files
+const KIND_METADATA_SUFFIX: &str = ".export_metadata";
+/// Prefix of the data shard files
+const SHARD_PREFIX: &str = "output-";
+/// Maximum directory depth searched for export files
+const MAX_SEARCH_DEPTH: usize = 4;
+/// Metadata file at the root of a `firebase emulators:export` directory
+pub const EMULATOR_METADATA_FILE: &str = "firebase-export-metadata.json";
+
+/// Contents of `firebase-export-metadata.json`
+#[derive(Debug, Deserialize)]
+struct EmulatorMetadataFile {
+    version: Option<String>,
+    firestore: Option<EmulatorFirestoreSection>,
+}
+
+/// `firestore` section of `firebase-export-metadata.json`; paths are
+/// relative to the emulator export directory
+#[derive(Debug, Deserialize)]
+struct EmulatorFirestoreSection {
+    version: Option<String>,
+    path: String,
+    metadata_file: Option<String>,
+}
+
+/// Firebase Emulator Suite export the Firestore data was found in
+#[derive(Debug, Clone)]
+pub struct EmulatorExport {
+    /// Directory holding `firebase-export-metadata.json`
+    pub directory: PathBuf,
+    /// Firebase CLI version that wrote the export
+    pub cli_version: Option<String>,
+    /// Firestore emulator version
+    pub firestore_version: Option<String>,
+}
+
+/// A single `output-N` data file of an export
+#[derive(Debug, Clone)]
+pub struct ExportShard {
+    /// Path to the shard file
+    pub path: PathBuf,
+    /// Kind the shard belongs to
+    pub kind: String,
+    /// Shard number (`N` in `output-N`)
+    pub index: u32,
+}
+
+/// One kind directory of an export (e.g. `all_namespaces/kind_users`)
+#[derive(Debug, Clone)]
+pub struct ExportKind {
+    /// Kind name, taken from the directory name without its `kind_` prefix
+    pub name: String,
+    /// Directory containing the kind's metadata and shards
+    pub directory: PathBuf,
+    /// Per-kind `*.export_metadata` file, if present
+    pub metadata_file: Option<PathBuf>,
+    /// Data shards in shard order
+    pub shards: Vec<ExportShard>,
+}
+
+/// Layout of a Firestore managed export directory
+#[derive(Debug, Clone)]
+pub struct ExportManifest {
+    /// Export root (the directory holding the overall metadata file)
+    pub root: PathBuf,
+    /// `*.overall_export_metadata` file, if present
+    pub overall_metadata: Option<PathBuf>,
+    /// Kinds found in the export
+    pub kinds: Vec<ExportKind>,
+    /// Inconsistencies noticed while reading the export layout
+    pub warnings: Vec<String>,
+    /// Emulator export wrapping the Firestore export, if any
+    pub emulator: Option<EmulatorExport>,
+    /// `(project, database)` named by a `projects/{project}/databases/{database}`
+    /// resource in the overall metadata, if any
+    pub database: Option<(String, String)>,
+}
+
+impl ExportManifest {
+    /// Discover the export layout below a directory.
+    ///
+    /// A directory written by `firebase emulators:export` is recognised by
+    /// its `firebase-export-metadata.json`, and only its Firestore folder is
+    /// read. Returns `Ok(None)` when `path` is not a directory or holds no
+    /// shards.
+    pub fn discover(path: &Path) -> Result<Option<Self>, FireupError> {
+        if !path.is_dir() {
+            return Ok(None);
+        }
+
+        let emulator_metadata = path.join(EMULATOR_METADATA_FILE);
+        if emulator_metadata.is_file() {
+            return Self::discover_emulator_export(path, &emulator_metadata);
+        }
+
+        let overall_metadata = find_file(path, MAX_SEARCH_DEPTH, &|name| name.ends_with(OVERALL_METADATA_SUFFIX));
+        Self::discover_layout(path, overall_metadata, None)
+    }
+
+    /// Discover the Firestore export inside an Emulator Suite export
+    fn discover_emulator_export(directory: &Path, metadata_path: &Path) -> Result<Option<Self>, FireupError> {
+        let context = ErrorContext {
+            operation: "read_emulator_metadata".to_string(),
+            metadata: HashMap::from([
+                ("file_path".to_string(), metadata_path.display().to_string()),
+            ]),
+            timestamp: chrono::Utc::now(),
+            call_path: vec!["leveldb_parser::export::ExportManifest".to_string()],
+        };
+
+        let bytes = read_metadata(metadata_path)?;
+        let metadata: EmulatorMetadataFile = serde_json::from_slice(&bytes).map_err(|e| FireupError::leveldb_parse(
+            format!("Invalid emulator export metadata {}: {}", metadata_path.display(), e),
+            context.clone(),
+        ))?;
+        let firestore = metadata.firestore.ok_or_else(|| FireupError::leveldb_parse(
+            format!("Emulator export at {} contains no Firestore data", directory.display()),
+            context.clone(),
+        ))?;
+
+        let firestore_dir = directory.join(&firestore.path);
+        if !firestore_dir.is_dir() {
+            return Err(FireupError::leveldb_parse(
+                format!("Firestore folder {} of the emulator export does not exist", firestore_dir.display()),
+                context,
+            ));
+        }
+
+        let overall_metadata = firestore.metadata_file
+            .map(|file| directory.join(file))
+            .filter(|file| file.is_file())
+            .or_else(|| find_file(&firestore_dir, MAX_SEARCH_DEPTH, &|name| name.ends_with(OVERALL_METADATA_SUFFIX)));
+        let emulator = EmulatorExport {
+            directory: directory.to_path_buf(),
+            cli_version: metadata.version,
+            firestore_version: firestore.version,
+        };
+        info!(
+            "Detected Firebase Emulator export at {} (firebase-tools {}, Firestore emulator {})",
+            directory.display(),
+            emulator.cli_version.as_deref().unwrap_or("unknown"),
+            emulator.firestore_version.as_deref().unwrap_or("unknown")
+        );
+        Self::discover_layout(&firestore_dir, overall_metadata, Some(emulator))
+    }
+
+    /// Resolve kinds and shards below `path`, rooted at the overall metadata
+    /// file when there is one
+    fn discover_layout(
+        path: &Path,
+        overall_metadata: Option<PathBuf>,
+        emulator: Option<EmulatorExport>,
+    ) -> Result<Option<Self>, FireupError> {
+        let mut warnings = Vec::new();
+        let root = overall_metadata.as_ref()
+            .and_then(|p| p.parent().map(Path::to_path_buf))
+            .unwrap_or_else(|| path.to_path_buf());
+
+        // Kind directories listed in the overall metadata
+        let mut kind_dirs: BTreeSet<PathBuf> = BTreeSet::new();
+        let mut database = None;
+        if let Some(overall) = &overall_metadata {
+            let bytes = read_metadata(overall)?;
+            let strings = metadata_strings(&bytes);
+            database = strings.iter().find_map(|string| database_resource(string));
+            for reference in strings {
+                if !reference.ends_with(KIND_METADATA_SUFFIX) {
+                    continue;
+                }
+                let metadata_path = root.join(&reference);
+                match metadata_path.parent() {
+                    Some(dir) if dir.is_dir() => {
+                        kind_dirs.insert(dir.to_path_buf());
+                    }
+                    _ => warnings.push(format!(
+                        "Kind metadata '{}' listed in {} does not exist",
+                        reference, overall.display()
+                    )),
+                }
+            }
+        }
+
+        // Any directory holding shards that the overall metadata did not mention
+        let mut scanned_dirs = Vec::new();
+        collect_shard_dirs(&root, MAX_SEARCH_DEPTH, &mut scanned_dirs);
+        for dir in scanned_dirs {
+            if overall_metadata.is_some() && !kind_dirs.contains(&dir) {
+                warnings.push(format!(
+                    "Directory {} contains shards but is not listed in the export metadata",
+                    dir.display()
+                ));
+            }
+            kind_dirs.insert(dir);
+        }
+
+        let mut kinds = Vec::new();
+        for dir in kind_dirs {
+            let kind = Self::read_kind(&dir, &mut warnings)?;
+            if kind.shards.is_empty() {
+                warnings.push(format!("Kind directory {} has no data shards", dir.display()));
+                continue;
+            }
+            kinds.push(kind);
+        }
+
+        for warning in &warnings {
+            warn!("{}", warning);
+        }
+
+        // An emulator export without documents is still an (empty) export
+        if kinds.is_empty() && emulator.is_none() {
+            return Ok(None);
+        }
+
+        let manifest = Self { root, overall_metadata, kinds, warnings, emulator, database };
+        info!(
+            "Discovered export at {} with {} kinds and {} shards",
+            manifest.root.display(), manifest.kinds.len(), manifest.shard_count()
+        );
+        Ok(Some(manifest))
+    }
+
+    /// Format of the export
+    pub fn format(&self) -> BackupFormat {
+        if self.emulator.is_some() {
+            BackupFormat::EmulatorExport
+        } else {
+            BackupFormat::FirestoreExport
+        }
+    }
+
+    /// All shards of the export, grouped by kind and in shard order
+    pub fn shards(&self) -> impl Iterator<Item = &ExportShard> {
+        self.kinds.iter().flat_map(|kind| kind.shards.iter())
+    }
+
+    /// Total number of shards in the export
+    pub fn shard_count(&self) -> usize {
+        self.kinds.iter().map(|kind| kind.shards.len()).sum()
+    }
+
+    /// Read one kind directory and resolve its shard list
+    fn read_kind(dir: &Path, warnings: &mut Vec<String>) -> Result<ExportKind, FireupError> {
+        let dir_name = dir.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        let name = dir_name.strip_prefix("kind_").unwrap_or(dir_name).to_string();
+
+        let metadata_file = find_file(dir, 0, &|file_name| file_name.ends_with(KIND_METADATA_SUFFIX));
+        let present: HashMap<String, PathBuf> = list_files(dir).into_iter()
+            .filter_map(|path| {
+                let file_name = path.file_name()?.to_str()?.to_string();
+                shard_index(&file_name).map(|_| (file_name, path))
+            })
+            .collect();
+
+        let mut shard_names: BTreeSet<String> = BTreeSet::new();
+        if let Some(metadata) = &metadata_file {
+            let bytes = read_metadata(metadata)?;
+            for listed in metadata_strings(&bytes).into_iter().filter(|s| shard_index(s).is_some()) {
+                if present.contains_key(&listed) {
+                    shard_names.insert(listed);
+                } else {
+                    warnings.push(format!(
+                        "Shard '{}' listed in {} is missing",
+                        listed, metadata.display()
+                    ));
+                }
+            }
+        }
+        for file_name in present.keys() {
+            if shard_names.insert(file_name.clone()) && metadata_file.is_some() {
+                warnings.push(format!(
+                    "Shard {} is not listed in the kind metadata",
+                    dir.join(file_name).display()
+                ));
+            }
+        }
+
+        let mut shards: Vec<ExportShard> = shard_names.into_iter()
+            .filter_map(|file_name| Some(ExportShard {
+                index: shard_index(&file_name)?,
+                path: present.get(&file_name)?.clone(),
+                kind: name.clone(),
+            }))
+            .collect();
+        shards.sort_by_key(|shard| shard.index);
+
+        debug!("Kind '{}' at {} has {} shards", name, dir.display(), shards.len());
+        Ok(ExportKind {
+            name,
+            directory: dir.to_path_buf(),
+            metadata_file,
+            shards,
+        })
+    }
+}
+
+/// Shard number of an `output-N` file name
+fn shard_index(file_name: &str) -> Option<u32> {
+    file_name.strip_prefix(SHARD_PREFIX)?.parse().ok()
+}
+
+fn list_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = stdfs::read_dir(dir) else { return Vec::new() };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    files
+}
+
+fn list_dirs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = stdfs::read_dir(dir) else { return Vec::new() };
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// Breadth-first search for the first file whose name matches
+fn find_file(dir: &Path, max_depth: usize, matches: &dyn Fn(&str) -> bool) -> Option<PathBuf> {
+    let found = list_files(dir).into_iter().find(|path| {
+        path.file_name().and_then(|n| n.to_str()).is_some_and(matches)
+    });
+    if found.is_some() || max_depth == 0 {
+        return found;
+    }
+    list_dirs(dir).into_iter().find_map(|sub| find_file(&sub, max_depth - 1, matches))
+}
+
+/// Collect every directory that directly contains `output-N` files
+fn collect_shard_dirs(dir: &Path, max_depth: usize, out: &mut Vec<PathBuf>) {
+    let has_shards = list_files(dir).iter().any(|path| {
+        path.file_name().and_then(|n| n.to_str()).and_then(shard_index).is_some()
+    });
+    if has_shards {
+        out.push(dir.to_path_buf());
+    }
+    if max_depth > 0 {
+        for sub in list_dirs(dir) {
+            collect_shard_dirs(&sub, max_depth - 1, out);
+        }
+    }
+}
+
+fn read_metadata(path: &Path) -> Result<Vec<u8>, FireupError> {
+    stdfs::read(path).map_err(|e| FireupError::leveldb_parse(
+        format!("Failed to read export metadata {}: {}", path.display(), e),
+        ErrorContext {
+            operation: "read_export_metadata".to_string(),
+            metadata: HashMap::from([
+                ("file_path".to_string(), path.display().to_string()),
+            ]),
+            timestamp: chrono::Utc::now(),
+            call_path: vec!["leveldb_parser::export::ExportManifest".to_string()],
+        }
+    ))
+}
+
+/// Extract the string fields of an export metadata file.
+///
+/// The overall metadata is a LevelDB log holding a protobuf record, the
+/// per-kind files are a bare protobuf. Neither schema is vendored, so the
+/// protobuf wire format is walked generically and every UTF-8 string field
+/// is returned.
+fn metadata_strings(bytes: &[u8]) -> Vec<String> {
+    let mut strings = Vec::new();
+    let _ = collect_proto_strings(bytes, 0, &mut strings);
+    for payload in log_payloads(bytes) {
+        let _ = collect_proto_strings(&payload, 0, &mut strings);
+    }
+    strings.sort();
+    strings.dedup();
+    strings
+}
+
+/// `(project, database)` of a string holding a
+/// `projects/{project}/databases/{database}` resource name
+fn database_resource(string: &str) -> Option<(String, String)> {
+    let (_, rest) = string.split_once("projects/")?;
+    let (project, rest) = rest.split_once("/databases/")?;
+    let database = rest.split('/').next()?;
+    if project.is_empty() || project.contains('/') || database.is_empty() {
+        return None;
+    }
+    Some((project.to_string(), database.to_string()))
+}
+
+/// Payloads of the records of a LevelDB log, reassembling fragments
+fn log_payloads(bytes: &[u8]) -> Vec<Vec<u8>> {
+    const HEADER_SIZE: usize = 7;
+    const BLOCK_SIZE: usize = 32768;
+
+    let mut payloads = Vec::new();
+    let mut current = Vec::new();
+    let mut offset = 0;
+    while offset + HEADER_SIZE <= bytes.len() {
+        let block_remaining = BLOCK_SIZE - offset % BLOCK_SIZE;
+        if block_remaining < HEADER_SIZE {
+            offset += block_remaining;
+            continue;
+        }
+        let length = u16::from_le_bytes([bytes[offset + 4], bytes[offset + 5]]) as usize;
+        let record_type = bytes[offset + 6];
+        let end = offset + HEADER_SIZE + length;
+        if record_type == 0 || record_type > 4 || end > bytes.len() {
+            break;
+        }
+        let data = &bytes[offset + HEADER_SIZE..end];
+        match record_type {
+            1 => payloads.push(data.to_vec()),
+            2 => current = data.to_vec(),
+            3 => current.extend_from_slice(data),
+            _ => {
+                current.extend_from_slice(data);
+                payloads.push(std::mem::take(&mut current));
+            }
+        }
+        offset = end;
+    }
+    payloads
+}
+
+/// Walk a protobuf message, collecting length-delimited fields that are UTF-8
+/// strings. Returns `None` when the bytes are not a well-formed message.
+fn collect_proto_strings(bytes: &[u8], depth: usize, out: &mut Vec<String>) -> Option<()> {
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let tag = read_varint(bytes, &mut offset)?;
+        if tag >> 3 == 0 {
+            return None;
+        }
+        match tag & 0x7 {
+            0 => {
+                read_varint(bytes, &mut offset)?;
+            }
+            1 => offset = offset.checked_add(8).filter(|end| *end <= bytes.len())?,
+            5 => offset = offset.checked_add(4).filter(|end| *end <= bytes.len())?,
+            2 => {
+                let length = read_varint(bytes, &mut offset)? as usize;
+                let end = offset.checked_add(length).filter(|end| *end <= bytes.len())?;
+                let field = &bytes[offset..end];
+                let mut nested = Vec::new();
+                if depth < 8 && !field.is_empty() && collect_proto_strings(field, depth + 1, &mut nested).is_some() {
+                    out.extend(nested);
+                }
+                if let Ok(text) = std::str::from_utf8(field) {
+                    if !text.is_empty() && !text.chars().any(char::is_control) {
+                        out.push(text.to_string());
+                    }
+                }
+                offset = end;
+            }
+            _ => return None,
+        }
+    }
+    Some(())
+}
+
+fn read_varint(bytes: &[u8], offset: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*offset)?;
+        *offset += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
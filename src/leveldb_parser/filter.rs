@@ -0,0 +1,147 @@
+// Collection and field filtering applied while parsing
+use crate::protos::google::datastore::admin::v1::EntityFilter;
+use crate::types::FirestoreDocument;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Selects the documents and fields kept while parsing.
+///
+/// Collections follow `EntityFilter` semantics: collection IDs play the role
+/// of kinds, and an empty include list keeps every collection. Excluded
+/// collections are dropped even when also included. Excluded fields are
+/// top-level field names, either bare (removed from every collection) or
+/// scoped as `collection/field`. Namespaces also follow `EntityFilter`:
+/// an empty list keeps every namespace and `""` is the default namespace.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocumentFilter {
+    include_collections: BTreeSet<String>,
+    exclude_collections: BTreeSet<String>,
+    exclude_fields: HashSet<String>,
+    exclude_collection_fields: HashMap<String, HashSet<String>>,
+    namespaces: BTreeSet<String>,
+}
+
+impl DocumentFilter {
+    /// A filter that keeps everything
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep only these collections (in addition to any already included)
+    pub fn with_include_collections<I, S>(mut self, collections: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.include_collections.extend(collections.into_iter().map(Into::into));
+        self
+    }
+
+    /// Drop these collections
+    pub fn with_exclude_collections<I, S>(mut self, collections: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.exclude_collections.extend(collections.into_iter().map(Into::into));
+        self
+    }
+
+    /// Drop these fields; `collection/field` limits an entry to one collection
+    pub fn with_exclude_fields<I, S>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        for field in fields.into_iter().map(Into::into) {
+            match field.split_once('/') {
+                Some((collection, name)) => {
+                    self.exclude_collection_fields
+                        .entry(collection.to_string())
+                        .or_default()
+                        .insert(name.to_string());
+                }
+                None => {
+                    self.exclude_fields.insert(field);
+                }
+            }
+        }
+        self
+    }
+
+    /// Keep only documents of these namespaces; `""` is the default namespace
+    pub fn with_namespaces<I, S>(mut self, namespaces: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.namespaces.extend(namespaces.into_iter().map(Into::into));
+        self
+    }
+
+    /// Filter keeping the kinds and namespaces of an `EntityFilter`
+    pub fn from_entity_filter(entity_filter: &EntityFilter) -> Self {
+        Self::new()
+            .with_include_collections(entity_filter.kinds.iter().cloned())
+            .with_namespaces(entity_filter.namespace_ids.iter().cloned())
+    }
+
+    /// `EntityFilter` selecting the collections this filter keeps. Exclusions
+    /// have no `EntityFilter` equivalent and are not represented.
+    pub fn entity_filter(&self) -> EntityFilter {
+        EntityFilter {
+            kinds: self.include_collections.iter()
+                .filter(|kind| !self.exclude_collections.contains(*kind))
+                .cloned()
+                .collect(),
+            namespace_ids: self.namespaces.iter().cloned().collect(),
+        }
+    }
+
+    /// Whether the filter keeps everything
+    pub fn is_empty(&self) -> bool {
+        self.include_collections.is_empty()
+            && self.exclude_collections.is_empty()
+            && self.exclude_fields.is_empty()
+            && self.exclude_collection_fields.is_empty()
+            && self.namespaces.is_empty()
+    }
+
+    /// Whether documents of a collection are kept
+    pub fn keeps_collection(&self, collection: &str) -> bool {
+        (self.include_collections.is_empty() || self.include_collections.contains(collection))
+            && !self.exclude_collections.contains(collection)
+    }
+
+    /// Whether documents of a namespace (`None` for the default one) are kept
+    pub fn keeps_namespace(&self, namespace: Option<&str>) -> bool {
+        self.namespaces.is_empty() || self.namespaces.contains(namespace.unwrap_or_default())
+    }
+
+    /// Whether the filter can keep anything of an export kind. Kinds of
+    /// exports made without an entity filter (`all_kinds`) hold every
+    /// collection and are always read.
+    pub fn keeps_kind(&self, kind: &str) -> bool {
+        kind == "all_kinds" || self.keeps_collection(kind)
+    }
+
+    /// Apply the filter to a decoded document, returning `None` when the
+    /// document is dropped. Subcollections are filtered the same way.
+    pub fn apply(&self, mut document: FirestoreDocument) -> Option<FirestoreDocument> {
+        if !self.keeps_collection(&document.collection)
+            || !self.keeps_namespace(document.metadata.namespace.as_deref())
+        {
+            return None;
+        }
+        let scoped = self.exclude_collection_fields.get(&document.collection);
+        if !self.exclude_fields.is_empty() || scoped.is_some() {
+            document.data.retain(|field, _| {
+                !self.exclude_fields.contains(field) && !scoped.is_some_and(|fields| fields.contains(field))
+            });
+        }
+        document.subcollections = std::mem::take(&mut document.subcollections)
+            .into_iter()
+            .filter_map(|subdocument| self.apply(subdocument))
+            .collect();
+        Some(document)
+    }
+}
@@ -0,0 +1,365 @@
+// Synthetic Firestore export generation for tests and load testing
+use crate::error::{FireupError, ErrorContext};
+use crate::leveldb_parser::writer::{document_entity, LevelDBWriter};
+use crate::types::{FirestoreDocument, FirestoreValue};
+use prost::Message;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Collection of the subcollection documents nested below each document
+pub const SUBCOLLECTION_NAME: &str = "items";
+
+/// Size and shape of a synthetic export.
+///
+/// Documents are generated deterministically from the seed. Field `i` of a
+/// document cycles through the Firestore value types (string, integer,
+/// double, boolean, timestamp, array, map, reference, geo point, bytes,
+/// null); maps nest up to `nesting_depth` levels and references point at the
+/// previous document of the first collection.
+#[derive(Debug, Clone)]
+pub struct FixtureSpec {
+    collections: Vec<String>,
+    documents_per_collection: usize,
+    target_bytes: Option<u64>,
+    fields_per_document: usize,
+    nesting_depth: usize,
+    array_length: usize,
+    subcollection_documents: usize,
+    shards_per_kind: usize,
+    project: String,
+    seed: u64,
+}
+
+impl Default for FixtureSpec {
+    fn default() -> Self {
+        Self {
+            collections: vec!["users".to_string(), "orders".to_string(), "products".to_string()],
+            documents_per_collection: 1000,
+            target_bytes: None,
+            fields_per_document: 8,
+            nesting_depth: 1,
+            array_length: 3,
+            subcollection_documents: 0,
+            shards_per_kind: 1,
+            project: "fixture-project".to_string(),
+            seed: 42,
+        }
+    }
+}
+
+impl FixtureSpec {
+    /// Default shape: three collections of 1000 documents with 8 fields
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Top-level collections to generate
+    pub fn with_collections<I, S>(mut self, collections: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.collections = collections.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Number of documents in each top-level collection
+    pub fn with_documents_per_collection(mut self, documents: usize) -> Self {
+        self.documents_per_collection = documents;
+        self
+    }
+
+    /// Keep generating documents, round-robin over the collections, until
+    /// the shards hold at least this many bytes (overrides the document count)
+    pub fn with_target_bytes(mut self, bytes: Option<u64>) -> Self {
+        self.target_bytes = bytes;
+        self
+    }
+
+    /// Number of top-level fields per document
+    pub fn with_fields_per_document(mut self, fields: usize) -> Self {
+        self.fields_per_document = fields;
+        self
+    }
+
+    /// Levels of nested maps in map fields
+    pub fn with_nesting_depth(mut self, depth: usize) -> Self {
+        self.nesting_depth = depth;
+        self
+    }
+
+    /// Number of elements in array fields
+    pub fn with_array_length(mut self, length: usize) -> Self {
+        self.array_length = length;
+        self
+    }
+
+    /// Documents in the `items` subcollection of every document
+    pub fn with_subcollection_documents(mut self, documents: usize) -> Self {
+        self.subcollection_documents = documents;
+        self
+    }
+
+    /// Number of `output-N` shards per kind (at least one)
+    pub fn with_shards_per_kind(mut self, shards: usize) -> Self {
+        self.shards_per_kind = shards.max(1);
+        self
+    }
+
+    /// Project written into entity keys and references
+    pub fn with_project(mut self, project: impl Into<String>) -> Self {
+        self.project = project.into();
+        self
+    }
+
+    /// Seed of the value generator
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Write the export below `root`, in the `gcloud firestore export` layout
+    /// (`all_namespaces/kind_{collection}/output-N` plus metadata files)
+    pub fn generate(&self, root: &Path) -> Result<FixtureSummary, FireupError> {
+        let context = |operation: &str, path: &Path| ErrorContext {
+            operation: operation.to_string(),
+            metadata: HashMap::from([
+                ("path".to_string(), path.display().to_string()),
+            ]),
+            timestamp: chrono::Utc::now(),
+            call_path: vec!["leveldb_parser::fixture::FixtureSpec".to_string()],
+        };
+        std::fs::create_dir_all(root).map_err(|e| FireupError::leveldb_parse(
+            format!("Failed to create fixture directory {}: {}", root.display(), e),
+            context("create_fixture_directory", root),
+        ))?;
+
+        let mut kinds = self.collections.clone();
+        if self.subcollection_documents > 0 && !kinds.iter().any(|kind| kind == SUBCOLLECTION_NAME) {
+            kinds.push(SUBCOLLECTION_NAME.to_string());
+        }
+        let mut writers: HashMap<String, Vec<LevelDBWriter>> = HashMap::new();
+        for kind in &kinds {
+            let dir = root.join(format!("all_namespaces/kind_{}", kind));
+            std::fs::create_dir_all(&dir).map_err(|e| FireupError::leveldb_parse(
+                format!("Failed to create kind directory {}: {}", dir.display(), e),
+                context("create_kind_directory", &dir),
+            ))?;
+            let shards = (0..self.shards_per_kind)
+                .map(|index| LevelDBWriter::create(&dir.join(format!("output-{}", index))))
+                .collect::<Result<Vec<_>, _>>()?;
+            writers.insert(kind.clone(), shards);
+        }
+
+        let mut generator = ValueGenerator::new(self.seed);
+        let mut documents = 0;
+        let mut bytes = 0u64;
+        let mut index = 0;
+        while !self.collections.is_empty() && self.wants_more(index, bytes) {
+            for collection in &self.collections {
+                let document = self.document(collection, index, &mut generator);
+                let shard = index % self.shards_per_kind;
+                bytes += write_entity(&mut writers, collection, shard, &document)?;
+                documents += 1;
+                for subdocument in &document.subcollections {
+                    bytes += write_entity(&mut writers, SUBCOLLECTION_NAME, shard, subdocument)?;
+                    documents += 1;
+                }
+            }
+            index += 1;
+        }
+
+        let mut shard_count = 0;
+        for (kind, shards) in writers {
+            let shard_names: Vec<String> = (0..shards.len()).map(|index| format!("output-{}", index)).collect();
+            shard_count += shards.len();
+            for shard in shards {
+                shard.finish()?;
+            }
+            let metadata_path = root.join(format!("all_namespaces/kind_{0}/all_namespaces_kind_{0}.export_metadata", kind));
+            std::fs::write(&metadata_path, metadata_message(&shard_names)).map_err(|e| FireupError::leveldb_parse(
+                format!("Failed to write kind metadata {}: {}", metadata_path.display(), e),
+                context("write_kind_metadata", &metadata_path),
+            ))?;
+        }
+
+        // The overall metadata is a log holding one record that lists the kind metadata files
+        let export_name = root.file_name().and_then(|n| n.to_str()).unwrap_or("fixture");
+        let overall_path = root.join(format!("{}.overall_export_metadata", export_name));
+        let kind_files: Vec<String> = kinds.iter()
+            .map(|kind| format!("all_namespaces/kind_{0}/all_namespaces_kind_{0}.export_metadata", kind))
+            .collect();
+        let mut overall = LevelDBWriter::create(&overall_path)?;
+        overall.add_record(&metadata_message(&kind_files))?;
+        overall.finish()?;
+
+        let summary = FixtureSummary {
+            root: root.to_path_buf(),
+            kinds,
+            shards: shard_count,
+            documents,
+            bytes,
+        };
+        info!(
+            "Generated fixture at {}: {} documents in {} kinds, {} shards, {} bytes",
+            summary.root.display(), summary.documents, summary.kinds.len(), summary.shards, summary.bytes
+        );
+        Ok(summary)
+    }
+
+    /// Whether another round of documents is needed
+    fn wants_more(&self, index: usize, bytes: u64) -> bool {
+        match self.target_bytes {
+            Some(target) => bytes < target,
+            None => index < self.documents_per_collection,
+        }
+    }
+
+    /// Build document `index` of a collection
+    fn document(&self, collection: &str, index: usize, generator: &mut ValueGenerator) -> FirestoreDocument {
+        let id = document_id(collection, index);
+        let path = format!("{}/{}", collection, id);
+        let mut document = self.build_document(collection, &id, &path, None, index, generator);
+        for item in 0..self.subcollection_documents {
+            let item_id = format!("i{:03}", item);
+            let item_path = format!("{}/{}/{}", path, SUBCOLLECTION_NAME, item_id);
+            let subdocument = self.build_document(SUBCOLLECTION_NAME, &item_id, &item_path, Some(&path), index, generator);
+            document.add_subcollection(subdocument);
+        }
+        document
+    }
+
+    fn build_document(
+        &self,
+        collection: &str,
+        id: &str,
+        path: &str,
+        parent_path: Option<&str>,
+        index: usize,
+        generator: &mut ValueGenerator,
+    ) -> FirestoreDocument {
+        let mut document = FirestoreDocument::new(id.to_string(), collection.to_string(), path.to_string());
+        document.metadata.parent_path = parent_path.map(str::to_string);
+        document.metadata.project = Some(self.project.clone());
+        for field in 0..self.fields_per_document {
+            let value = match field % FIELD_TYPES {
+                7 => self.reference(index),
+                kind => generator.value(kind, self.nesting_depth, self.array_length),
+            };
+            document.add_field(format!("field_{}", field), value);
+        }
+        document
+    }
+
+    /// Reference to the previous document of the first collection
+    fn reference(&self, index: usize) -> FirestoreValue {
+        let collection = &self.collections[0];
+        FirestoreValue::Reference(format!(
+            "projects/{}/databases/(default)/documents/{}/{}",
+            self.project, collection, document_id(collection, index.saturating_sub(1))
+        ))
+    }
+}
+
+/// ID of document `index` of a generated collection, e.g. `users-000042`
+fn document_id(collection: &str, index: usize) -> String {
+    format!("{}-{:06}", collection, index)
+}
+
+/// Write one document (without its subcollections) to a shard of its kind,
+/// returning the bytes written
+fn write_entity(
+    writers: &mut HashMap<String, Vec<LevelDBWriter>>,
+    kind: &str,
+    shard: usize,
+    document: &FirestoreDocument,
+) -> Result<u64, FireupError> {
+    let writer = &mut writers.get_mut(kind).expect("a writer per kind")[shard];
+    let before = writer.bytes_written();
+    writer.add_record(&document_entity(document).encode_to_vec())?;
+    Ok(writer.bytes_written() - before)
+}
+
+/// What [`FixtureSpec::generate`] wrote
+#[derive(Debug, Clone)]
+pub struct FixtureSummary {
+    pub root: PathBuf,
+    /// Kinds written, subcollection kind included
+    pub kinds: Vec<String>,
+    pub shards: usize,
+    /// Documents written, subcollection documents included
+    pub documents: usize,
+    /// Bytes of shard data written
+    pub bytes: u64,
+}
+
+/// Number of value types a field index cycles through
+const FIELD_TYPES: usize = 11;
+
+/// Deterministic value source (SplitMix64)
+struct ValueGenerator {
+    state: u64,
+}
+
+impl ValueGenerator {
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn text(&mut self, length: usize) -> String {
+        const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz ";
+        (0..length).map(|_| ALPHABET[(self.next() % ALPHABET.len() as u64) as usize] as char).collect()
+    }
+
+    /// A value of the given type index (see [`FixtureSpec`])
+    fn value(&mut self, kind: usize, depth: usize, array_length: usize) -> FirestoreValue {
+        match kind {
+            0 => {
+                let length = 8 + (self.next() % 24) as usize;
+                FirestoreValue::String(self.text(length))
+            }
+            1 => FirestoreValue::Integer((self.next() % 1_000_000) as i64),
+            2 => FirestoreValue::Double((self.next() % 1_000_000) as f64 / 100.0),
+            3 => FirestoreValue::Boolean(self.next().is_multiple_of(2)),
+            4 => {
+                let seconds = 1_600_000_000 + (self.next() % 100_000_000) as i64;
+                chrono::DateTime::from_timestamp(seconds, 0)
+                    .map_or(FirestoreValue::Null, FirestoreValue::Timestamp)
+            }
+            5 => FirestoreValue::Array(
+                (0..array_length).map(|_| FirestoreValue::String(self.text(6))).collect()
+            ),
+            6 if depth > 0 => FirestoreValue::Map(HashMap::from([
+                ("label".to_string(), FirestoreValue::String(self.text(10))),
+                ("count".to_string(), FirestoreValue::Integer((self.next() % 100) as i64)),
+                ("nested".to_string(), self.value(6, depth - 1, array_length)),
+            ])),
+            6 => FirestoreValue::Null,
+            8 => FirestoreValue::GeoPoint {
+                latitude: (self.next() % 180_000) as f64 / 1000.0 - 90.0,
+                longitude: (self.next() % 360_000) as f64 / 1000.0 - 180.0,
+            },
+            9 => FirestoreValue::Bytes(self.next().to_le_bytes().to_vec()),
+            _ => FirestoreValue::Null,
+        }
+    }
+}
+
+/// Protobuf message with the strings as repeated field 1, the shape export
+/// metadata readers look for
+fn metadata_message(strings: &[String]) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    for string in strings {
+        prost::encoding::string::encode(1, string, &mut buffer);
+    }
+    buffer
+}
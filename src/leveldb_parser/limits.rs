@@ -0,0 +1,229 @@
+// Firestore data model limits checked on parsed documents
+use crate::types::{
+    relative_document_path, FirestoreDocument, FirestoreValue, MAX_DOCUMENT_SIZE, NEAR_LIMIT_DOCUMENT_SIZE,
+};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Maximum size of a field name, a field path and a document or collection ID
+pub const MAX_NAME_SIZE: usize = 1500;
+/// Maximum depth of fields in a map or array
+pub const MAX_FIELD_DEPTH: usize = 20;
+/// Maximum number of index entries for a document
+pub const MAX_INDEX_ENTRIES: u64 = 40_000;
+
+/// A Firestore limit a document can break
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitRule {
+    /// Document larger than [`MAX_DOCUMENT_SIZE`]
+    DocumentSize,
+    /// Document of at least [`NEAR_LIMIT_DOCUMENT_SIZE`]; only a warning
+    DocumentSizeNearLimit,
+    /// Empty field name, or field name or path over [`MAX_NAME_SIZE`] bytes
+    FieldName,
+    /// Maps and arrays nested deeper than [`MAX_FIELD_DEPTH`]
+    NestingDepth,
+    /// More automatic index entries than [`MAX_INDEX_ENTRIES`]
+    IndexEntries,
+    /// Field name, document ID or collection ID matching `__.*__`
+    ReservedName,
+    /// Document or collection ID that Firestore does not accept
+    DocumentId,
+}
+
+impl LimitRule {
+    /// Whether a violation makes the backup invalid, rather than being
+    /// reported as a warning
+    pub fn is_error(&self) -> bool {
+        !matches!(self, Self::DocumentSizeNearLimit)
+    }
+}
+
+impl fmt::Display for LimitRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::DocumentSize => "document-size",
+            Self::DocumentSizeNearLimit => "document-size-near-limit",
+            Self::FieldName => "field-name",
+            Self::NestingDepth => "nesting-depth",
+            Self::IndexEntries => "index-entries",
+            Self::ReservedName => "reserved-name",
+            Self::DocumentId => "document-id",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A document breaking a Firestore limit
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct LimitViolation {
+    pub rule: LimitRule,
+    /// Path of the offending document
+    pub document_path: String,
+    pub message: String,
+}
+
+impl fmt::Display for LimitViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.rule, self.document_path, self.message)
+    }
+}
+
+/// Check documents, and their subcollection documents, against Firestore's
+/// data model limits
+pub fn check_documents(documents: &[FirestoreDocument]) -> Vec<LimitViolation> {
+    let mut violations = Vec::new();
+    for document in documents {
+        violations.extend(check_document(document));
+        violations.extend(check_documents(&document.subcollections));
+    }
+    violations
+}
+
+/// Check one document (without its subcollections) against Firestore's data
+/// model limits
+pub fn check_document(document: &FirestoreDocument) -> Vec<LimitViolation> {
+    let path = document.document_path();
+    let mut violations = Vec::new();
+    let mut violation = |rule: LimitRule, message: String| violations.push(LimitViolation {
+        rule,
+        document_path: path.clone(),
+        message,
+    });
+
+    let size = document.metadata.size_bytes.unwrap_or_else(|| document.storage_size());
+    if size > MAX_DOCUMENT_SIZE {
+        violation(LimitRule::DocumentSize, format!(
+            "document is {} bytes, over the {} byte limit", size, MAX_DOCUMENT_SIZE
+        ));
+    } else if size >= NEAR_LIMIT_DOCUMENT_SIZE {
+        violation(LimitRule::DocumentSizeNearLimit, format!(
+            "document is {} bytes, close to the {} byte limit", size, MAX_DOCUMENT_SIZE
+        ));
+    }
+
+    // Every collection and document ID of the path, ancestors included
+    for (position, id) in relative_document_path(&path).split('/').enumerate() {
+        let kind = if position % 2 == 0 { "collection ID" } else { "document ID" };
+        if let Some(problem) = id_problem(id) {
+            violation(LimitRule::DocumentId, format!("{} '{}' {}", kind, id, problem));
+        } else if is_reserved(id) {
+            violation(LimitRule::ReservedName, format!("{} '{}' matches the reserved __.*__ pattern", kind, id));
+        }
+    }
+
+    let mut fields = Vec::new();
+    collect_fields(&document.data, "", &mut fields);
+    fields.sort();
+    for (field_path, name) in &fields {
+        if name.is_empty() {
+            violation(LimitRule::FieldName, format!("field '{}' has an empty name", field_path));
+        } else if name.len() > MAX_NAME_SIZE {
+            violation(LimitRule::FieldName, format!(
+                "field name '{}…' is {} bytes, over the {} byte limit", truncated(name), name.len(), MAX_NAME_SIZE
+            ));
+        } else if field_path.len() > MAX_NAME_SIZE {
+            violation(LimitRule::FieldName, format!(
+                "field path '{}…' is {} bytes, over the {} byte limit", truncated(field_path), field_path.len(), MAX_NAME_SIZE
+            ));
+        }
+        if is_reserved(name) {
+            violation(LimitRule::ReservedName, format!("field '{}' matches the reserved __.*__ pattern", field_path));
+        }
+    }
+
+    let mut names: Vec<&String> = document.data.keys().collect();
+    names.sort();
+    for name in names {
+        let depth = value_depth(&document.data[name]);
+        if depth > MAX_FIELD_DEPTH {
+            violation(LimitRule::NestingDepth, format!(
+                "field '{}' nests maps and arrays {} levels deep, over the limit of {}", name, depth, MAX_FIELD_DEPTH
+            ));
+        }
+    }
+
+    let index_entries = index_entry_count(&document.data);
+    if index_entries > MAX_INDEX_ENTRIES {
+        violation(LimitRule::IndexEntries, format!(
+            "document needs {} automatic index entries, over the limit of {}", index_entries, MAX_INDEX_ENTRIES
+        ));
+    }
+
+    violations
+}
+
+/// Why Firestore rejects an ID, if it does
+fn id_problem(id: &str) -> Option<String> {
+    if id.is_empty() {
+        Some("is empty".to_string())
+    } else if id.len() > MAX_NAME_SIZE {
+        Some(format!("is {} bytes, over the {} byte limit", id.len(), MAX_NAME_SIZE))
+    } else if id == "." || id == ".." {
+        Some("cannot be '.' or '..'".to_string())
+    } else {
+        None
+    }
+}
+
+/// Why Firestore rejects a field name, if it does: empty, longer than
+/// [`MAX_NAME_SIZE`] bytes or matching `__.*__`
+pub fn field_name_problem(name: &str) -> Option<String> {
+    if name.is_empty() {
+        Some("empty name".to_string())
+    } else if name.len() > MAX_NAME_SIZE {
+        Some(format!("name of {} bytes, over the {} byte limit", name.len(), MAX_NAME_SIZE))
+    } else if is_reserved(name) {
+        Some("name matching the reserved __.*__ pattern".to_string())
+    } else {
+        None
+    }
+}
+
+/// Whether a name matches `__.*__`
+fn is_reserved(name: &str) -> bool {
+    name.len() >= 4 && name.starts_with("__") && name.ends_with("__")
+}
+
+/// First characters of an over-long name, for messages
+fn truncated(name: &str) -> String {
+    name.chars().take(40).collect()
+}
+
+/// Dotted path and name of every field, nested map fields included
+fn collect_fields(fields: &HashMap<String, FirestoreValue>, prefix: &str, out: &mut Vec<(String, String)>) {
+    for (name, value) in fields {
+        let path = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+        if let FirestoreValue::Map(nested) = value {
+            collect_fields(nested, &path, out);
+        }
+        out.push((path, name.clone()));
+    }
+}
+
+/// Levels of maps and arrays in a value: `{a: {b: {}}}` is 2 deep below `a`,
+/// plain values add none
+fn value_depth(value: &FirestoreValue) -> usize {
+    match value {
+        FirestoreValue::Map(fields) => 1 + fields.values().map(value_depth).max().unwrap_or(0),
+        FirestoreValue::Array(values) => 1 + values.iter().map(value_depth).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// Entries of the automatic single-field indexes: an ascending and a
+/// descending entry for each plain field (map subfields included) and an
+/// array-contains entry for each distinct array element
+fn index_entry_count(fields: &HashMap<String, FirestoreValue>) -> u64 {
+    fields.values()
+        .map(|value| match value {
+            FirestoreValue::Map(nested) => index_entry_count(nested),
+            FirestoreValue::Array(values) => values.iter()
+                .map(|element| format!("{:?}", element))
+                .collect::<HashSet<_>>()
+                .len() as u64,
+            _ => 2,
+        })
+        .sum()
+}
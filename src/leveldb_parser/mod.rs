@@ -1,6 +1,17 @@
 // LevelDB parser module for Firestore backup files
+pub mod entity_decoder;
+pub mod export;
+pub mod filter;
+pub mod fixture;
+pub mod limits;
 pub mod parser;
+pub mod realtime;
+pub mod references;
+pub mod repair;
+pub mod salvage;
+pub mod stream;
 pub mod validator;
+pub mod writer;
 
 #[cfg(test)]
 mod tests;
@@ -1,12 +1,22 @@
 // LevelDB parser implementation for Firestore backup files
 use crate::error::{FireupError, ErrorContext};
-use crate::types::FirestoreDocument;
+use crate::leveldb_parser::entity_decoder::EntityDecoder;
+use crate::leveldb_parser::export::ExportManifest;
+use crate::leveldb_parser::filter::DocumentFilter;
+use crate::leveldb_parser::realtime;
+use crate::leveldb_parser::salvage::{guess_collection, DroppedRecord, SalvageReport, SkippedRange};
+use crate::leveldb_parser::stream::{DocumentStream, RawRecord};
+use crate::types::{FirestoreDocument, FirestoreValue};
 use crate::monitoring::{get_monitoring_system, AuditOperationType, AuditResult};
 use bytes::{Buf, Bytes};
+use futures::stream::StreamExt;
+use rayon::prelude::*;
+use rayon::ThreadPool;
 use std::collections::HashMap;
 use std::io::SeekFrom;
 use std::path::{Path, PathBuf};
 use std::fs as stdfs;
+use std::sync::Arc;
 use tokio::fs::File;
 use tokio::io::{AsyncReadExt, AsyncSeekExt};
 use tracing::{debug, info, warn, instrument};
@@ -42,6 +52,44 @@ impl TryFrom<u8> for RecordType {
     }
 }
 
+/// Delta added when masking a CRC, as defined by LevelDB's `crc32c::Mask`
+const CRC_MASK_DELTA: u32 = 0xa282_ead8;
+
+/// Masked CRC32C (Castagnoli) of a log record: the checksum covers the
+/// record type byte followed by the payload, then is rotated and offset so
+/// that CRCs of data containing embedded CRCs stay well distributed
+pub fn masked_crc32c(record_type: u8, data: &[u8]) -> u32 {
+    let crc = crc32c::crc32c_append(crc32c::crc32c(&[record_type]), data);
+    crc.rotate_right(15).wrapping_add(CRC_MASK_DELTA)
+}
+
+/// How record checksums are verified while reading
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChecksumMode {
+    /// Fail on the first record whose checksum does not match
+    #[default]
+    Strict,
+    /// Log mismatching records but keep them
+    Warn,
+    /// Skip checksum verification
+    Off,
+}
+
+/// Which copy is kept when a document path occurs more than once
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Keep the copy with the latest `updated_at`; among equal timestamps,
+    /// the copy read last
+    #[default]
+    LatestUpdate,
+    /// Keep the copy read first
+    FirstWins,
+    /// Fail the parse on the first duplicate
+    Error,
+    /// Keep every copy
+    KeepAll,
+}
+
 /// LevelDB log record header
 #[derive(Debug, Clone)]
 pub struct RecordHeader {
@@ -55,13 +103,23 @@ pub struct RecordHeader {
 pub struct LogBlock {
     pub data: Bytes,
     pub records: Vec<LogRecord>,
+    /// Byte ranges skipped in salvage mode after a corrupted record
+    pub skipped: Vec<SkippedRange>,
 }
 
 /// Supported backup formats
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BackupFormat {
+    /// A single LevelDB log file
     LevelDb,
+    /// A file with one JSON document per line
     JsonLines,
+    /// A `gcloud firestore export` directory of LevelDB shards
+    FirestoreExport,
+    /// A `firebase emulators:export` directory wrapping a Firestore export
+    EmulatorExport,
+    /// A Firebase Realtime Database JSON export (one JSON tree)
+    RealtimeDatabase,
 }
 
 /// Individual log record within a block
@@ -69,12 +127,19 @@ pub enum BackupFormat {
 pub struct LogRecord {
     pub header: RecordHeader,
     pub data: Bytes,
+    /// File offset of the record header
+    pub offset: u64,
+    /// Checksum verification outcome (`None` when verification is off)
+    pub checksum_valid: Option<bool>,
 }
 
 /// LevelDB file reader with validation capabilities
+#[derive(Debug, Clone)]
 pub struct LevelDBReader {
     pub file_path: String,
     block_size: usize,
+    checksum_mode: ChecksumMode,
+    salvage: bool,
 }
 
 impl LevelDBReader {
@@ -85,8 +150,33 @@ impl LevelDBReader {
         Self {
             file_path: resolved,
             block_size: 32768, // 32KB blocks as per LevelDB specification
+            checksum_mode: ChecksumMode::default(),
+            salvage: false,
         }
     }
+
+    /// Enable salvage mode: on a corrupted record, skip to the next block
+    /// boundary and keep reading instead of failing
+    pub fn with_salvage_mode(mut self, salvage: bool) -> Self {
+        self.salvage = salvage;
+        self
+    }
+
+    /// Whether salvage mode is enabled
+    pub fn salvage_mode(&self) -> bool {
+        self.salvage
+    }
+
+    /// Set how record checksums are verified
+    pub fn with_checksum_mode(mut self, checksum_mode: ChecksumMode) -> Self {
+        self.checksum_mode = checksum_mode;
+        self
+    }
+
+    /// Checksum verification mode of this reader
+    pub fn checksum_mode(&self) -> ChecksumMode {
+        self.checksum_mode
+    }
     
     /// Resolve a backup path that may be a directory to the actual backup file
     fn resolve_backup_path(input: &str) -> Option<String> {
@@ -143,6 +233,19 @@ impl LevelDBReader {
     
     /// Read and validate the entire LevelDB file
     pub async fn read_file(&self) -> Result<Vec<LogBlock>, FireupError> {
+        let mut block_reader = self.open_blocks().await?;
+        
+        let mut blocks = Vec::new();
+        while let Some(block) = block_reader.next_block().await? {
+            blocks.push(block);
+        }
+        
+        info!("Successfully read {} blocks from LevelDB file", blocks.len());
+        Ok(blocks)
+    }
+
+    /// Open the file for incremental, block-at-a-time reading
+    pub async fn open_blocks(&self) -> Result<BlockReader, FireupError> {
         let context = ErrorContext {
             operation: "read_file".to_string(),
             metadata: HashMap::from([
@@ -160,7 +263,7 @@ impl LevelDBReader {
             ));
         }
         
-        let mut file = File::open(&self.file_path).await
+        let file = File::open(&self.file_path).await
             .map_err(|e| FireupError::leveldb_parse(
                 format!("Failed to open file: {}", e),
                 context.clone()
@@ -174,17 +277,12 @@ impl LevelDBReader {
         
         info!("Reading LevelDB file: {} ({} bytes)", self.file_path, file_size);
         
-        let mut blocks = Vec::new();
-        let mut position = 0u64;
-        
-        while position < file_size {
-            let block = self.read_block(&mut file, position).await?;
-            position += self.block_size as u64;
-            blocks.push(block);
-        }
-        
-        info!("Successfully read {} blocks from LevelDB file", blocks.len());
-        Ok(blocks)
+        Ok(BlockReader {
+            reader: self.clone(),
+            file,
+            position: 0,
+            file_size,
+        })
     }
     
     /// Read a single 32KB block from the file
@@ -219,16 +317,17 @@ impl LevelDBReader {
         debug!("Read block at position {} ({} bytes)", position, bytes_read);
         
         // Parse records within the block
-        let records = self.parse_block_records(&block_data, position)?;
+        let (records, skipped) = self.parse_block_records(&block_data, position)?;
         
         Ok(LogBlock {
             data: block_data,
             records,
+            skipped,
         })
     }
     
-    /// Parse all records within a block
-    fn parse_block_records(&self, block_data: &Bytes, block_position: u64) -> Result<Vec<LogRecord>, FireupError> {
+    /// Parse all records within a block, along with any range skipped in salvage mode
+    fn parse_block_records(&self, block_data: &Bytes, block_position: u64) -> Result<(Vec<LogRecord>, Vec<SkippedRange>), FireupError> {
         let _context = ErrorContext {
             operation: "parse_block_records".to_string(),
             metadata: HashMap::from([
@@ -241,6 +340,7 @@ impl LevelDBReader {
         };
         
         let mut records = Vec::new();
+        let mut skipped = Vec::new();
         let mut offset = 0;
         
         while offset + 7 <= block_data.len() { // Minimum header size is 7 bytes
@@ -252,9 +352,26 @@ impl LevelDBReader {
             
             match self.parse_record_at_offset(block_data, offset, block_position) {
                 Ok((record, next_offset)) => {
+                    if record.checksum_valid == Some(false) {
+                        let error = self.checksum_mismatch_error(&record.header, &record.data, block_position + offset as u64);
+                        if self.salvage {
+                            skipped.push(self.skip_rest_of_block(block_data, block_position, offset, &error));
+                            break;
+                        }
+                        if self.checksum_mode == ChecksumMode::Strict {
+                            return Err(error);
+                        }
+                        warn!("{}", error);
+                    }
                     records.push(record);
                     offset = next_offset;
                 }
+                Err(e) if self.salvage => {
+                    // Resync at the next block boundary rather than guessing
+                    // at record boundaries inside corrupted data
+                    skipped.push(self.skip_rest_of_block(block_data, block_position, offset, &e));
+                    break;
+                }
                 Err(e) => {
                     debug!("Failed to parse record at offset {} in block {}: {}", offset, block_position, e);
                     // Skip to next potential record boundary
@@ -264,7 +381,23 @@ impl LevelDBReader {
         }
         
         debug!("Parsed {} records from block at position {}", records.len(), block_position);
-        Ok(records)
+        Ok((records, skipped))
+    }
+
+    /// Describe the rest of a block as skipped, from a corrupted record onwards
+    fn skip_rest_of_block(&self, block_data: &Bytes, block_position: u64, offset: usize, error: &FireupError) -> SkippedRange {
+        let range = SkippedRange {
+            file_path: self.file_path.clone(),
+            start: block_position + offset as u64,
+            end: block_position + block_data.len() as u64,
+            reason: error.to_string(),
+            collection: guess_collection(&block_data[(offset + 7).min(block_data.len())..]),
+        };
+        warn!(
+            "Salvage: skipping bytes {}..{} of {}: {}",
+            range.start, range.end, self.file_path, range.reason
+        );
+        range
     }
     
     /// Parse a single record at the given offset within a block
@@ -312,12 +445,15 @@ impl LevelDBReader {
         // Extract record data
         let record_data = block_data.slice(offset + 7..data_end);
         
-        // Validate CRC32 checksum
-        self.validate_checksum(&header, &record_data)?;
+        // Verify the masked CRC32C checksum; the caller decides what a mismatch means
+        let checksum_valid = (self.checksum_mode != ChecksumMode::Off)
+            .then(|| masked_crc32c(header.record_type as u8, &record_data) == header.checksum);
         
         let record = LogRecord {
             header,
             data: record_data,
+            offset: block_position + offset as u64,
+            checksum_valid,
         };
         
         debug!("Parsed record: type={:?}, length={}, checksum=0x{:08x}", 
@@ -326,12 +462,13 @@ impl LevelDBReader {
         Ok((record, data_end))
     }
     
-    /// Validate CRC32 checksum for a record
-    fn validate_checksum(&self, header: &RecordHeader, data: &Bytes) -> Result<(), FireupError> {
+    /// Build the error reported for a record whose checksum does not match
+    fn checksum_mismatch_error(&self, header: &RecordHeader, data: &Bytes, position: u64) -> FireupError {
         let context = ErrorContext {
             operation: "validate_checksum".to_string(),
             metadata: HashMap::from([
                 ("file_path".to_string(), self.file_path.clone()),
+                ("position".to_string(), position.to_string()),
                 ("record_type".to_string(), format!("{:?}", header.record_type)),
                 ("data_length".to_string(), data.len().to_string()),
             ]),
@@ -339,32 +476,14 @@ impl LevelDBReader {
             call_path: vec!["leveldb_parser::parser::LevelDBReader".to_string()],
         };
         
-        // Calculate CRC32 checksum
-        let calculated_checksum = self.calculate_crc32(header.record_type as u8, data);
-        
-        if calculated_checksum != header.checksum {
-            return Err(FireupError::leveldb_parse(
-                format!(
-                    "Checksum mismatch: expected 0x{:08x}, calculated 0x{:08x}",
-                    header.checksum, calculated_checksum
-                ),
-                context
-            ));
-        }
-        
-        debug!("Checksum validation passed: 0x{:08x}", calculated_checksum);
-        Ok(())
-    }
-    
-    /// Calculate CRC32 checksum according to LevelDB specification
-    fn calculate_crc32(&self, record_type: u8, data: &Bytes) -> u32 {
-        // LevelDB uses a specific CRC32 implementation
-        // For now, we'll use a simple implementation
-        // In production, this should use the exact same CRC32 as LevelDB
-        let mut crc = crc32fast::Hasher::new();
-        crc.update(&[record_type]);
-        crc.update(data);
-        crc.finalize()
+        let calculated_checksum = masked_crc32c(header.record_type as u8, data);
+        FireupError::leveldb_parse(
+            format!(
+                "Checksum mismatch at offset {}: expected 0x{:08x}, calculated 0x{:08x}",
+                position, header.checksum, calculated_checksum
+            ),
+            context
+        )
     }
     
     /// Get file size
@@ -394,12 +513,194 @@ impl LevelDBReader {
     }
 }
 
+/// Incremental reader that holds a single block in memory at a time
+pub struct BlockReader {
+    reader: LevelDBReader,
+    file: File,
+    position: u64,
+    file_size: u64,
+}
+
+impl BlockReader {
+    /// Read the next 32KB block, or `None` at end of file
+    pub async fn next_block(&mut self) -> Result<Option<LogBlock>, FireupError> {
+        if self.position >= self.file_size {
+            return Ok(None);
+        }
+        
+        let block = self.reader.read_block(&mut self.file, self.position).await?;
+        self.position += self.reader.block_size as u64;
+        Ok(Some(block))
+    }
+
+    /// Byte offset of the next block
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Total size of the file being read
+    pub fn file_size(&self) -> u64 {
+        self.file_size
+    }
+}
+
+/// Reassembles fragmented log records (First/Middle/Last) into complete records
+#[derive(Debug, Default)]
+pub struct RecordAssembler {
+    parts: Vec<Bytes>,
+    /// File offset of the first pending fragment
+    partial_offset: u64,
+    /// In salvage mode, broken fragment sequences are dropped and reported
+    /// instead of failing, under this file path
+    salvage_path: Option<String>,
+    dropped: Vec<DroppedRecord>,
+}
+
+impl RecordAssembler {
+    /// Create an empty assembler
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an assembler that drops and reports broken fragment sequences
+    pub fn salvaging(file_path: impl Into<String>) -> Self {
+        Self {
+            salvage_path: Some(file_path.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Drop any pending fragments, e.g. because the data after them was skipped
+    pub fn interrupt(&mut self, reason: &str) {
+        self.drop_partial(reason);
+    }
+
+    /// Drop a fragmented record left unfinished at end of file
+    pub fn finish(&mut self) {
+        if self.has_partial() {
+            warn!("Found incomplete fragmented record at end of file");
+            self.drop_partial("Unfinished fragmented record at end of file");
+        }
+    }
+
+    /// Records dropped so far in salvage mode
+    pub fn take_dropped(&mut self) -> Vec<DroppedRecord> {
+        std::mem::take(&mut self.dropped)
+    }
+
+    fn drop_partial(&mut self, reason: &str) {
+        if self.parts.is_empty() {
+            return;
+        }
+        if let Some(file_path) = &self.salvage_path {
+            self.dropped.push(DroppedRecord {
+                file_path: file_path.clone(),
+                offset: self.partial_offset,
+                bytes: self.parts.iter().map(|p| p.len()).sum(),
+                reason: reason.to_string(),
+                collection: guess_collection(&self.parts[0]),
+            });
+        }
+        self.parts.clear();
+    }
+
+    fn drop_fragment(&mut self, record: &LogRecord, reason: &str) {
+        if let Some(file_path) = &self.salvage_path {
+            self.dropped.push(DroppedRecord {
+                file_path: file_path.clone(),
+                offset: record.offset,
+                bytes: record.data.len(),
+                reason: reason.to_string(),
+                collection: None,
+            });
+        }
+    }
+
+    /// Feed the next log record, returning a complete record once one is available
+    pub fn push(&mut self, record: &LogRecord) -> Result<Option<Bytes>, FireupError> {
+        let context = ErrorContext {
+            operation: "reconstruct_records".to_string(),
+            metadata: HashMap::from([
+                ("record_type".to_string(), format!("{:?}", record.header.record_type)),
+                ("pending_parts".to_string(), self.parts.len().to_string()),
+            ]),
+            timestamp: chrono::Utc::now(),
+            call_path: vec!["leveldb_parser::parser::RecordAssembler".to_string()],
+        };
+
+        match record.header.record_type {
+            RecordType::Full => {
+                // Complete record in a single log record
+                if !self.parts.is_empty() {
+                    warn!("Found Full record while reconstructing fragmented record");
+                    self.drop_partial("Fragmented record interrupted by a Full record");
+                }
+                Ok(Some(record.data.clone()))
+            }
+            RecordType::First => {
+                // Start of a fragmented record
+                if !self.parts.is_empty() {
+                    warn!("Found First record while reconstructing fragmented record");
+                    self.drop_partial("Fragmented record interrupted by a First record");
+                }
+                self.partial_offset = record.offset;
+                self.parts.push(record.data.clone());
+                Ok(None)
+            }
+            RecordType::Middle => {
+                // Middle part of a fragmented record
+                if self.parts.is_empty() {
+                    if self.salvage_path.is_some() {
+                        self.drop_fragment(record, "Middle record without preceding First record");
+                        return Ok(None);
+                    }
+                    return Err(FireupError::leveldb_parse(
+                        "Found Middle record without preceding First record".to_string(),
+                        context
+                    ));
+                }
+                self.parts.push(record.data.clone());
+                Ok(None)
+            }
+            RecordType::Last => {
+                // End of a fragmented record
+                if self.parts.is_empty() {
+                    if self.salvage_path.is_some() {
+                        self.drop_fragment(record, "Last record without preceding First record");
+                        return Ok(None);
+                    }
+                    return Err(FireupError::leveldb_parse(
+                        "Found Last record without preceding First record".to_string(),
+                        context
+                    ));
+                }
+                self.parts.push(record.data.clone());
+                
+                // Combine all parts into a complete record
+                let total_size: usize = self.parts.iter().map(|p| p.len()).sum();
+                let mut combined = Vec::with_capacity(total_size);
+                for part in self.parts.drain(..) {
+                    combined.extend_from_slice(&part);
+                }
+                Ok(Some(Bytes::from(combined)))
+            }
+        }
+    }
+
+    /// Whether a fragmented record is still waiting for its remaining parts
+    pub fn has_partial(&self) -> bool {
+        !self.parts.is_empty()
+    }
+}
+
 /// Parse result containing documents and metadata
 pub struct ParseResult {
     pub documents: Vec<FirestoreDocument>,
     pub collections: Vec<String>,
     pub metadata: BackupMetadata,
     pub errors: Vec<FireupError>,
+    /// What was skipped or dropped, when parsed in salvage mode
+    pub salvage_report: Option<SalvageReport>,
 }
 
 /// Metadata about the backup file
@@ -410,6 +711,84 @@ pub struct BackupMetadata {
     pub collection_count: usize,
     pub blocks_processed: usize,
     pub records_processed: usize,
+    /// Per-kind counts (empty when a single file was parsed)
+    pub kinds: Vec<KindStats>,
+    /// Per-shard counts, one entry per parsed file
+    pub shards: Vec<ShardStats>,
+    /// Format the input was read as
+    pub format: BackupFormat,
+    /// Documents dropped by the parser's [`DocumentFilter`]
+    pub documents_filtered: usize,
+    /// Copies of already-seen document paths that were discarded
+    pub duplicate_count: usize,
+    /// Named Datastore namespaces the documents belong to, sorted
+    pub namespaces: Vec<String>,
+    /// Databases the documents belong to, as `project/database`, sorted
+    pub databases: Vec<String>,
+}
+
+/// Counts for one kind of a multi-shard export
+#[derive(Debug, Clone)]
+pub struct KindStats {
+    pub kind: String,
+    pub shard_count: usize,
+    pub document_count: usize,
+    pub error_count: usize,
+}
+
+/// Counts for one parsed backup file
+#[derive(Debug, Clone)]
+pub struct ShardStats {
+    pub path: String,
+    pub kind: Option<String>,
+    pub file_size: u64,
+    pub blocks_processed: usize,
+    pub records_processed: usize,
+    pub document_count: usize,
+    pub error_count: usize,
+    /// Documents dropped by the parser's [`DocumentFilter`]
+    pub documents_filtered: usize,
+}
+
+/// Project, database, namespace and path identifying a document
+pub(crate) type DocumentKey = (Option<String>, Option<String>, Option<String>, String);
+
+/// Key telling apart copies of the same document from other documents
+pub(crate) fn document_key(document: &FirestoreDocument) -> DocumentKey {
+    (
+        document.metadata.project.clone(),
+        document.metadata.database.clone(),
+        document.metadata.namespace.clone(),
+        document.document_path(),
+    )
+}
+
+/// Error for a document path read twice under [`DuplicatePolicy::Error`]
+pub(crate) fn duplicate_document_error(path: String) -> FireupError {
+    FireupError::document_parse(
+        format!("Document {} occurs more than once in the backup", path),
+        Some(path.clone()),
+        ErrorContext {
+            operation: "deduplicate_documents".to_string(),
+            metadata: HashMap::from([
+                ("document_path".to_string(), path),
+                ("duplicate_policy".to_string(), format!("{:?}", DuplicatePolicy::Error)),
+            ]),
+            timestamp: chrono::Utc::now(),
+            call_path: vec!["leveldb_parser::parser::FirestoreDocumentParser".to_string()],
+        },
+    )
+}
+
+/// Number of records decoded together on the parse thread pool
+const DECODE_BATCH_SIZE: usize = 512;
+
+/// Documents, errors and counts of one parsed source file
+struct SourceOutcome {
+    documents: Vec<FirestoreDocument>,
+    errors: Vec<FireupError>,
+    shards: Vec<ShardStats>,
+    salvage_report: Option<SalvageReport>,
 }
 
 /// Trait for LevelDB parsing operations
@@ -419,138 +798,230 @@ pub trait LevelDBParser {
 }
 
 /// Firestore document parser that converts LevelDB records to Firestore documents
+#[derive(Clone)]
 pub struct FirestoreDocumentParser {
     reader: LevelDBReader,
+    export: Option<ExportManifest>,
+    parse_threads: usize,
+    filter: DocumentFilter,
+    duplicate_policy: DuplicatePolicy,
+    realtime_database_depth: usize,
+    database: Option<String>,
 }
 
 impl FirestoreDocumentParser {
-    /// Create a new Firestore document parser
+    /// Create a new Firestore document parser.
+    ///
+    /// A directory is read as a Firestore export: every `output-N` shard of
+    /// every kind listed in its metadata is parsed.
     pub fn new(file_path: impl Into<String>) -> Self {
+        let input = file_path.into();
+        let export = ExportManifest::discover(Path::new(&input)).unwrap_or_else(|e| {
+            warn!("Failed to read export layout of {}: {}", input, e);
+            None
+        });
         Self {
-            reader: LevelDBReader::new(file_path),
+            reader: LevelDBReader::new(input),
+            export,
+            parse_threads: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            filter: DocumentFilter::new(),
+            duplicate_policy: DuplicatePolicy::default(),
+            realtime_database_depth: 1,
+            database: None,
         }
     }
+
+    /// Set how record checksums are verified
+    pub fn with_checksum_mode(mut self, checksum_mode: ChecksumMode) -> Self {
+        self.reader = self.reader.with_checksum_mode(checksum_mode);
+        self
+    }
+
+    /// Checksum verification mode used for every file read
+    pub fn checksum_mode(&self) -> ChecksumMode {
+        self.reader.checksum_mode()
+    }
+
+    /// Enable salvage mode for every file read (see [`LevelDBReader::with_salvage_mode`])
+    pub fn with_salvage_mode(mut self, salvage: bool) -> Self {
+        self.reader = self.reader.with_salvage_mode(salvage);
+        self
+    }
+
+    /// Whether salvage mode is enabled
+    pub fn salvage_mode(&self) -> bool {
+        self.reader.salvage_mode()
+    }
+
+    /// Set how many threads decode records (defaults to the number of CPUs).
+    ///
+    /// Up to this many source files are read at once, and their records are
+    /// decoded in batches on a shared thread pool. Documents keep the order
+    /// of a single-threaded parse.
+    pub fn with_parse_threads(mut self, threads: usize) -> Self {
+        self.parse_threads = threads.max(1);
+        self
+    }
+
+    /// Number of threads used to decode records
+    pub fn parse_threads(&self) -> usize {
+        self.parse_threads
+    }
+
+    /// Drop documents and fields while parsing. Export kinds the filter
+    /// excludes are not read at all.
+    pub fn with_filter(mut self, filter: DocumentFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Filter applied to every decoded document
+    pub fn filter(&self) -> &DocumentFilter {
+        &self.filter
+    }
+
+    /// Set which copy of a repeated document path [`Self::parse_documents`]
+    /// keeps. Streamed documents are not deduplicated.
+    pub fn with_duplicate_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
+    /// Policy applied to repeated document paths
+    pub fn duplicate_policy(&self) -> DuplicatePolicy {
+        self.duplicate_policy
+    }
+
+    /// Set how many collection levels a Realtime Database export is split
+    /// into (default 1: top-level keys are collections, and everything below
+    /// their children is document fields)
+    pub fn with_realtime_database_depth(mut self, depth: usize) -> Self {
+        self.realtime_database_depth = depth.max(1);
+        self
+    }
+
+    /// Collection levels of a Realtime Database export
+    pub fn realtime_database_depth(&self) -> usize {
+        self.realtime_database_depth
+    }
+
+    /// Set the Firestore database ID of documents whose record does not name
+    /// one (e.g. `(default)` or `orders`). Without it, the database named in
+    /// the export metadata is used, if any.
+    pub fn with_database(mut self, database: Option<String>) -> Self {
+        self.database = database.filter(|database| !database.is_empty());
+        self
+    }
+
+    /// Database ID given to documents whose record does not name one
+    pub fn database(&self) -> Option<&str> {
+        self.database.as_deref()
+    }
+
+    /// Export layout discovered for a directory input
+    pub fn export_manifest(&self) -> Option<&ExportManifest> {
+        self.export.as_ref()
+    }
     
-    /// Parse the entire backup file and extract Firestore documents
+    /// Parse the entire backup and extract Firestore documents
     #[instrument(skip(self))]
     pub async fn parse_documents(&self) -> Result<ParseResult, FireupError> {
         let tracker = get_monitoring_system().start_operation("leveldb_parsing").await;
-        tracker.add_metadata("file_path", &self.reader.file_path).await.ok();
-        let context = ErrorContext {
-            operation: "parse_documents".to_string(),
-            metadata: HashMap::from([
-                ("file_path".to_string(), self.reader.file_path.clone()),
-            ]),
-            timestamp: chrono::Utc::now(),
-            call_path: vec!["leveldb_parser::parser::FirestoreDocumentParser".to_string()],
+        let source_path = match &self.export {
+            Some(export) => export.root.to_string_lossy().to_string(),
+            None => self.reader.file_path.clone(),
         };
+        tracker.add_metadata("file_path", &source_path).await.ok();
         
         info!("Starting Firestore document parsing");
-        
-        let file_size = self.reader.file_size().await?;
-        let format = self.detect_backup_format().await.unwrap_or(BackupFormat::LevelDb);
-        
-        // Parse each complete record as a Firestore document
+
+        let pool = self.build_parse_pool(&source_path)?;
+        let outcomes: Vec<Result<SourceOutcome, FireupError>> = futures::stream::iter(self.sources())
+            .map(|source| self.parse_source(source, pool.clone()))
+            .buffered(self.parse_threads)
+            .collect()
+            .await;
+
         let mut documents = Vec::new();
         let mut collections = std::collections::HashSet::new();
         let mut errors = Vec::new();
-        let mut blocks_processed: usize = 0;
-        let mut records_processed: usize = 0;
-
-        match format {
-            BackupFormat::JsonLines => {
-                info!(
-                    "Detected JSON Lines backup; parsing as JSON lines: {}",
-                    self.reader.file_path
-                );
-                let file_bytes = tokio::fs::read(&self.reader.file_path).await.map_err(|e| {
-                    FireupError::leveldb_parse(
-                        format!("Failed to read file for JSON parsing: {}", e),
-                        context.clone(),
-                    )
-                })?;
-                let mut non_empty_lines = 0usize;
-                for (index, line) in file_bytes.split(|b| *b == b'\n').enumerate() {
-                    let Ok(line_str) = std::str::from_utf8(line) else { continue };
-                    let trimmed = line_str.trim();
-                    if trimmed.is_empty() { continue; }
-                    non_empty_lines += 1;
-                    match serde_json::from_str::<serde_json::Value>(trimmed) {
-                        Ok(json_value) => {
-                            match self.parse_json_document(&json_value, index).await {
-                                Ok(Some(document)) => {
-                                    collections.insert(document.collection.clone());
-                                    documents.push(document);
-                                }
-                                Ok(None) => {
-                                    debug!("Skipped non-document JSON line at index {}", index);
-                                }
-                                Err(e) => {
-                                    warn!("Failed to parse JSON line {}: {}", index, e);
-                                    errors.push(e);
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            // Ignore lines that aren't valid JSON
-                            debug!("Invalid JSON line {}: {}", index, e);
-                            continue;
-                        }
-                    }
-                }
-                if documents.is_empty() {
-                    warn!("JSON Lines parsing did not produce any documents ({} non-empty lines)", non_empty_lines);
-                }
-                blocks_processed = 0;
-                records_processed = documents.len();
-            }
-            BackupFormat::LevelDb => {
-                // Read all blocks from the LevelDB file
-                let blocks = self.reader.read_file().await?;
-                // Reconstruct complete records from potentially fragmented log records
-                let complete_records = self.reconstruct_records(&blocks)?;
-                for (index, record_data) in complete_records.iter().enumerate() {
-                    match self.parse_firestore_record(record_data, index).await {
-                        Ok(Some(document)) => {
-                            collections.insert(document.collection.clone());
-                            documents.push(document);
-                        }
-                        Ok(None) => {
-                            // Skip non-document records (metadata, etc.)
-                            debug!("Skipped non-document record at index {}", index);
-                        }
-                        Err(e) => {
-                            warn!("Failed to parse record at index {}: {}", index, e);
-                            errors.push(e);
-                        }
-                    }
-                }
-                blocks_processed = blocks.len();
-                records_processed = complete_records.len();
+        let mut shards = Vec::new();
+        let mut salvage_report = self.salvage_mode().then(SalvageReport::new);
+        for outcome in outcomes {
+            let outcome = outcome?;
+            collections.extend(outcome.documents.iter().map(|document| document.collection.clone()));
+            documents.extend(outcome.documents);
+            errors.extend(outcome.errors);
+            shards.extend(outcome.shards);
+            if let (Some(report), Some(source_report)) = (salvage_report.as_mut(), outcome.salvage_report) {
+                report.merge(source_report);
             }
         }
+        if let Some(report) = &salvage_report {
+            info!("Salvage: {}", report.summary());
+        }
+        let (documents, duplicate_count) = self.deduplicate(documents)?;
+        if duplicate_count > 0 {
+            info!("Discarded {} duplicate document copies ({:?})", duplicate_count, self.duplicate_policy);
+        }
         
         let collections: Vec<String> = collections.into_iter().collect();
+        let namespaces: Vec<String> = documents.iter()
+            .filter_map(|document| document.metadata.namespace.clone())
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let databases: Vec<String> = documents.iter()
+            .filter_map(|document| match (&document.metadata.project, &document.metadata.database) {
+                (Some(project), Some(database)) => Some(format!("{}/{}", project, database)),
+                _ => None,
+            })
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        let mut kinds: Vec<KindStats> = Vec::new();
+        if let Some(export) = &self.export {
+            for export_kind in &export.kinds {
+                let kind_shards = shards.iter().filter(|s| s.kind.as_deref() == Some(export_kind.name.as_str()));
+                let (document_count, error_count) = kind_shards
+                    .fold((0, 0), |(docs, errs), s| (docs + s.document_count, errs + s.error_count));
+                kinds.push(KindStats {
+                    kind: export_kind.name.clone(),
+                    shard_count: export_kind.shards.len(),
+                    document_count,
+                    error_count,
+                });
+            }
+        }
         
         let metadata = BackupMetadata {
-            file_size,
+            file_size: shards.iter().map(|s| s.file_size).sum(),
             document_count: documents.len(),
             collection_count: collections.len(),
-            blocks_processed,
-            records_processed,
+            blocks_processed: shards.iter().map(|s| s.blocks_processed).sum(),
+            records_processed: shards.iter().map(|s| s.records_processed).sum(),
+            documents_filtered: shards.iter().map(|s| s.documents_filtered).sum(),
+            duplicate_count,
+            namespaces,
+            databases,
+            kinds,
+            shards,
+            format: self.backup_format().await,
         };
         
         info!(
-            "Parsing complete: {} documents, {} collections, {} errors",
-            documents.len(), collections.len(), errors.len()
+            "Parsing complete: {} documents, {} collections, {} shards, {} errors",
+            documents.len(), collections.len(), metadata.shards.len(), errors.len()
         );
 
         // Log data access audit entry
         let mut details = HashMap::new();
-        details.insert("file_path".to_string(), self.reader.file_path.clone());
+        details.insert("file_path".to_string(), source_path.clone());
         details.insert("documents_parsed".to_string(), documents.len().to_string());
         details.insert("collections_found".to_string(), collections.len().to_string());
         details.insert("blocks_processed".to_string(), metadata.blocks_processed.to_string());
+        details.insert("shards_processed".to_string(), metadata.shards.len().to_string());
         details.insert("file_size_bytes".to_string(), metadata.file_size.to_string());
         
         let audit_result = if errors.is_empty() {
@@ -564,7 +1035,7 @@ impl FirestoreDocumentParser {
         get_monitoring_system().log_audit_entry(
             AuditOperationType::DataAccess,
             "backup_file",
-            &self.reader.file_path,
+            &source_path,
             "parse_documents",
             audit_result,
             details,
@@ -579,16 +1050,215 @@ impl FirestoreDocumentParser {
             collections,
             metadata,
             errors,
+            salvage_report,
+        })
+    }
+
+    /// Stream documents one at a time, reading the backup incrementally.
+    ///
+    /// Only the block (or JSON line) currently being decoded is held in
+    /// memory, so arbitrarily large exports can be processed.
+    pub fn stream_documents(&self) -> DocumentStream {
+        DocumentStream::new(self.clone(), self.sources())
+    }
+
+    /// Format of the input: the export layout for a directory, otherwise
+    /// detected from the file contents
+    pub async fn backup_format(&self) -> BackupFormat {
+        match &self.export {
+            Some(export) => export.format(),
+            None => self.detect_backup_format(&self.reader.file_path).await.unwrap_or(BackupFormat::LevelDb),
+        }
+    }
+
+    /// `(file path, kind)` of every source file, in parse order
+    fn sources(&self) -> Vec<(String, Option<String>)> {
+        match &self.export {
+            Some(export) => export.shards()
+                .filter(|shard| {
+                    let keep = self.filter.keeps_kind(&shard.kind);
+                    if !keep {
+                        debug!("Skipping shard {} of filtered kind '{}'", shard.path.display(), shard.kind);
+                    }
+                    keep
+                })
+                .map(|shard| (shard.path.to_string_lossy().to_string(), Some(shard.kind.clone())))
+                .collect(),
+            None => vec![(self.reader.file_path.clone(), None)],
+        }
+    }
+
+    /// Thread pool records are decoded on, or `None` to decode inline
+    fn build_parse_pool(&self, source_path: &str) -> Result<Option<Arc<ThreadPool>>, FireupError> {
+        if self.parse_threads <= 1 {
+            return Ok(None);
+        }
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(self.parse_threads)
+            .thread_name(|index| format!("fireup-parse-{}", index))
+            .build()
+            .map(|pool| Some(Arc::new(pool)))
+            .map_err(|e| FireupError::leveldb_parse(
+                format!("Failed to start parse threads: {}", e),
+                ErrorContext {
+                    operation: "parse_documents".to_string(),
+                    metadata: HashMap::from([
+                        ("file_path".to_string(), source_path.to_string()),
+                        ("parse_threads".to_string(), self.parse_threads.to_string()),
+                    ]),
+                    timestamp: chrono::Utc::now(),
+                    call_path: vec!["leveldb_parser::parser::FirestoreDocumentParser".to_string()],
+                }
+            ))
+    }
+
+    /// Read one source file and decode its records in batches
+    async fn parse_source(
+        &self,
+        source: (String, Option<String>),
+        pool: Option<Arc<ThreadPool>>,
+    ) -> Result<SourceOutcome, FireupError> {
+        let mut stream = DocumentStream::new(self.clone(), vec![source]);
+        let mut documents = Vec::new();
+        let mut errors = Vec::new();
+
+        loop {
+            let mut batch = Vec::with_capacity(DECODE_BATCH_SIZE);
+            while batch.len() < DECODE_BATCH_SIZE {
+                match stream.next_raw_record().await {
+                    Some(Ok(raw)) => batch.push(raw),
+                    Some(Err(e)) => return Err(e),
+                    None => break,
+                }
+            }
+            if batch.is_empty() {
+                break;
+            }
+
+            let (batch, outcomes) = match &pool {
+                Some(pool) => self.decode_batch_on(pool, batch).await?,
+                None => {
+                    let outcomes = batch.iter().map(|raw| self.decode_raw_record(raw)).collect();
+                    (batch, outcomes)
+                }
+            };
+            for (raw, outcome) in batch.iter().zip(outcomes) {
+                match stream.record_outcome(raw, outcome) {
+                    Some(Ok(document)) => documents.push(document),
+                    Some(Err(e)) => errors.push(e),
+                    None => {}
+                }
+            }
+        }
+
+        Ok(SourceOutcome {
+            documents,
+            errors,
+            salvage_report: stream.salvage_report().cloned(),
+            shards: stream.into_shard_stats(),
         })
     }
 
+    /// Decode a batch of records in parallel, keeping their order
+    async fn decode_batch_on(
+        &self,
+        pool: &ThreadPool,
+        batch: Vec<RawRecord>,
+    ) -> Result<(Vec<RawRecord>, Vec<Result<Option<FirestoreDocument>, FireupError>>), FireupError> {
+        let batch_size = batch.len();
+        let parser = self.clone();
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+        pool.spawn(move || {
+            let outcomes = batch.par_iter().map(|raw| parser.decode_raw_record(raw)).collect();
+            let _ = sender.send((batch, outcomes));
+        });
+        receiver.await.map_err(|_| FireupError::leveldb_parse(
+            "Parse thread stopped before finishing its batch".to_string(),
+            ErrorContext {
+                operation: "decode_batch".to_string(),
+                metadata: HashMap::from([
+                    ("batch_size".to_string(), batch_size.to_string()),
+                ]),
+                timestamp: chrono::Utc::now(),
+                call_path: vec!["leveldb_parser::parser::FirestoreDocumentParser".to_string()],
+            }
+        ))
+    }
+
+    /// Keep one copy per document path (within its database and namespace) according to
+    /// the duplicate policy, returning the kept documents (in order of first
+    /// occurrence) and the number of copies discarded
+    pub(crate) fn deduplicate(&self, documents: Vec<FirestoreDocument>) -> Result<(Vec<FirestoreDocument>, usize), FireupError> {
+        if self.duplicate_policy == DuplicatePolicy::KeepAll {
+            return Ok((documents, 0));
+        }
+        let mut kept: Vec<FirestoreDocument> = Vec::with_capacity(documents.len());
+        let mut positions: HashMap<DocumentKey, usize> = HashMap::with_capacity(documents.len());
+        let mut duplicates = 0;
+
+        for document in documents {
+            let key = document_key(&document);
+            let Some(&position) = positions.get(&key) else {
+                positions.insert(key, kept.len());
+                kept.push(document);
+                continue;
+            };
+
+            duplicates += 1;
+            match self.duplicate_policy {
+                DuplicatePolicy::LatestUpdate => {
+                    if document.metadata.updated_at >= kept[position].metadata.updated_at {
+                        kept[position] = document;
+                    }
+                }
+                DuplicatePolicy::FirstWins | DuplicatePolicy::KeepAll => {}
+                DuplicatePolicy::Error => return Err(duplicate_document_error(key.3)),
+            }
+        }
+
+        Ok((kept, duplicates))
+    }
+
+    /// Decode a record read by a [`DocumentStream`]
+    pub(crate) fn decode_raw_record(&self, raw: &RawRecord) -> Result<Option<FirestoreDocument>, FireupError> {
+        let mut document = match raw {
+            RawRecord::LevelDb { index, data, .. } => self.parse_firestore_record(data, *index)?,
+            RawRecord::JsonLine { index, value, .. } => self.parse_json_document(value, *index)?,
+            RawRecord::RealtimeNode { node, .. } => Some(node.to_document()),
+        };
+        if let Some(document) = &mut document {
+            self.fill_database(document);
+            if document.metadata.size_bytes.is_none() {
+                document.metadata.size_bytes = Some(document.storage_size());
+            }
+        }
+        Ok(document)
+    }
+
+    /// Give a document without a database the one set with
+    /// [`Self::with_database`], or else the one of the export metadata
+    fn fill_database(&self, document: &mut FirestoreDocument) {
+        if document.metadata.database.is_some() {
+            return;
+        }
+        let exported = self.export.as_ref().and_then(|export| export.database.as_ref());
+        if let Some(database) = &self.database {
+            document.metadata.database = Some(database.clone());
+        } else if let Some((_, database)) = exported {
+            document.metadata.database = Some(database.clone());
+        }
+        if document.metadata.project.is_none() {
+            document.metadata.project = exported.map(|(project, _)| project.clone());
+        }
+    }
+
     /// Detect whether the backup file is a LevelDB log or JSON Lines
-    async fn detect_backup_format(&self) -> Result<BackupFormat, FireupError> {
+    pub(crate) async fn detect_backup_format(&self, file_path: &str) -> Result<BackupFormat, FireupError> {
         // Read a small prefix of the file
-        let mut file = File::open(&self.reader.file_path).await
+        let mut file = File::open(file_path).await
             .map_err(|e| FireupError::leveldb_parse(format!("Failed to open file for format detection: {}", e), ErrorContext {
                 operation: "detect_backup_format".to_string(),
-                metadata: HashMap::from([("file_path".to_string(), self.reader.file_path.clone())]),
+                metadata: HashMap::from([("file_path".to_string(), file_path.to_string())]),
                 timestamp: chrono::Utc::now(),
                 call_path: vec!["leveldb_parser::parser::FirestoreDocumentParser".to_string()],
             }))?;
@@ -597,12 +1267,21 @@ impl FirestoreDocumentParser {
         let n = file.read(&mut buf).await.unwrap_or(0);
         buf.truncate(n);
 
-        // If the prefix decodes as UTF-8 and contains braces or newlines, likely JSON lines
-        if let Ok(prefix) = std::str::from_utf8(&buf) {
+        // A prefix that decodes as UTF-8 (ignoring a multi-byte character cut off
+        // at its end) is a Realtime Database tree, or JSON lines when it
+        // contains braces and newlines
+        let prefix = std::str::from_utf8(&buf).or_else(|e| match e.error_len() {
+            None => std::str::from_utf8(&buf[..e.valid_up_to()]),
+            Some(_) => Err(e),
+        });
+        if let Ok(prefix) = prefix {
             let has_brace = prefix.contains('{') || prefix.contains('[');
             let has_newline = prefix.contains('\n');
             let printable_ratio = prefix.chars().filter(|c| !c.is_control() || *c == '\n' || *c == '\r' || *c == '\t').count() as f64
                 / (prefix.chars().count().max(1) as f64);
+            if printable_ratio > 0.95 && realtime::looks_like_realtime_database(prefix) {
+                return Ok(BackupFormat::RealtimeDatabase);
+            }
             if has_brace && has_newline && printable_ratio > 0.95 {
                 return Ok(BackupFormat::JsonLines);
             }
@@ -611,91 +1290,8 @@ impl FirestoreDocumentParser {
         Ok(BackupFormat::LevelDb)
     }
     
-    /// Reconstruct complete records from potentially fragmented log records
-    fn reconstruct_records(&self, blocks: &[LogBlock]) -> Result<Vec<Bytes>, FireupError> {
-        let context = ErrorContext {
-            operation: "reconstruct_records".to_string(),
-            metadata: HashMap::from([
-                ("blocks_count".to_string(), blocks.len().to_string()),
-            ]),
-            timestamp: chrono::Utc::now(),
-            call_path: vec!["leveldb_parser::parser::FirestoreDocumentParser".to_string()],
-        };
-        
-        let mut complete_records = Vec::new();
-        let mut current_record_parts = Vec::new();
-        
-        for block in blocks {
-            for record in &block.records {
-                match record.header.record_type {
-                    RecordType::Full => {
-                        // Complete record in a single log record
-                        if !current_record_parts.is_empty() {
-                            warn!("Found Full record while reconstructing fragmented record");
-                            current_record_parts.clear();
-                        }
-                        complete_records.push(record.data.clone());
-                    }
-                    RecordType::First => {
-                        // Start of a fragmented record
-                        if !current_record_parts.is_empty() {
-                            warn!("Found First record while reconstructing fragmented record");
-                        }
-                        current_record_parts.clear();
-                        current_record_parts.push(record.data.clone());
-                    }
-                    RecordType::Middle => {
-                        // Middle part of a fragmented record
-                        if current_record_parts.is_empty() {
-                            return Err(FireupError::leveldb_parse(
-                                "Found Middle record without preceding First record".to_string(),
-                                context
-                            ));
-                        }
-                        current_record_parts.push(record.data.clone());
-                    }
-                    RecordType::Last => {
-                        // End of a fragmented record
-                        if current_record_parts.is_empty() {
-                            return Err(FireupError::leveldb_parse(
-                                "Found Last record without preceding First record".to_string(),
-                                context
-                            ));
-                        }
-                        current_record_parts.push(record.data.clone());
-                        
-                        // Combine all parts into a complete record
-                        let complete_record = self.combine_record_parts(&current_record_parts);
-                        complete_records.push(complete_record);
-                        current_record_parts.clear();
-                    }
-                }
-            }
-        }
-        
-        // Check for incomplete fragmented records
-        if !current_record_parts.is_empty() {
-            warn!("Found incomplete fragmented record at end of file");
-        }
-        
-        debug!("Reconstructed {} complete records from {} blocks", complete_records.len(), blocks.len());
-        Ok(complete_records)
-    }
-    
-    /// Combine multiple record parts into a single complete record
-    fn combine_record_parts(&self, parts: &[Bytes]) -> Bytes {
-        let total_size: usize = parts.iter().map(|p| p.len()).sum();
-        let mut combined = Vec::with_capacity(total_size);
-        
-        for part in parts {
-            combined.extend_from_slice(part);
-        }
-        
-        Bytes::from(combined)
-    }
-    
     /// Parse a complete record as a Firestore document
-    async fn parse_firestore_record(&self, record_data: &Bytes, record_index: usize) -> Result<Option<FirestoreDocument>, FireupError> {
+    pub(crate) fn parse_firestore_record(&self, record_data: &Bytes, record_index: usize) -> Result<Option<FirestoreDocument>, FireupError> {
         let context = ErrorContext {
             operation: "parse_firestore_record".to_string(),
             metadata: HashMap::from([
@@ -708,12 +1304,14 @@ impl FirestoreDocumentParser {
         
         // Try to parse as JSON first (common format for Firestore exports)
         if let Ok(json_value) = serde_json::from_slice::<serde_json::Value>(record_data) {
-            return self.parse_json_document(&json_value, record_index).await;
+            return self.parse_json_document(&json_value, record_index);
         }
         
-        // Try to parse as protobuf-encoded data
-        if let Ok(document) = self.parse_protobuf_document(record_data, record_index).await {
-            return Ok(document);
+        // Try to parse as protobuf-encoded entity (native Firestore export format)
+        match self.parse_protobuf_document(record_data, record_index) {
+            Ok(Some(document)) => return Ok(Some(document)),
+            Ok(None) => {}
+            Err(e) => debug!("Record {} is not an entity protobuf: {}", record_index, e),
         }
         
         // Check if this is a metadata record (skip)
@@ -728,7 +1326,7 @@ impl FirestoreDocumentParser {
     }
     
     /// Parse a JSON-formatted Firestore document
-    async fn parse_json_document(&self, json_value: &serde_json::Value, record_index: usize) -> Result<Option<FirestoreDocument>, FireupError> {
+    pub(crate) fn parse_json_document(&self, json_value: &serde_json::Value, record_index: usize) -> Result<Option<FirestoreDocument>, FireupError> {
         let context = ErrorContext {
             operation: "parse_json_document".to_string(),
             metadata: HashMap::from([
@@ -753,14 +1351,13 @@ impl FirestoreDocumentParser {
         // Extract metadata
         let metadata = self.extract_document_metadata(obj)?;
         
-        // Parse subcollections if present
-        let subcollections = self.extract_subcollections(obj).await?;
-        
+        // Subcollection documents are records of their own, linked to this
+        // document through their parent path
         let document = FirestoreDocument {
             id: doc_id,
             collection,
             data,
-            subcollections,
+            subcollections: Vec::new(),
             metadata,
         };
         
@@ -768,16 +1365,10 @@ impl FirestoreDocumentParser {
         Ok(Some(document))
     }
     
-    /// Parse a protobuf-encoded Firestore document
-    async fn parse_protobuf_document(&self, _record_data: &Bytes, record_index: usize) -> Result<Option<FirestoreDocument>, FireupError> {
-        // For now, we'll implement a basic protobuf parser
-        // In a production system, you'd use the actual Firestore protobuf definitions
-        
+    /// Parse a protobuf-encoded Firestore document (`google.datastore.v1.Entity`)
+    fn parse_protobuf_document(&self, record_data: &Bytes, record_index: usize) -> Result<Option<FirestoreDocument>, FireupError> {
         debug!("Attempting to parse protobuf document at index {}", record_index);
-        
-        // This is a placeholder implementation
-        // Real protobuf parsing would require the Firestore protobuf schema
-        Ok(None)
+        EntityDecoder::new().decode(record_data, record_index)
     }
     
     /// Extract document ID and collection name from JSON object
@@ -821,13 +1412,15 @@ impl FirestoreDocumentParser {
         Ok((doc_id, collection))
     }
     
-    /// Parse a Firestore document path to extract collection and document ID
+    /// Parse a Firestore document path to extract collection and document ID.
+    /// The project and database of a full resource name are kept in the
+    /// document metadata (see [`crate::types::document_database`]).
     fn parse_document_path(&self, path: &str) -> Option<(String, String)> {
         // Firestore paths typically look like:
         // projects/{project}/databases/{database}/documents/{collection}/{document}
         // or just {collection}/{document}
         
-        let parts: Vec<&str> = path.split('/').collect();
+        let parts: Vec<&str> = crate::types::relative_document_path(path).split('/').collect();
         
         // Look for the pattern ending with collection/document
         if parts.len() >= 2 {
@@ -840,16 +1433,15 @@ impl FirestoreDocumentParser {
     }
     
     /// Extract document fields from JSON object
-    fn extract_document_fields(&self, obj: &serde_json::Map<String, serde_json::Value>) -> Result<HashMap<String, serde_json::Value>, FireupError> {
+    fn extract_document_fields(&self, obj: &serde_json::Map<String, serde_json::Value>) -> Result<HashMap<String, FirestoreValue>, FireupError> {
         let mut fields = HashMap::new();
         
         // Look for 'fields' object (common in Firestore exports)
         if let Some(fields_value) = obj.get("fields") {
             if let Some(fields_obj) = fields_value.as_object() {
                 for (key, value) in fields_obj {
-                    // Firestore fields are often wrapped in type objects
-                    let unwrapped_value = self.unwrap_firestore_value(value);
-                    fields.insert(key.clone(), unwrapped_value);
+                    // Firestore fields are wrapped in type objects
+                    fields.insert(key.clone(), FirestoreValue::from_firestore_json(value));
                 }
                 return Ok(fields);
             }
@@ -858,78 +1450,13 @@ impl FirestoreDocumentParser {
         // If no 'fields' object, treat the entire object as fields (excluding metadata)
         for (key, value) in obj {
             if !self.is_metadata_field(key) {
-                fields.insert(key.clone(), value.clone());
+                fields.insert(key.clone(), FirestoreValue::from(value.clone()));
             }
         }
         
         Ok(fields)
     }
     
-    /// Unwrap Firestore-specific value encoding
-    fn unwrap_firestore_value(&self, value: &serde_json::Value) -> serde_json::Value {
-        // Firestore values are often encoded as {"stringValue": "text"} or {"integerValue": "123"}
-        if let Some(obj) = value.as_object() {
-            if obj.len() == 1 {
-                for (type_key, type_value) in obj {
-                    match type_key.as_str() {
-                        "stringValue" => return type_value.clone(),
-                        "integerValue" => {
-                            if let Some(int_str) = type_value.as_str() {
-                                if let Ok(int_val) = int_str.parse::<i64>() {
-                                    return serde_json::Value::Number(int_val.into());
-                                }
-                            }
-                            return type_value.clone();
-                        }
-                        "doubleValue" => {
-                            if let Some(double_str) = type_value.as_str() {
-                                if let Ok(double_val) = double_str.parse::<f64>() {
-                                    return serde_json::Value::Number(
-                                        serde_json::Number::from_f64(double_val).unwrap_or_else(|| 0.into())
-                                    );
-                                }
-                            }
-                            return type_value.clone();
-                        }
-                        "booleanValue" => return type_value.clone(),
-                        "timestampValue" => return type_value.clone(),
-                        "arrayValue" => {
-                            if let Some(array_obj) = type_value.as_object() {
-                                if let Some(values) = array_obj.get("values") {
-                                    if let Some(values_array) = values.as_array() {
-                                        let unwrapped: Vec<serde_json::Value> = values_array
-                                            .iter()
-                                            .map(|v| self.unwrap_firestore_value(v))
-                                            .collect();
-                                        return serde_json::Value::Array(unwrapped);
-                                    }
-                                }
-                            }
-                            return type_value.clone();
-                        }
-                        "mapValue" => {
-                            if let Some(map_obj) = type_value.as_object() {
-                                if let Some(fields) = map_obj.get("fields") {
-                                    if let Some(fields_obj) = fields.as_object() {
-                                        let mut unwrapped_map = serde_json::Map::new();
-                                        for (k, v) in fields_obj {
-                                            unwrapped_map.insert(k.clone(), self.unwrap_firestore_value(v));
-                                        }
-                                        return serde_json::Value::Object(unwrapped_map);
-                                    }
-                                }
-                            }
-                            return type_value.clone();
-                        }
-                        _ => return value.clone(),
-                    }
-                }
-            }
-        }
-        
-        value.clone()
-    }
-    
     /// Check if a field name represents metadata rather than document data
     fn is_metadata_field(&self, field_name: &str) -> bool {
         matches!(field_name, 
@@ -956,22 +1483,21 @@ impl FirestoreDocumentParser {
             .and_then(|v| v.as_str())
             .unwrap_or("unknown")
             .to_string();
+        let parent_path = crate::types::parent_document_path(&path);
+        let (project, database) = crate::types::document_database(&path).unzip();
         
         Ok(crate::types::DocumentMetadata {
             created_at,
             updated_at,
             path,
-            size_bytes: None, // Will be calculated later if needed
+            parent_path,
+            namespace: None,
+            project,
+            database,
+            size_bytes: None, // Filled in once the fields are decoded
         })
     }
     
-    /// Extract subcollections from JSON object
-    async fn extract_subcollections(&self, _obj: &serde_json::Map<String, serde_json::Value>) -> Result<Vec<FirestoreDocument>, FireupError> {
-        // For now, return empty subcollections
-        // In a full implementation, this would recursively parse nested collections
-        Ok(Vec::new())
-    }
-    
     /// Check if a record represents metadata rather than document data
     fn is_metadata_record(&self, record_data: &Bytes) -> bool {
         // Check for common metadata patterns
@@ -989,7 +1515,14 @@ impl FirestoreDocumentParser {
 
 impl LevelDBParser for FirestoreDocumentParser {
     async fn parse_backup(&self, file_path: &str) -> Result<ParseResult, FireupError> {
-        let parser = FirestoreDocumentParser::new(file_path);
+        let parser = FirestoreDocumentParser::new(file_path)
+            .with_checksum_mode(self.checksum_mode())
+            .with_salvage_mode(self.salvage_mode())
+            .with_parse_threads(self.parse_threads())
+            .with_filter(self.filter.clone())
+            .with_duplicate_policy(self.duplicate_policy())
+            .with_realtime_database_depth(self.realtime_database_depth())
+            .with_database(self.database.clone());
         parser.parse_documents().await
     }
 }
\ No newline at end of file
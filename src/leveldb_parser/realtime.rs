@@ -0,0 +1,141 @@
+// Mapping of Firebase Realtime Database JSON exports onto Firestore documents
+use crate::types::{parent_document_path, DocumentMetadata, FirestoreDocument, FirestoreValue};
+use serde_json::{Map, Value};
+use tracing::warn;
+
+/// Field holding the value of a document node that is not an object
+/// (e.g. `"members": {"u1": true}` yields `members/u1` with `value: true`)
+pub const LEAF_VALUE_FIELD: &str = "value";
+
+/// A node of a Realtime Database tree mapped to a document
+#[derive(Debug, Clone)]
+pub struct RealtimeNode {
+    /// Document path, e.g. `users/u1/posts/p1`
+    pub path: String,
+    /// Children of the node that stay document fields
+    pub fields: Map<String, Value>,
+}
+
+impl RealtimeNode {
+    /// Build the Firestore document for this node
+    pub fn to_document(&self) -> FirestoreDocument {
+        let mut segments = self.path.rsplit('/');
+        let id = segments.next().unwrap_or_default().to_string();
+        let collection = segments.next().unwrap_or_default().to_string();
+        FirestoreDocument {
+            id,
+            collection,
+            data: self.fields.iter()
+                .map(|(key, value)| (key.clone(), FirestoreValue::from(value.clone())))
+                .collect(),
+            subcollections: Vec::new(),
+            metadata: DocumentMetadata {
+                created_at: None,
+                updated_at: None,
+                path: self.path.clone(),
+                parent_path: parent_document_path(&self.path),
+                namespace: None,
+                project: None,
+                database: None,
+                size_bytes: None,
+            },
+        }
+    }
+}
+
+/// Split a Realtime Database tree into document nodes.
+///
+/// Top-level keys are collections and their children are documents. Up to
+/// `collection_depth` collection levels are created: below a document, a
+/// child whose own children are all objects becomes a subcollection until
+/// the depth is used up, and everything else stays a field. Parents come
+/// before their subcollection documents.
+pub fn document_nodes(tree: Value, collection_depth: usize) -> Vec<RealtimeNode> {
+    let mut nodes = Vec::new();
+    let Value::Object(root) = tree else {
+        warn!("Realtime Database export is not a JSON object; no documents to read");
+        return nodes;
+    };
+
+    for (collection, children) in root {
+        match children {
+            Value::Object(children) => {
+                collect_collection(None, &collection, children, collection_depth.max(1), &mut nodes);
+            }
+            _ => warn!("Skipping top-level Realtime Database value '{}': not a collection of children", collection),
+        }
+    }
+    nodes
+}
+
+fn collect_collection(
+    parent_path: Option<&str>,
+    collection: &str,
+    children: Map<String, Value>,
+    depth: usize,
+    nodes: &mut Vec<RealtimeNode>,
+) {
+    for (id, value) in children {
+        let path = match parent_path {
+            Some(parent) => format!("{}/{}/{}", parent, collection, id),
+            None => format!("{}/{}", collection, id),
+        };
+
+        let Value::Object(children) = value else {
+            nodes.push(RealtimeNode {
+                path,
+                fields: Map::from_iter([(LEAF_VALUE_FIELD.to_string(), value)]),
+            });
+            continue;
+        };
+
+        let mut fields = Map::new();
+        let mut subcollections = Vec::new();
+        for (key, child) in children {
+            match child {
+                Value::Object(grandchildren) if depth > 1 && is_keyed_collection(&grandchildren) => {
+                    subcollections.push((key, grandchildren));
+                }
+                child => {
+                    fields.insert(key, child);
+                }
+            }
+        }
+
+        nodes.push(RealtimeNode { path: path.clone(), fields });
+        for (key, grandchildren) in subcollections {
+            collect_collection(Some(&path), &key, grandchildren, depth - 1, nodes);
+        }
+    }
+}
+
+/// Whether an object looks like a list of keyed records (every child is an object)
+fn is_keyed_collection(children: &Map<String, Value>) -> bool {
+    !children.is_empty() && children.values().all(Value::is_object)
+}
+
+/// Top-level keys of a JSON Lines document that a Realtime Database tree is
+/// unlikely to start with
+const DOCUMENT_KEYS: [&str; 5] = ["name", "path", "fields", "id", "collection"];
+
+/// Whether the start of a text file looks like a Realtime Database export
+/// (one JSON object) rather than JSON Lines of Firestore documents
+pub fn looks_like_realtime_database(prefix: &str) -> bool {
+    let trimmed = prefix.trim_start();
+    if !trimmed.starts_with('{') {
+        return false;
+    }
+    match trimmed.split_once('\n') {
+        Some((first_line, rest)) => match serde_json::from_str::<Value>(first_line) {
+            // A complete object followed by another line is JSON Lines
+            Ok(Value::Object(_)) if rest.lines().any(|line| !line.trim().is_empty()) => false,
+            // Firestore documents carry their name, fields or ID at the top level
+            Ok(Value::Object(object)) => !DOCUMENT_KEYS.iter().any(|key| object.contains_key(*key)),
+            Ok(_) => false,
+            // A pretty-printed tree spans several lines
+            Err(_) => true,
+        },
+        // One line longer than the prefix
+        None => !trimmed.contains("\"fields\""),
+    }
+}
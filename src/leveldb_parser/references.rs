@@ -0,0 +1,265 @@
+// Referential integrity across all documents of a backup
+use crate::types::{document_path_string, relative_document_path, FirestoreDocument, FirestoreValue};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Dangling references listed by [`ReferentialIntegrityReport::to_text`]
+const TEXT_REPORT_DANGLING_LIMIT: usize = 20;
+
+/// How a document points at another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferenceKind {
+    /// A reference-typed value
+    Reference,
+    /// A string holding the path of a document in a collection of the backup
+    PathString,
+}
+
+/// A reference to a document that is not in the backup
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct DanglingReference {
+    /// Path of the referencing document
+    pub source_path: String,
+    /// Dotted path of the referencing field
+    pub field_path: String,
+    /// Path of the missing document
+    pub target_path: String,
+    pub kind: ReferenceKind,
+}
+
+/// References from one field of a collection to one target collection
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ReferenceCount {
+    pub source_collection: String,
+    pub field_path: String,
+    pub target_collection: String,
+    pub references: usize,
+    pub dangling: usize,
+}
+
+/// Documents of one collection with the same ID under different parents;
+/// they collide on the primary key of the collection's table
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SharedDocumentId {
+    /// Qualified collection (see [`FirestoreDocument::qualified_collection`])
+    pub collection: String,
+    pub document_id: String,
+    pub paths: Vec<String>,
+}
+
+/// Result of checking every reference of a backup against its documents
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct ReferentialIntegrityReport {
+    pub documents_checked: usize,
+    /// Reference-typed values checked
+    pub references_checked: usize,
+    /// Path-like strings checked
+    pub path_strings_checked: usize,
+    /// Copies of documents the parser discarded as duplicates of a path
+    pub duplicate_copies: usize,
+    /// Counts per source field and target collection, most dangling first
+    pub reference_counts: Vec<ReferenceCount>,
+    pub dangling_references: Vec<DanglingReference>,
+    pub shared_ids: Vec<SharedDocumentId>,
+}
+
+impl ReferentialIntegrityReport {
+    /// Gather every reference value and path-like string of the documents
+    /// (subcollection documents included) and check that its target exists.
+    ///
+    /// A string counts as a path when it has an even number of segments and
+    /// its collections are collections of the backup, so that values such as
+    /// `12/05` or `image/png` are not mistaken for references.
+    pub fn check(documents: &[FirestoreDocument]) -> Self {
+        let mut all = Vec::new();
+        flatten(documents, &mut all);
+
+        let paths: HashSet<String> = all.iter().map(|document| document.document_path()).collect();
+        let collections: HashSet<String> = paths.iter()
+            .flat_map(|path| path.split('/').step_by(2).map(str::to_string).collect::<Vec<_>>())
+            .collect();
+
+        let mut report = Self {
+            documents_checked: all.len(),
+            ..Self::default()
+        };
+        let mut counts: BTreeMap<(String, String, String), (usize, usize)> = BTreeMap::new();
+        for document in &all {
+            let source_path = document.document_path();
+            let source_collection = document.qualified_collection();
+            let mut found = Vec::new();
+            collect_targets(&document.data, "", &collections, &mut found);
+
+            for (field_path, target_path, kind) in found {
+                match kind {
+                    ReferenceKind::Reference => report.references_checked += 1,
+                    ReferenceKind::PathString => report.path_strings_checked += 1,
+                }
+                let target_collection = target_path.rsplit('/').nth(1).unwrap_or_default().to_string();
+                let count = counts
+                    .entry((source_collection.clone(), field_path.clone(), target_collection))
+                    .or_default();
+                count.0 += 1;
+                if !paths.contains(&target_path) {
+                    count.1 += 1;
+                    report.dangling_references.push(DanglingReference {
+                        source_path: source_path.clone(),
+                        field_path,
+                        target_path,
+                        kind,
+                    });
+                }
+            }
+        }
+
+        report.reference_counts = counts.into_iter()
+            .map(|((source_collection, field_path, target_collection), (references, dangling))| ReferenceCount {
+                source_collection,
+                field_path,
+                target_collection,
+                references,
+                dangling,
+            })
+            .collect();
+        report.reference_counts.sort_by_key(|count| std::cmp::Reverse(count.dangling));
+        report.dangling_references.sort_by(|a, b| {
+            (&a.source_path, &a.field_path, &a.target_path).cmp(&(&b.source_path, &b.field_path, &b.target_path))
+        });
+        report.shared_ids = shared_ids(&all);
+        report
+    }
+
+    /// Record the duplicate copies the parser discarded
+    pub fn with_duplicate_copies(mut self, duplicate_copies: usize) -> Self {
+        self.duplicate_copies = duplicate_copies;
+        self
+    }
+
+    /// Number of references whose target is missing
+    pub fn dangling_count(&self) -> usize {
+        self.dangling_references.len()
+    }
+
+    /// Whether every reference resolves and every document ID is unique
+    /// within its collection
+    pub fn is_clean(&self) -> bool {
+        self.dangling_references.is_empty() && self.shared_ids.is_empty()
+    }
+
+    /// Plain text rendering of the report
+    pub fn to_text(&self) -> String {
+        let mut report = String::new();
+
+        report.push_str("=== Referential Integrity Report ===\n\n");
+        report.push_str(&format!("Documents: {}\n", self.documents_checked));
+        report.push_str(&format!("Reference Values: {}\n", self.references_checked));
+        report.push_str(&format!("Path Strings: {}\n", self.path_strings_checked));
+        report.push_str(&format!("Dangling References: {}\n", self.dangling_count()));
+        report.push_str(&format!("Shared Document IDs: {}\n", self.shared_ids.len()));
+        report.push_str(&format!("Discarded Duplicate Copies: {}\n", self.duplicate_copies));
+
+        if !self.reference_counts.is_empty() {
+            report.push_str("\n--- References by Field ---\n");
+            for count in &self.reference_counts {
+                report.push_str(&format!(
+                    "{}.{} -> {}: {} references, {} dangling\n",
+                    count.source_collection, count.field_path, count.target_collection,
+                    count.references, count.dangling
+                ));
+            }
+        }
+
+        if !self.dangling_references.is_empty() {
+            report.push_str("\n--- Dangling References ---\n");
+            for dangling in self.dangling_references.iter().take(TEXT_REPORT_DANGLING_LIMIT) {
+                report.push_str(&format!(
+                    "{} {} -> {}\n",
+                    dangling.source_path, dangling.field_path, dangling.target_path
+                ));
+            }
+            if self.dangling_references.len() > TEXT_REPORT_DANGLING_LIMIT {
+                report.push_str(&format!(
+                    "... and {} more\n",
+                    self.dangling_references.len() - TEXT_REPORT_DANGLING_LIMIT
+                ));
+            }
+        }
+
+        if !self.shared_ids.is_empty() {
+            report.push_str("\n--- Documents Sharing an ID ---\n");
+            for shared in &self.shared_ids {
+                report.push_str(&format!(
+                    "{}/{}: {}\n",
+                    shared.collection, shared.document_id, shared.paths.join(", ")
+                ));
+            }
+        }
+
+        report.push_str("\n=== End of Report ===\n");
+        report
+    }
+}
+
+/// Documents and their subcollection documents, parents first
+fn flatten<'a>(documents: &'a [FirestoreDocument], out: &mut Vec<&'a FirestoreDocument>) {
+    for document in documents {
+        out.push(document);
+        flatten(&document.subcollections, out);
+    }
+}
+
+/// Field path, target document path and kind of every reference in a set of
+/// fields, looking into maps and arrays
+fn collect_targets(
+    fields: &HashMap<String, FirestoreValue>,
+    prefix: &str,
+    collections: &HashSet<String>,
+    out: &mut Vec<(String, String, ReferenceKind)>,
+) {
+    for (name, value) in fields {
+        let field_path = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+        collect_value_targets(value, &field_path, collections, out);
+    }
+}
+
+fn collect_value_targets(
+    value: &FirestoreValue,
+    field_path: &str,
+    collections: &HashSet<String>,
+    out: &mut Vec<(String, String, ReferenceKind)>,
+) {
+    match value {
+        FirestoreValue::Reference(reference) => {
+            out.push((field_path.to_string(), relative_document_path(reference).to_string(), ReferenceKind::Reference));
+        }
+        FirestoreValue::String(s) => {
+            if let Some(path) = document_path_string(s, collections) {
+                out.push((field_path.to_string(), path.to_string(), ReferenceKind::PathString));
+            }
+        }
+        FirestoreValue::Array(values) => {
+            for element in values {
+                collect_value_targets(element, field_path, collections, out);
+            }
+        }
+        FirestoreValue::Map(fields) => collect_targets(fields, field_path, collections, out),
+        _ => {}
+    }
+}
+
+/// IDs used by more than one document of the same qualified collection
+fn shared_ids(documents: &[&FirestoreDocument]) -> Vec<SharedDocumentId> {
+    let mut by_id: BTreeMap<(String, String), Vec<String>> = BTreeMap::new();
+    for document in documents {
+        by_id.entry((document.qualified_collection(), document.id.clone()))
+            .or_default()
+            .push(document.document_path());
+    }
+    by_id.into_iter()
+        .filter_map(|((collection, document_id), mut paths)| {
+            paths.sort();
+            paths.dedup();
+            (paths.len() > 1).then_some(SharedDocumentId { collection, document_id, paths })
+        })
+        .collect()
+}
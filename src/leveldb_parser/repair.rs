@@ -0,0 +1,480 @@
+// Rewriting a backup as a cleaned export
+use crate::error::{FireupError, ErrorContext};
+use crate::leveldb_parser::limits::field_name_problem;
+use crate::leveldb_parser::parser::{ChecksumMode, DuplicatePolicy, FirestoreDocumentParser};
+use crate::leveldb_parser::validator::{FileInfo, IntegrityInfo, StructureInfo, ValidationResult};
+use crate::leveldb_parser::writer::LevelDBWriter;
+use crate::types::{FirestoreDocument, FirestoreValue};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use tracing::info;
+
+/// A fix `fireup repair` can apply
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepairFix {
+    /// Skip corrupted blocks and records that do not decode, instead of
+    /// failing on them
+    DropUnreadable,
+    /// Keep one copy of each document path (see [`BackupRepairer::with_duplicate_policy`])
+    Deduplicate,
+    /// Remove fields whose names Firestore rejects: empty, over 1,500 bytes
+    /// or matching `__.*__`
+    StripInvalidFields,
+    /// Convert values stored with the wrong type (`"42"` in an integer
+    /// field, `1.0` in an integer field, RFC 3339 strings in a timestamp
+    /// field, ...) to the type most documents of the collection use
+    CoerceTypes,
+}
+
+impl RepairFix {
+    /// Every fix, in the order they are applied
+    pub const ALL: [RepairFix; 4] = [
+        RepairFix::DropUnreadable,
+        RepairFix::Deduplicate,
+        RepairFix::StripInvalidFields,
+        RepairFix::CoerceTypes,
+    ];
+}
+
+impl std::fmt::Display for RepairFix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::DropUnreadable => "drop-unreadable",
+            Self::Deduplicate => "deduplicate",
+            Self::StripInvalidFields => "strip-invalid-fields",
+            Self::CoerceTypes => "coerce-types",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Format of the export written by [`BackupRepairer::repair`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RepairOutputFormat {
+    /// One document per line in the Firestore REST encoding
+    #[default]
+    JsonLines,
+    /// A single LevelDB log of `google.datastore.v1.Entity` records
+    LevelDbLog,
+}
+
+/// One change made while repairing
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RepairAction {
+    pub fix: RepairFix,
+    /// Document the change applies to, when it applies to one
+    pub document_path: Option<String>,
+    pub description: String,
+}
+
+/// Reads a backup, applies the chosen fixes and writes a new export.
+///
+/// JSON Lines output keeps project and database in the resource name of each
+/// document; LevelDB output keeps project and Datastore namespace in the keys.
+pub struct BackupRepairer {
+    input: String,
+    fixes: BTreeSet<RepairFix>,
+    output_format: RepairOutputFormat,
+    duplicate_policy: DuplicatePolicy,
+    parse_threads: Option<usize>,
+}
+
+impl BackupRepairer {
+    /// Repair a backup file or export directory, applying every fix
+    pub fn new(input: impl Into<String>) -> Self {
+        Self {
+            input: input.into(),
+            fixes: RepairFix::ALL.into_iter().collect(),
+            output_format: RepairOutputFormat::default(),
+            duplicate_policy: DuplicatePolicy::default(),
+            parse_threads: None,
+        }
+    }
+
+    /// Apply only these fixes
+    pub fn with_fixes(mut self, fixes: impl IntoIterator<Item = RepairFix>) -> Self {
+        self.fixes = fixes.into_iter().collect();
+        self
+    }
+
+    /// Fixes that will be applied
+    pub fn fixes(&self) -> &BTreeSet<RepairFix> {
+        &self.fixes
+    }
+
+    /// Set the format of the repaired export
+    pub fn with_output_format(mut self, output_format: RepairOutputFormat) -> Self {
+        self.output_format = output_format;
+        self
+    }
+
+    /// Set which copy of a duplicated document is kept by
+    /// [`RepairFix::Deduplicate`]
+    pub fn with_duplicate_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
+    /// Set the number of threads decoding backup records
+    pub fn with_parse_threads(mut self, threads: usize) -> Self {
+        self.parse_threads = Some(threads);
+        self
+    }
+
+    /// Read the backup, apply the fixes and write the repaired export to
+    /// `output`. The result describes the input and lists every change in
+    /// [`ValidationResult::repairs`].
+    pub async fn repair(&self, output: &Path) -> Result<ValidationResult, FireupError> {
+        let drop_unreadable = self.fixes.contains(&RepairFix::DropUnreadable);
+        let mut parser = FirestoreDocumentParser::new(self.input.clone())
+            .with_checksum_mode(ChecksumMode::Strict)
+            .with_salvage_mode(drop_unreadable)
+            .with_duplicate_policy(DuplicatePolicy::KeepAll);
+        if let Some(threads) = self.parse_threads {
+            parser = parser.with_parse_threads(threads);
+        }
+        let parse_result = parser.parse_documents().await?;
+
+        let mut repairs = Vec::new();
+        if !parse_result.errors.is_empty() && !drop_unreadable {
+            return Err(FireupError::leveldb_parse(
+                format!(
+                    "{} records of {} could not be decoded; apply the drop-unreadable fix to leave them out",
+                    parse_result.errors.len(), self.input
+                ),
+                self.context("repair_backup", HashMap::from([
+                    ("parse_errors".to_string(), parse_result.errors.len().to_string()),
+                ])),
+            ));
+        }
+        for error in &parse_result.errors {
+            repairs.push(RepairAction {
+                fix: RepairFix::DropUnreadable,
+                document_path: None,
+                description: format!("Dropped undecodable record: {}", error),
+            });
+        }
+        if let Some(salvage) = &parse_result.salvage_report {
+            for range in &salvage.skipped_ranges {
+                repairs.push(RepairAction {
+                    fix: RepairFix::DropUnreadable,
+                    document_path: None,
+                    description: format!(
+                        "Skipped bytes {}..{} of {}: {}",
+                        range.start, range.end, range.file_path, range.reason
+                    ),
+                });
+            }
+            for record in &salvage.dropped_records {
+                repairs.push(RepairAction {
+                    fix: RepairFix::DropUnreadable,
+                    document_path: None,
+                    description: format!(
+                        "Dropped incomplete record at offset {} of {}: {}",
+                        record.offset, record.file_path, record.reason
+                    ),
+                });
+            }
+        }
+
+        let mut documents = parse_result.documents;
+        if self.fixes.contains(&RepairFix::Deduplicate) {
+            documents = self.deduplicate(&parser, documents, &mut repairs)?;
+        }
+        if self.fixes.contains(&RepairFix::StripInvalidFields) {
+            for document in &mut documents {
+                let path = document.document_path();
+                strip_invalid_fields(&mut document.data, "", &path, &mut repairs);
+            }
+        }
+        if self.fixes.contains(&RepairFix::CoerceTypes) {
+            coerce_types(&mut documents, &mut repairs);
+        }
+
+        let written = match self.output_format {
+            RepairOutputFormat::JsonLines => self.write_json_lines(&documents, output)?,
+            RepairOutputFormat::LevelDbLog => {
+                let mut writer = LevelDBWriter::create(output)?;
+                for document in &documents {
+                    writer.add_document(document)?;
+                }
+                let written = writer.records_written();
+                writer.finish()?;
+                written
+            }
+        };
+        info!("Wrote {} documents to {} ({} repairs)", written, output.display(), repairs.len());
+
+        let metadata = &parse_result.metadata;
+        let unreadable = repairs.iter().filter(|repair| repair.fix == RepairFix::DropUnreadable).count();
+        let mut warnings = Vec::new();
+        for fix in RepairFix::ALL {
+            let count = repairs.iter().filter(|repair| repair.fix == fix).count();
+            if count > 0 {
+                warnings.push(format!("{}: {} changes", fix, count));
+            }
+        }
+
+        Ok(ValidationResult {
+            is_valid: true,
+            errors: Vec::new(),
+            warnings,
+            file_info: FileInfo {
+                file_path: self.input.clone(),
+                file_size: metadata.file_size,
+                is_readable: true,
+                last_modified: None,
+            },
+            structure_info: StructureInfo {
+                total_blocks: metadata.blocks_processed,
+                total_records: metadata.records_processed,
+                valid_records: metadata.records_processed.saturating_sub(parse_result.errors.len()),
+                corrupted_records: unreadable,
+                metadata_records: 0,
+                document_records: written,
+            },
+            integrity_info: IntegrityInfo {
+                checksum_failures: 0,
+                incomplete_records: parse_result.salvage_report.as_ref().map_or(0, |s| s.dropped_records.len()),
+                parsing_errors: parse_result.errors.len(),
+                overall_integrity_score: if metadata.records_processed == 0 {
+                    1.0
+                } else {
+                    metadata.records_processed.saturating_sub(unreadable) as f64 / metadata.records_processed as f64
+                },
+            },
+            limit_violations: Vec::new(),
+            referential_integrity: None,
+            repairs,
+        })
+    }
+
+    /// Keep one copy per document path according to the duplicate policy,
+    /// recording each path that had copies
+    fn deduplicate(
+        &self,
+        parser: &FirestoreDocumentParser,
+        documents: Vec<FirestoreDocument>,
+        repairs: &mut Vec<RepairAction>,
+    ) -> Result<Vec<FirestoreDocument>, FireupError> {
+        let mut copies: HashMap<(Option<String>, String), usize> = HashMap::new();
+        for document in &documents {
+            *copies.entry((document.metadata.namespace.clone(), document.metadata.path.clone())).or_default() += 1;
+        }
+        let mut duplicated: Vec<(String, usize)> = documents.iter()
+            .filter_map(|document| {
+                let count = copies.remove(&(document.metadata.namespace.clone(), document.metadata.path.clone()))?;
+                (count > 1).then(|| (document.document_path(), count))
+            })
+            .collect();
+        duplicated.sort();
+        for (path, count) in duplicated {
+            repairs.push(RepairAction {
+                fix: RepairFix::Deduplicate,
+                document_path: Some(path),
+                description: format!("Kept 1 of {} copies ({:?})", count, self.duplicate_policy),
+            });
+        }
+
+        let (documents, _) = parser.clone()
+            .with_duplicate_policy(self.duplicate_policy)
+            .deduplicate(documents)?;
+        Ok(documents)
+    }
+
+    /// Write documents, subcollection documents included, as JSON lines;
+    /// returns the number of documents written
+    fn write_json_lines(&self, documents: &[FirestoreDocument], output: &Path) -> Result<usize, FireupError> {
+        let context = || self.context("write_json_lines", HashMap::from([
+            ("output_path".to_string(), output.display().to_string()),
+        ]));
+        let file = File::create(output).map_err(|e| FireupError::leveldb_parse(
+            format!("Failed to create {}: {}", output.display(), e),
+            context(),
+        ))?;
+        let mut out = BufWriter::new(file);
+
+        let mut pending: Vec<&FirestoreDocument> = documents.iter().rev().collect();
+        let mut written = 0;
+        while let Some(document) = pending.pop() {
+            let line = document_json(document);
+            writeln!(out, "{}", line).map_err(|e| FireupError::leveldb_parse(
+                format!("Failed to write {}: {}", output.display(), e),
+                context(),
+            ))?;
+            written += 1;
+            pending.extend(document.subcollections.iter().rev());
+        }
+        out.flush().map_err(|e| FireupError::leveldb_parse(
+            format!("Failed to write {}: {}", output.display(), e),
+            context(),
+        ))?;
+        Ok(written)
+    }
+
+    fn context(&self, operation: &str, mut metadata: HashMap<String, String>) -> ErrorContext {
+        metadata.insert("input".to_string(), self.input.clone());
+        ErrorContext {
+            operation: operation.to_string(),
+            metadata,
+            timestamp: chrono::Utc::now(),
+            call_path: vec!["leveldb_parser::repair::BackupRepairer".to_string()],
+        }
+    }
+}
+
+/// A document as a line of a JSON Lines export: the resource name (or path),
+/// the fields in the Firestore REST encoding and the timestamps
+fn document_json(document: &FirestoreDocument) -> serde_json::Value {
+    let name = if document.metadata.path.starts_with("projects/") {
+        document.metadata.path.clone()
+    } else {
+        document.document_path()
+    };
+    let mut line = serde_json::json!({
+        "name": name,
+        "fields": document.data.iter()
+            .map(|(key, value)| (key.clone(), value.to_firestore_json()))
+            .collect::<serde_json::Map<_, _>>(),
+    });
+    let timestamps = [("createTime", document.metadata.created_at), ("updateTime", document.metadata.updated_at)];
+    for (key, timestamp) in timestamps {
+        if let Some(timestamp) = timestamp {
+            line[key] = serde_json::Value::String(timestamp.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true));
+        }
+    }
+    line
+}
+
+/// Remove fields (and map subfields) with names Firestore rejects
+fn strip_invalid_fields(
+    fields: &mut HashMap<String, FirestoreValue>,
+    prefix: &str,
+    document_path: &str,
+    repairs: &mut Vec<RepairAction>,
+) {
+    let mut names: Vec<String> = fields.keys().cloned().collect();
+    names.sort();
+    for name in names {
+        let field_path = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+        if let Some(problem) = field_name_problem(&name) {
+            fields.remove(&name);
+            repairs.push(RepairAction {
+                fix: RepairFix::StripInvalidFields,
+                document_path: Some(document_path.to_string()),
+                description: format!("Removed field '{}' ({})", field_path, problem),
+            });
+        } else if let Some(FirestoreValue::Map(nested)) = fields.get_mut(&name) {
+            strip_invalid_fields(nested, &field_path, document_path, repairs);
+        }
+    }
+}
+
+/// Convert values whose type differs from the type held by most values of
+/// the same field in the same collection, where the conversion is lossless
+fn coerce_types(documents: &mut [FirestoreDocument], repairs: &mut Vec<RepairAction>) {
+    let mut type_counts: HashMap<(String, String), HashMap<&'static str, usize>> = HashMap::new();
+    for document in documents.iter() {
+        let collection = document.qualified_collection();
+        count_types(&document.data, "", &mut |field_path, value| {
+            if !value.is_null() {
+                *type_counts.entry((collection.clone(), field_path))
+                    .or_default()
+                    .entry(value.type_name())
+                    .or_default() += 1;
+            }
+        });
+    }
+    let dominant: HashMap<(String, String), &'static str> = type_counts.into_iter()
+        .filter_map(|(field, counts)| {
+            let total: usize = counts.values().sum();
+            let (type_name, count) = counts.into_iter().max_by_key(|(_, count)| *count)?;
+            (count * 2 > total).then_some((field, type_name))
+        })
+        .collect();
+
+    for document in documents.iter_mut() {
+        let collection = document.qualified_collection();
+        let path = document.document_path();
+        coerce_fields(&mut document.data, "", &|field_path| dominant.get(&(collection.clone(), field_path.to_string())).copied(), &path, repairs);
+    }
+}
+
+/// Visit every field and map subfield with its dotted path
+fn count_types(fields: &HashMap<String, FirestoreValue>, prefix: &str, visit: &mut impl FnMut(String, &FirestoreValue)) {
+    for (name, value) in fields {
+        let field_path = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+        if let FirestoreValue::Map(nested) = value {
+            count_types(nested, &field_path, visit);
+        }
+        visit(field_path, value);
+    }
+}
+
+fn coerce_fields(
+    fields: &mut HashMap<String, FirestoreValue>,
+    prefix: &str,
+    dominant: &dyn Fn(&str) -> Option<&'static str>,
+    document_path: &str,
+    repairs: &mut Vec<RepairAction>,
+) {
+    let mut names: Vec<String> = fields.keys().cloned().collect();
+    names.sort();
+    for name in names {
+        let field_path = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+        let Some(value) = fields.get_mut(&name) else { continue };
+        if let FirestoreValue::Map(nested) = value {
+            coerce_fields(nested, &field_path, dominant, document_path, repairs);
+            continue;
+        }
+        let Some(target) = dominant(&field_path).filter(|target| *target != value.type_name()) else { continue };
+        if let Some(coerced) = coerce(value, target) {
+            repairs.push(RepairAction {
+                fix: RepairFix::CoerceTypes,
+                document_path: Some(document_path.to_string()),
+                description: format!(
+                    "Coerced field '{}' from {} {} to {}",
+                    field_path, value.type_name(), value.to_json(), target
+                ),
+            });
+            *value = coerced;
+        }
+    }
+}
+
+/// Lossless conversion of a value to the type named by
+/// [`FirestoreValue::type_name`], for the glitches seen in exports
+fn coerce(value: &FirestoreValue, target: &str) -> Option<FirestoreValue> {
+    // Doubles represent integers exactly up to 2^53
+    const MAX_EXACT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+    match (value, target) {
+        (FirestoreValue::String(s), "integer") => s.trim().parse().ok().map(FirestoreValue::Integer),
+        (FirestoreValue::Double(d), "integer") if d.fract() == 0.0 && d.abs() <= MAX_EXACT_INTEGER => {
+            Some(FirestoreValue::Integer(*d as i64))
+        }
+        (FirestoreValue::String(s), "number") => s.trim().parse::<f64>().ok()
+            .filter(|d| d.is_finite())
+            .map(FirestoreValue::Double),
+        (FirestoreValue::Integer(i), "number") if (*i as f64).abs() <= MAX_EXACT_INTEGER => {
+            Some(FirestoreValue::Double(*i as f64))
+        }
+        (FirestoreValue::String(s), "boolean") => match s.trim() {
+            "true" => Some(FirestoreValue::Boolean(true)),
+            "false" => Some(FirestoreValue::Boolean(false)),
+            _ => None,
+        },
+        (FirestoreValue::String(s), "timestamp") => chrono::DateTime::parse_from_rfc3339(s.trim()).ok()
+            .map(|dt| FirestoreValue::Timestamp(dt.with_timezone(&chrono::Utc))),
+        (FirestoreValue::String(s), "reference") => {
+            let reference = FirestoreValue::Reference(s.clone());
+            let segments = crate::types::relative_document_path(s).split('/').count();
+            (segments.is_multiple_of(2) && reference.reference_target().is_some()).then_some(reference)
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,149 @@
+// Salvage reporting for partially corrupted LevelDB backups
+use crate::error::{FireupError, ErrorContext};
+use crate::protos::google::datastore::v1::Key;
+use prost::Message;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+/// A byte range the reader skipped while resyncing to the next block
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedRange {
+    pub file_path: String,
+    /// Offset of the first skipped byte
+    pub start: u64,
+    /// Offset one past the last skipped byte
+    pub end: u64,
+    pub reason: String,
+    /// Collection whose data was in the range, when it can still be told
+    pub collection: Option<String>,
+}
+
+/// A record that could not be recovered
+#[derive(Debug, Clone, Serialize)]
+pub struct DroppedRecord {
+    pub file_path: String,
+    /// Offset of the first fragment of the record
+    pub offset: u64,
+    /// Bytes of the record that were read before it was dropped
+    pub bytes: usize,
+    pub reason: String,
+    /// Collection the record belonged to, when it can still be told
+    pub collection: Option<String>,
+}
+
+/// Everything lost (and kept) while salvaging a corrupted backup
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SalvageReport {
+    pub skipped_ranges: Vec<SkippedRange>,
+    pub dropped_records: Vec<DroppedRecord>,
+    /// Collections with at least one dropped record
+    pub affected_collections: BTreeSet<String>,
+    /// Complete records recovered
+    pub recovered_records: usize,
+}
+
+impl SalvageReport {
+    /// Create an empty report
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a skipped byte range
+    pub fn add_skipped_range(&mut self, range: SkippedRange) {
+        if let Some(collection) = &range.collection {
+            self.affected_collections.insert(collection.clone());
+        }
+        self.skipped_ranges.push(range);
+    }
+
+    /// Record a dropped record
+    pub fn add_dropped_record(&mut self, record: DroppedRecord) {
+        if let Some(collection) = &record.collection {
+            self.affected_collections.insert(collection.clone());
+        }
+        self.dropped_records.push(record);
+    }
+
+    /// Add everything recorded in another report (e.g. of another shard)
+    pub fn merge(&mut self, other: SalvageReport) {
+        self.skipped_ranges.extend(other.skipped_ranges);
+        self.dropped_records.extend(other.dropped_records);
+        self.affected_collections.extend(other.affected_collections);
+        self.recovered_records += other.recovered_records;
+    }
+
+    /// Total number of bytes skipped
+    pub fn skipped_bytes(&self) -> u64 {
+        self.skipped_ranges.iter().map(|r| r.end - r.start).sum()
+    }
+
+    /// Whether anything was lost
+    pub fn is_clean(&self) -> bool {
+        self.skipped_ranges.is_empty() && self.dropped_records.is_empty()
+    }
+
+    /// One-line summary for logs
+    pub fn summary(&self) -> String {
+        format!(
+            "{} records recovered, {} records dropped, {} byte ranges skipped ({} bytes), {} collections affected",
+            self.recovered_records,
+            self.dropped_records.len(),
+            self.skipped_ranges.len(),
+            self.skipped_bytes(),
+            self.affected_collections.len()
+        )
+    }
+
+    /// Write the report as pretty-printed JSON
+    pub fn write_to(&self, path: &Path) -> Result<(), FireupError> {
+        let context = ErrorContext {
+            operation: "write_salvage_report".to_string(),
+            metadata: HashMap::from([
+                ("report_path".to_string(), path.display().to_string()),
+            ]),
+            timestamp: chrono::Utc::now(),
+            call_path: vec!["leveldb_parser::salvage::SalvageReport".to_string()],
+        };
+
+        let json = serde_json::to_string_pretty(self).map_err(|e| FireupError::leveldb_parse(
+            format!("Failed to serialize salvage report: {}", e),
+            context.clone(),
+        ))?;
+        std::fs::write(path, json).map_err(|e| FireupError::leveldb_parse(
+            format!("Failed to write salvage report: {}", e),
+            context,
+        ))
+    }
+}
+
+/// Best-effort collection name of a (possibly truncated) record: either the
+/// collection in a JSON document name, or the kind of an entity key
+pub fn guess_collection(data: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(data);
+    if let Some(index) = text.find("/documents/") {
+        let collection = text[index + "/documents/".len()..]
+            .split(['/', '"'])
+            .next()
+            .filter(|c| !c.is_empty())?;
+        return Some(collection.to_string());
+    }
+
+    // Entity field 1 is the key; its kind is readable once the key is complete
+    let (&tag, rest) = data.split_first()?;
+    if tag != 0x0a {
+        return None;
+    }
+    let mut length = 0usize;
+    let mut header = 0;
+    for (index, byte) in rest.iter().take(5).enumerate() {
+        length |= ((byte & 0x7f) as usize) << (7 * index);
+        if byte & 0x80 == 0 {
+            header = index + 1;
+            break;
+        }
+    }
+    let key_bytes = rest.get(header..header + length).filter(|_| header > 0)?;
+    let key = Key::decode(key_bytes).ok()?;
+    key.path.last().map(|element| element.kind.clone()).filter(|kind| !kind.is_empty())
+}
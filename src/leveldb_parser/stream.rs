@@ -0,0 +1,438 @@
+// Streaming document parsing with bounded memory
+use crate::error::{FireupError, ErrorContext};
+use crate::leveldb_parser::parser::{
+    BackupFormat, BlockReader, FirestoreDocumentParser, LevelDBReader, LogRecord, RecordAssembler, ShardStats,
+};
+use crate::leveldb_parser::realtime::{self, RealtimeNode};
+use crate::leveldb_parser::salvage::SalvageReport;
+use crate::types::FirestoreDocument;
+use bytes::Bytes;
+use futures::stream::{self, Stream};
+use std::collections::{HashMap, VecDeque};
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tracing::{debug, info, warn};
+
+/// Entry of the queue of records read from the current block
+enum Pending {
+    Record(LogRecord),
+    /// The rest of the block was skipped in salvage mode
+    Gap,
+}
+
+/// A complete record read from a source, not yet decoded
+pub(crate) enum RawRecord {
+    LevelDb { shard: usize, index: usize, data: Bytes },
+    JsonLine { shard: usize, index: usize, value: serde_json::Value },
+    RealtimeNode { shard: usize, node: RealtimeNode },
+}
+
+impl RawRecord {
+    /// Index of the source the record was read from
+    fn shard(&self) -> usize {
+        match self {
+            RawRecord::LevelDb { shard, .. }
+            | RawRecord::JsonLine { shard, .. }
+            | RawRecord::RealtimeNode { shard, .. } => *shard,
+        }
+    }
+}
+
+/// Records of a source handed out but not yet counted, and whether the
+/// source has been read to the end (and in which format)
+#[derive(Default)]
+struct SourceProgress {
+    in_flight: usize,
+    exhausted: Option<bool>,
+}
+
+/// Read state of the source file currently being streamed
+enum SourceState {
+    LevelDb {
+        blocks: BlockReader,
+        pending: VecDeque<Pending>,
+        assembler: RecordAssembler,
+    },
+    JsonLines {
+        reader: BufReader<File>,
+        line: Vec<u8>,
+        line_index: usize,
+    },
+    /// Realtime Database export, already split into document nodes
+    RealtimeDatabase {
+        nodes: VecDeque<RealtimeNode>,
+    },
+}
+
+/// Documents of a backup, decoded one at a time.
+///
+/// Record-level decoding errors are yielded and the stream continues.
+/// Errors that make the rest of a file unreadable (I/O failures, broken
+/// record fragmentation) are yielded once and end the stream; see
+/// [`DocumentStream::is_aborted`]. In salvage mode corrupted data is
+/// skipped instead and recorded in [`DocumentStream::salvage_report`].
+pub struct DocumentStream {
+    parser: FirestoreDocumentParser,
+    sources: VecDeque<(String, Option<String>)>,
+    current: Option<SourceState>,
+    shards: Vec<ShardStats>,
+    progress: Vec<SourceProgress>,
+    salvage: Option<SalvageReport>,
+    aborted: bool,
+}
+
+impl DocumentStream {
+    /// Create a stream over the given `(file path, kind)` sources, read in order
+    pub fn new(parser: FirestoreDocumentParser, sources: Vec<(String, Option<String>)>) -> Self {
+        let salvage = parser.salvage_mode().then(SalvageReport::new);
+        Self {
+            parser,
+            sources: sources.into(),
+            current: None,
+            shards: Vec::new(),
+            progress: Vec::new(),
+            salvage,
+            aborted: false,
+        }
+    }
+
+    /// Decode the next document, or `None` once every source is exhausted
+    pub async fn next_document(&mut self) -> Option<Result<FirestoreDocument, FireupError>> {
+        loop {
+            let raw = match self.next_raw_record().await? {
+                Ok(raw) => raw,
+                Err(e) => return Some(Err(e)),
+            };
+            let outcome = self.parser.decode_raw_record(&raw);
+            if let Some(result) = self.record_outcome(&raw, outcome) {
+                return Some(result);
+            }
+        }
+    }
+
+    /// Read the next complete record without decoding it. Every error
+    /// returned here ends the stream.
+    pub(crate) async fn next_raw_record(&mut self) -> Option<Result<RawRecord, FireupError>> {
+        loop {
+            if self.aborted {
+                return None;
+            }
+
+            let Some(state) = self.current.as_mut() else {
+                let (path, kind) = self.sources.pop_front()?;
+                match self.open_source(path, kind).await {
+                    Ok(state) => self.current = Some(state),
+                    Err(e) => {
+                        self.aborted = true;
+                        return Some(Err(e));
+                    }
+                }
+                continue;
+            };
+            let shard = self.shards.len().saturating_sub(1);
+            let Some(stats) = self.shards.last_mut() else {
+                self.current = None;
+                continue;
+            };
+
+            match state {
+                SourceState::LevelDb { blocks, pending, assembler } => {
+                    let next = Self::next_record(blocks, pending, assembler, stats, self.salvage.as_mut()).await;
+                    if let Some(report) = self.salvage.as_mut() {
+                        for dropped in assembler.take_dropped() {
+                            report.add_dropped_record(dropped);
+                        }
+                    }
+                    match next {
+                        Ok(Some(data)) => {
+                            if let Some(report) = self.salvage.as_mut() {
+                                report.recovered_records += 1;
+                            }
+                            let index = stats.records_processed;
+                            stats.records_processed += 1;
+                            self.progress[shard].in_flight += 1;
+                            return Some(Ok(RawRecord::LevelDb { shard, index, data }));
+                        }
+                        Ok(None) => {
+                            self.current = None;
+                            self.source_exhausted(shard, false);
+                        }
+                        Err(e) => {
+                            self.aborted = true;
+                            return Some(Err(e));
+                        }
+                    }
+                }
+                SourceState::RealtimeDatabase { nodes } => match nodes.pop_front() {
+                    Some(node) => {
+                        stats.records_processed += 1;
+                        self.progress[shard].in_flight += 1;
+                        return Some(Ok(RawRecord::RealtimeNode { shard, node }));
+                    }
+                    None => {
+                        self.current = None;
+                        self.source_exhausted(shard, false);
+                    }
+                },
+                SourceState::JsonLines { reader, line, line_index } => {
+                    line.clear();
+                    match reader.read_until(b'\n', line).await {
+                        Ok(0) => {
+                            self.current = None;
+                            self.source_exhausted(shard, true);
+                        }
+                        Ok(_) => {
+                            let index = *line_index;
+                            *line_index += 1;
+                            // Every non-empty line is a record, decodable or not
+                            let trimmed = line.trim_ascii();
+                            if trimmed.is_empty() {
+                                continue;
+                            }
+                            stats.records_processed += 1;
+                            let Ok(trimmed) = std::str::from_utf8(trimmed) else { continue };
+                            match serde_json::from_str::<serde_json::Value>(trimmed) {
+                                Ok(value) => {
+                                    self.progress[shard].in_flight += 1;
+                                    return Some(Ok(RawRecord::JsonLine { shard, index, value }));
+                                }
+                                Err(e) => {
+                                    // Ignore lines that aren't valid JSON
+                                    debug!("Invalid JSON line {}: {}", index, e);
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            self.aborted = true;
+                            return Some(Err(FireupError::leveldb_parse(
+                                format!("Failed to read file for JSON parsing: {}", e),
+                                ErrorContext {
+                                    operation: "stream_documents".to_string(),
+                                    metadata: HashMap::from([
+                                        ("file_path".to_string(), stats.path.clone()),
+                                        ("line_index".to_string(), line_index.to_string()),
+                                    ]),
+                                    timestamp: chrono::Utc::now(),
+                                    call_path: vec!["leveldb_parser::stream::DocumentStream".to_string()],
+                                }
+                            )));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Count the decoding outcome of a record read by [`Self::next_raw_record`],
+    /// returning what the stream yields for it (nothing for non-document records)
+    pub(crate) fn record_outcome(
+        &mut self,
+        raw: &RawRecord,
+        outcome: Result<Option<FirestoreDocument>, FireupError>,
+    ) -> Option<Result<FirestoreDocument, FireupError>> {
+        let shard = raw.shard();
+        let stats = &mut self.shards[shard];
+        let result = match outcome {
+            Ok(Some(document)) => match self.parser.filter().apply(document) {
+                Some(document) => {
+                    stats.document_count += 1;
+                    Some(Ok(document))
+                }
+                None => {
+                    stats.documents_filtered += 1;
+                    None
+                }
+            },
+            Ok(None) => {
+                debug!("Skipped non-document record in {}", stats.path);
+                None
+            }
+            Err(e) => {
+                warn!("Failed to parse record in {}: {}", stats.path, e);
+                stats.error_count += 1;
+                Some(Err(e))
+            }
+        };
+
+        let progress = &mut self.progress[shard];
+        progress.in_flight = progress.in_flight.saturating_sub(1);
+        if progress.in_flight == 0 {
+            if let Some(json_lines) = progress.exhausted {
+                Self::finish_source(&self.shards[shard], json_lines);
+            }
+        }
+        result
+    }
+
+    /// Mark a source as read to the end, reporting on it once every record
+    /// handed out from it has been counted
+    fn source_exhausted(&mut self, shard: usize, json_lines: bool) {
+        let progress = &mut self.progress[shard];
+        progress.exhausted = Some(json_lines);
+        if progress.in_flight == 0 {
+            Self::finish_source(&self.shards[shard], json_lines);
+        }
+    }
+
+    /// Whether the stream ended early on an unrecoverable error
+    pub fn is_aborted(&self) -> bool {
+        self.aborted
+    }
+
+    /// Counts for every source opened so far
+    pub fn shard_stats(&self) -> &[ShardStats] {
+        &self.shards
+    }
+
+    /// What was skipped or dropped so far, when reading in salvage mode
+    pub fn salvage_report(&self) -> Option<&SalvageReport> {
+        self.salvage.as_ref()
+    }
+
+    /// Consume the stream, returning the per-source counts
+    pub fn into_shard_stats(self) -> Vec<ShardStats> {
+        self.shards
+    }
+
+    /// Adapt into a `futures::Stream` of documents
+    pub fn into_stream(self) -> impl Stream<Item = Result<FirestoreDocument, FireupError>> {
+        stream::unfold(self, |mut documents| async move {
+            let item = documents.next_document().await?;
+            Some((item, documents))
+        })
+    }
+
+    /// Open the next source file and detect its format
+    async fn open_source(&mut self, path: String, kind: Option<String>) -> Result<SourceState, FireupError> {
+        let reader = LevelDBReader::new(path)
+            .with_checksum_mode(self.parser.checksum_mode())
+            .with_salvage_mode(self.parser.salvage_mode());
+        let file_size = reader.file_size().await?;
+        let format = self.parser.detect_backup_format(&reader.file_path).await.unwrap_or(BackupFormat::LevelDb);
+
+        self.shards.push(ShardStats {
+            path: reader.file_path.clone(),
+            kind,
+            file_size,
+            blocks_processed: 0,
+            records_processed: 0,
+            document_count: 0,
+            error_count: 0,
+            documents_filtered: 0,
+        });
+        self.progress.push(SourceProgress::default());
+
+        match format {
+            BackupFormat::JsonLines => {
+                info!("Detected JSON Lines backup; parsing as JSON lines: {}", reader.file_path);
+                let file = File::open(&reader.file_path).await.map_err(|e| FireupError::leveldb_parse(
+                    format!("Failed to read file for JSON parsing: {}", e),
+                    ErrorContext {
+                        operation: "stream_documents".to_string(),
+                        metadata: HashMap::from([
+                            ("file_path".to_string(), reader.file_path.clone()),
+                        ]),
+                        timestamp: chrono::Utc::now(),
+                        call_path: vec!["leveldb_parser::stream::DocumentStream".to_string()],
+                    }
+                ))?;
+                Ok(SourceState::JsonLines {
+                    reader: BufReader::new(file),
+                    line: Vec::new(),
+                    line_index: 0,
+                })
+            }
+            BackupFormat::RealtimeDatabase => {
+                info!("Detected Realtime Database export: {}", reader.file_path);
+                let context = ErrorContext {
+                    operation: "stream_documents".to_string(),
+                    metadata: HashMap::from([
+                        ("file_path".to_string(), reader.file_path.clone()),
+                    ]),
+                    timestamp: chrono::Utc::now(),
+                    call_path: vec!["leveldb_parser::stream::DocumentStream".to_string()],
+                };
+                // The export is a single JSON tree and has to be read whole
+                let bytes = tokio::fs::read(&reader.file_path).await.map_err(|e| FireupError::leveldb_parse(
+                    format!("Failed to read Realtime Database export: {}", e),
+                    context.clone(),
+                ))?;
+                let tree: serde_json::Value = serde_json::from_slice(&bytes).map_err(|e| FireupError::leveldb_parse(
+                    format!("Invalid Realtime Database export: {}", e),
+                    context,
+                ))?;
+                drop(bytes);
+                let nodes = realtime::document_nodes(tree, self.parser.realtime_database_depth());
+                debug!("Realtime Database export {} holds {} document nodes", reader.file_path, nodes.len());
+                Ok(SourceState::RealtimeDatabase { nodes: nodes.into() })
+            }
+            // Export shards are LevelDB logs as well
+            BackupFormat::LevelDb | BackupFormat::FirestoreExport | BackupFormat::EmulatorExport => Ok(SourceState::LevelDb {
+                blocks: reader.open_blocks().await?,
+                pending: VecDeque::new(),
+                assembler: if reader.salvage_mode() {
+                    RecordAssembler::salvaging(reader.file_path.clone())
+                } else {
+                    RecordAssembler::new()
+                },
+            }),
+        }
+    }
+
+    /// Next complete (reassembled) record of a LevelDB source, reading
+    /// another block only when the current one is used up
+    async fn next_record(
+        blocks: &mut BlockReader,
+        pending: &mut VecDeque<Pending>,
+        assembler: &mut RecordAssembler,
+        stats: &mut ShardStats,
+        mut salvage: Option<&mut SalvageReport>,
+    ) -> Result<Option<Bytes>, FireupError> {
+        loop {
+            while let Some(entry) = pending.pop_front() {
+                match entry {
+                    Pending::Record(record) => {
+                        if let Some(complete) = assembler.push(&record)? {
+                            return Ok(Some(complete));
+                        }
+                    }
+                    // Fragments on either side of a gap must not be joined
+                    Pending::Gap => assembler.interrupt("Fragmented record continued in skipped data"),
+                }
+            }
+
+            match blocks.next_block().await? {
+                Some(block) => {
+                    stats.blocks_processed += 1;
+                    let has_gap = !block.skipped.is_empty();
+                    if has_gap {
+                        stats.error_count += block.skipped.len();
+                    }
+                    if let Some(report) = salvage.as_deref_mut() {
+                        for range in block.skipped {
+                            report.add_skipped_range(range);
+                        }
+                    }
+                    pending.extend(block.records.into_iter().map(Pending::Record));
+                    if has_gap {
+                        pending.push_back(Pending::Gap);
+                    }
+                }
+                None => {
+                    assembler.finish();
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    fn finish_source(stats: &ShardStats, json_lines: bool) {
+        if json_lines && stats.document_count == 0 {
+            warn!("JSON Lines parsing did not produce any documents: {}", stats.path);
+        }
+        debug!(
+            "Parsed {}: {} documents, {} records, {} errors",
+            stats.path, stats.document_count, stats.records_processed, stats.error_count
+        );
+    }
+}
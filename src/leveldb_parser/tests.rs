@@ -1,7 +1,8 @@
 // Unit tests for LevelDB parser functionality
 use super::*;
 use crate::error::{FireupError, ErrorContext};
-use crate::leveldb_parser::parser::{LevelDBReader, FirestoreDocumentParser, RecordType};
+use crate::leveldb_parser::parser::{BackupFormat, LevelDBReader, FirestoreDocumentParser, RecordType};
+use crate::types::FirestoreValue;
 use crate::leveldb_parser::validator::LoggingProgressCallback;
 use tempfile::TempDir;
 use tokio::fs;
@@ -16,12 +17,9 @@ fn create_record_header(record_type: RecordType, data_length: u16, checksum: u32
     header
 }
 
-/// Helper function to calculate CRC32 checksum for test data
+/// Helper function to calculate the masked CRC32C checksum for test data
 fn calculate_test_checksum(record_type: u8, data: &[u8]) -> u32 {
-    let mut crc = crc32fast::Hasher::new();
-    crc.update(&[record_type]);
-    crc.update(data);
-    crc.finalize()
+    crate::leveldb_parser::parser::masked_crc32c(record_type, data)
 }
 
 /// Helper function to create a valid LevelDB block with test data
@@ -727,4 +725,1533 @@ mod tests {
         assert!(parse_result.collections.contains(&"users".to_string()));
         assert!(parse_result.collections.contains(&"posts".to_string()));
     }
-}
\ No newline at end of file
+
+    fn entity_value(value_type: crate::protos::google::datastore::v1::value::ValueType) -> crate::protos::google::datastore::v1::Value {
+        crate::protos::google::datastore::v1::Value { value_type: Some(value_type) }
+    }
+
+    fn entity_key(project: &str, path: &[(&str, &str)]) -> crate::protos::google::datastore::v1::Key {
+        use crate::protos::google::datastore::v1::{key, Key, PartitionId};
+
+        Key {
+            partition_id: Some(PartitionId {
+                project_id: project.to_string(),
+                database_id: String::new(),
+                namespace_id: String::new(),
+            }),
+            path: path.iter()
+                .map(|(kind, name)| key::PathElement {
+                    kind: kind.to_string(),
+                    id_type: Some(key::path_element::IdType::Name(name.to_string())),
+                })
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_firestore_document_parser_protobuf_entity() {
+        use crate::protos::google::datastore::v1::{value::ValueType, ArrayValue, Entity};
+        use prost::Message;
+
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+        let mut entity = Entity {
+            key: Some(entity_key("test", &[("users", "user1")])),
+            properties: std::collections::HashMap::new(),
+        };
+        entity.properties.insert("name".to_string(), entity_value(ValueType::StringValue("Alice".to_string())));
+        entity.properties.insert("age".to_string(), entity_value(ValueType::IntegerValue(30)));
+        entity.properties.insert("score".to_string(), entity_value(ValueType::DoubleValue(9.5)));
+        entity.properties.insert("active".to_string(), entity_value(ValueType::BooleanValue(true)));
+        entity.properties.insert("nothing".to_string(), entity_value(ValueType::NullValue(0)));
+        entity.properties.insert("avatar".to_string(), entity_value(ValueType::BlobValue(b"hi".to_vec())));
+        entity.properties.insert("joined".to_string(), entity_value(ValueType::TimestampValue(prost_types::Timestamp {
+            seconds: 1_672_531_200,
+            nanos: 123_456_789,
+        })));
+        entity.properties.insert("manager".to_string(), entity_value(ValueType::KeyValue(
+            entity_key("test", &[("users", "user2")])
+        )));
+        entity.properties.insert("tags".to_string(), entity_value(ValueType::ArrayValue(ArrayValue {
+            values: vec![
+                entity_value(ValueType::StringValue("a".to_string())),
+                entity_value(ValueType::IntegerValue(2)),
+            ],
+        })));
+        entity.properties.insert("address".to_string(), entity_value(ValueType::EntityValue(Entity {
+            key: None,
+            properties: std::collections::HashMap::from([
+                ("city".to_string(), entity_value(ValueType::StringValue("Paris".to_string()))),
+            ]),
+        })));
+        let encoded = entity.encode_to_vec();
+
+        let file_path = create_test_leveldb_file(
+            &temp_dir,
+            "entity.leveldb",
+            vec![(RecordType::Full, encoded.as_slice())]
+        ).await.expect("Failed to create test file");
+
+        let parser = FirestoreDocumentParser::new(file_path);
+        let parse_result = parser.parse_documents().await.expect("Failed to parse entity file");
+
+        assert_eq!(parse_result.documents.len(), 1);
+        let document = &parse_result.documents[0];
+        assert_eq!(document.id, "user1");
+        assert_eq!(document.collection, "users");
+        assert_eq!(document.metadata.path, "users/user1");
+        assert_eq!(document.data["name"], FirestoreValue::String("Alice".to_string()));
+        assert_eq!(document.data["age"], FirestoreValue::Integer(30));
+        assert_eq!(document.data["score"], FirestoreValue::Double(9.5));
+        assert_eq!(document.data["active"], FirestoreValue::Boolean(true));
+        assert_eq!(document.data["nothing"], FirestoreValue::Null);
+        assert_eq!(document.data["avatar"], FirestoreValue::Bytes(b"hi".to_vec()));
+        assert_eq!(document.data["avatar"].to_json(), serde_json::json!("aGk="));
+        let FirestoreValue::Timestamp(joined) = &document.data["joined"] else {
+            panic!("expected a timestamp, got {:?}", document.data["joined"]);
+        };
+        assert_eq!(joined.timestamp_subsec_nanos(), 123_456_789);
+        assert_eq!(document.data["joined"].to_json(), serde_json::json!("2023-01-01T00:00:00.123456789Z"));
+        assert_eq!(
+            document.data["manager"],
+            FirestoreValue::Reference("projects/test/databases/(default)/documents/users/user2".to_string())
+        );
+        assert_eq!(document.data["tags"].to_json(), serde_json::json!(["a", 2]));
+        assert_eq!(document.data["address"].to_json(), serde_json::json!({"city": "Paris"}));
+    }
+
+    #[tokio::test]
+    async fn test_entity_decoder_numeric_ids_and_keyless_entities() {
+        use crate::leveldb_parser::entity_decoder::EntityDecoder;
+        use crate::protos::google::datastore::v1::{key, Entity, Key};
+        use prost::Message;
+
+        let decoder = EntityDecoder::new();
+
+        let entity = Entity {
+            key: Some(Key {
+                partition_id: None,
+                path: vec![key::PathElement {
+                    kind: "orders".to_string(),
+                    id_type: Some(key::path_element::IdType::Id(42)),
+                }],
+            }),
+            properties: std::collections::HashMap::new(),
+        };
+        let document = decoder.decode(&entity.encode_to_vec(), 0)
+            .expect("decode failed")
+            .expect("expected a document");
+        assert_eq!(document.id, "42");
+        assert_eq!(document.collection, "orders");
+
+        let keyless = Entity { key: None, properties: std::collections::HashMap::new() };
+        assert!(decoder.decode(&keyless.encode_to_vec(), 1).expect("decode failed").is_none());
+    }
+
+    /// Encode a length-delimited protobuf field
+    fn proto_field(field: u8, payload: &[u8]) -> Vec<u8> {
+        let mut encoded = vec![(field << 3) | 2, payload.len() as u8];
+        encoded.extend_from_slice(payload);
+        encoded
+    }
+
+    /// Build an export directory with a `users` kind split over two shards
+    /// and a single-shard `posts` kind
+    async fn create_test_export(temp_dir: &TempDir) -> std::path::PathBuf {
+        let root = temp_dir.path().join("firestore_export");
+        for kind in ["users", "posts"] {
+            std::fs::create_dir_all(root.join(format!("all_namespaces/kind_{}", kind)))
+                .expect("Failed to create kind dir");
+        }
+
+        let overall: Vec<u8> = ["users", "posts"].iter()
+            .flat_map(|kind| proto_field(1, &proto_field(
+                2,
+                format!("all_namespaces/kind_{0}/all_namespaces_kind_{0}.export_metadata", kind).as_bytes(),
+            )))
+            .collect();
+        create_test_leveldb_file(
+            temp_dir,
+            "firestore_export/firestore_export.overall_export_metadata",
+            vec![(RecordType::Full, &overall)],
+        ).await.expect("Failed to create overall metadata");
+
+        for (kind, shards) in [("users", vec!["output-0", "output-1"]), ("posts", vec!["output-0"])] {
+            let listing: Vec<u8> = shards.iter()
+                .flat_map(|shard| proto_field(2, &proto_field(2, shard.as_bytes())))
+                .collect();
+            std::fs::write(
+                root.join(format!("all_namespaces/kind_{0}/all_namespaces_kind_{0}.export_metadata", kind)),
+                listing,
+            ).expect("Failed to write kind metadata");
+        }
+
+        let user1 = r#"{"name": "projects/test/databases/(default)/documents/users/user1", "fields": {"name": {"stringValue": "Alice"}}}"#;
+        let user2 = r#"{"name": "projects/test/databases/(default)/documents/users/user2", "fields": {"name": {"stringValue": "Bob"}}}"#;
+        let user3 = r#"{"name": "projects/test/databases/(default)/documents/users/user3", "fields": {"name": {"stringValue": "Carol"}}}"#;
+        let post1 = r#"{"name": "projects/test/databases/(default)/documents/posts/post1", "fields": {"title": {"stringValue": "Hello"}}}"#;
+
+        let shards: Vec<(&str, Vec<&str>)> = vec![
+            ("users/output-0", vec![user1, user2]),
+            ("users/output-1", vec![user3]),
+            ("posts/output-0", vec![post1]),
+        ];
+        for (shard, docs) in shards {
+            let (kind, file) = shard.split_once('/').unwrap();
+            create_test_leveldb_file(
+                temp_dir,
+                &format!("firestore_export/all_namespaces/kind_{}/{}", kind, file),
+                docs.iter().map(|d| (RecordType::Full, d.as_bytes())).collect(),
+            ).await.expect("Failed to create shard");
+        }
+
+        root
+    }
+
+    #[tokio::test]
+    async fn test_export_manifest_discovers_kinds_and_shards() {
+        use crate::leveldb_parser::export::ExportManifest;
+
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = create_test_export(&temp_dir).await;
+
+        // An unlisted shard is still picked up, with a warning
+        create_test_leveldb_file(
+            &temp_dir,
+            "firestore_export/all_namespaces/kind_posts/output-1",
+            vec![],
+        ).await.expect("Failed to create shard");
+
+        let manifest = ExportManifest::discover(temp_dir.path())
+            .expect("Discovery failed")
+            .expect("Export not found");
+
+        assert_eq!(manifest.root, root);
+        assert!(manifest.overall_metadata.is_some());
+        let kinds: Vec<(&str, usize)> = manifest.kinds.iter()
+            .map(|k| (k.name.as_str(), k.shards.len()))
+            .collect();
+        assert_eq!(kinds, vec![("posts", 2), ("users", 2)]);
+        assert!(manifest.kinds.iter().all(|k| k.metadata_file.is_some()));
+        assert_eq!(manifest.shard_count(), 4);
+        assert_eq!(manifest.warnings.len(), 1);
+        assert!(manifest.warnings[0].contains("not listed"));
+
+        // Plain files are not exports
+        let file_path = root.join("all_namespaces/kind_users/output-0");
+        assert!(ExportManifest::discover(&file_path).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_export_manifest_reads_emulator_fixture() {
+        use crate::leveldb_parser::export::ExportManifest;
+
+        let fixture = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/.firestore-data");
+        let manifest = ExportManifest::discover(&fixture)
+            .expect("Discovery failed")
+            .expect("Export not found");
+
+        assert!(manifest.overall_metadata.is_some());
+        assert_eq!(manifest.kinds.len(), 1);
+        assert_eq!(manifest.kinds[0].name, "all_kinds");
+        assert_eq!(manifest.shard_count(), 1);
+        assert!(manifest.warnings.is_empty());
+        assert_eq!(manifest.format(), BackupFormat::EmulatorExport);
+        let emulator = manifest.emulator.as_ref().expect("emulator export");
+        assert_eq!(emulator.cli_version.as_deref(), Some("14.23.0"));
+        assert_eq!(emulator.firestore_version.as_deref(), Some("1.19.8"));
+    }
+
+    #[tokio::test]
+    async fn test_parse_emulator_export_directory() {
+        use crate::leveldb_parser::export::{ExportManifest, EMULATOR_METADATA_FILE};
+
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        create_test_export(&temp_dir).await;
+        // Data of other emulators is not part of the Firestore export
+        create_test_leveldb_file(&temp_dir, "output-0", vec![]).await.expect("Failed to create stray file");
+        std::fs::write(temp_dir.path().join(EMULATOR_METADATA_FILE), r#"{
+            "version": "13.0.0",
+            "firestore": {
+                "version": "1.18.2",
+                "path": "firestore_export",
+                "metadata_file": "firestore_export/firestore_export.overall_export_metadata"
+            },
+            "auth": {"version": "13.0.0", "path": "auth_export"}
+        }"#).expect("Failed to write emulator metadata");
+
+        let parser = FirestoreDocumentParser::new(temp_dir.path().to_string_lossy().to_string());
+        assert_eq!(parser.backup_format().await, BackupFormat::EmulatorExport);
+        let result = parser.parse_documents().await.expect("Parsing failed");
+        let ids: Vec<&str> = result.documents.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(ids, vec!["post1", "user1", "user2", "user3"]);
+        assert_eq!(result.metadata.format, BackupFormat::EmulatorExport);
+        assert_eq!(result.metadata.shards.len(), 3);
+
+        // Without the emulator metadata the directory is a plain export
+        std::fs::remove_file(temp_dir.path().join(EMULATOR_METADATA_FILE)).unwrap();
+        let manifest = ExportManifest::discover(temp_dir.path()).unwrap().expect("Export not found");
+        assert_eq!(manifest.format(), BackupFormat::FirestoreExport);
+
+        // Emulator exports without Firestore data are rejected
+        std::fs::write(
+            temp_dir.path().join(EMULATOR_METADATA_FILE),
+            r#"{"version": "13.0.0", "auth": {"version": "13.0.0", "path": "auth_export"}}"#,
+        ).unwrap();
+        let error = ExportManifest::discover(temp_dir.path()).expect_err("No Firestore data");
+        assert!(error.to_string().contains("contains no Firestore data"));
+    }
+
+    #[tokio::test]
+    async fn test_parse_multi_shard_export_directory() {
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        create_test_export(&temp_dir).await;
+
+        let parser = FirestoreDocumentParser::new(temp_dir.path().to_string_lossy().to_string());
+        let parse_result = parser.parse_documents().await.expect("Parsing failed");
+
+        assert_eq!(parse_result.documents.len(), 4);
+        assert!(parse_result.errors.is_empty());
+
+        let metadata = &parse_result.metadata;
+        assert_eq!(metadata.document_count, 4);
+        assert_eq!(metadata.collection_count, 2);
+        assert_eq!(metadata.shards.len(), 3);
+        assert_eq!(metadata.file_size, 3 * 32768);
+        assert_eq!(metadata.records_processed, 4);
+
+        let users = metadata.kinds.iter().find(|k| k.kind == "users").expect("users kind");
+        assert_eq!(users.shard_count, 2);
+        assert_eq!(users.document_count, 3);
+        let posts = metadata.kinds.iter().find(|k| k.kind == "posts").expect("posts kind");
+        assert_eq!(posts.shard_count, 1);
+        assert_eq!(posts.document_count, 1);
+
+        let shard_docs: Vec<usize> = metadata.shards.iter().map(|s| s.document_count).collect();
+        assert_eq!(shard_docs, vec![1, 2, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_documents_reads_blocks_incrementally() {
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+        let doc1 = r#"{"name": "projects/test/databases/(default)/documents/users/user1", "fields": {"name": {"stringValue": "Alice"}}}"#;
+        let doc2 = r#"{"name": "projects/test/databases/(default)/documents/users/user2", "fields": {"name": {"stringValue": "Bob"}}}"#;
+
+        // Two blocks, one document each; the second document is fragmented
+        // across a First record in block one and a Last record in block two
+        let (head, tail) = doc2.as_bytes().split_at(20);
+        let mut file_bytes = Vec::new();
+        for (record_type, data) in [(RecordType::Full, doc1.as_bytes()), (RecordType::First, head)] {
+            let checksum = calculate_test_checksum(record_type as u8, data);
+            file_bytes.extend(create_record_header(record_type, data.len() as u16, checksum));
+            file_bytes.extend_from_slice(data);
+        }
+        file_bytes.resize(32768, 0);
+        let checksum = calculate_test_checksum(RecordType::Last as u8, tail);
+        file_bytes.extend(create_record_header(RecordType::Last, tail.len() as u16, checksum));
+        file_bytes.extend_from_slice(tail);
+
+        let file_path = temp_dir.path().join("stream.leveldb");
+        fs::write(&file_path, &file_bytes).await.expect("Failed to write test file");
+
+        let parser = FirestoreDocumentParser::new(file_path.to_string_lossy().to_string());
+        let mut stream = parser.stream_documents();
+
+        let first = stream.next_document().await.expect("first document").expect("decoded");
+        assert_eq!(first.id, "user1");
+        assert_eq!(stream.shard_stats()[0].blocks_processed, 1);
+
+        let second = stream.next_document().await.expect("second document").expect("decoded");
+        assert_eq!(second.id, "user2");
+        assert_eq!(second.data.get("name"), Some(&FirestoreValue::String("Bob".to_string())));
+        assert_eq!(stream.shard_stats()[0].blocks_processed, 2);
+
+        assert!(stream.next_document().await.is_none());
+        assert!(!stream.is_aborted());
+        let stats = stream.into_shard_stats();
+        assert_eq!(stats[0].document_count, 2);
+        assert_eq!(stats[0].records_processed, 2);
+    }
+
+    #[tokio::test]
+    async fn test_stream_documents_over_export_and_json_lines() {
+        use futures::StreamExt;
+
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        create_test_export(&temp_dir).await;
+
+        let parser = FirestoreDocumentParser::new(temp_dir.path().to_string_lossy().to_string());
+        let ids: Vec<String> = parser.stream_documents().into_stream()
+            .map(|item| item.expect("decoded").id)
+            .collect()
+            .await;
+        assert_eq!(ids, vec!["post1", "user1", "user2", "user3"]);
+
+        let json_path = temp_dir.path().join("documents.jsonl");
+        fs::write(&json_path, concat!(
+            r#"{"name": "projects/test/databases/(default)/documents/users/user1", "fields": {}}"#, "\n",
+            "not json\n",
+            "\n",
+            r#"{"name": "projects/test/databases/(default)/documents/users/user2", "fields": {}}"#, "\n",
+        )).await.expect("Failed to write JSON lines");
+
+        let parser = FirestoreDocumentParser::new(json_path.to_string_lossy().to_string());
+        let mut stream = parser.stream_documents();
+        let mut count = 0;
+        while let Some(item) = stream.next_document().await {
+            item.expect("decoded");
+            count += 1;
+        }
+        assert_eq!(count, 2);
+        assert_eq!(stream.shard_stats()[0].blocks_processed, 0);
+        // One record per non-empty line, whether streamed or parsed at once
+        assert_eq!(stream.shard_stats()[0].records_processed, 3);
+        let parse_result = parser.parse_documents().await.expect("Failed to parse JSON lines");
+        assert_eq!(parse_result.documents.len(), 2);
+        assert_eq!(parse_result.metadata.records_processed, 3);
+    }
+
+    #[tokio::test]
+    async fn test_parser_records_parent_paths() {
+        use crate::leveldb_parser::entity_decoder::EntityDecoder;
+        use crate::protos::google::datastore::v1::Entity;
+
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+        let user = r#"{"name": "projects/test/databases/(default)/documents/users/u1", "fields": {}}"#;
+        let order = r#"{"name": "projects/test/databases/(default)/documents/users/u1/orders/o1", "fields": {}}"#;
+        let item = r#"{"name": "projects/test/databases/(default)/documents/users/u1/orders/o1/items/i1", "fields": {}}"#;
+        let file_path = create_test_leveldb_file(
+            &temp_dir,
+            "nested.leveldb",
+            vec![
+                (RecordType::Full, user.as_bytes()),
+                (RecordType::Full, order.as_bytes()),
+                (RecordType::Full, item.as_bytes()),
+            ],
+        ).await.expect("Failed to create test file");
+
+        let parse_result = FirestoreDocumentParser::new(file_path).parse_documents().await.unwrap();
+        let parents: Vec<Option<&str>> = parse_result.documents.iter()
+            .map(|d| d.metadata.parent_path.as_deref())
+            .collect();
+        assert_eq!(parents, vec![None, Some("users/u1"), Some("users/u1/orders/o1")]);
+        assert_eq!(parse_result.documents[2].qualified_collection(), "users_orders_items");
+        assert_eq!(
+            parse_result.documents[2].parent_reference(),
+            Some(("users_orders".to_string(), "o1".to_string()))
+        );
+
+        let entity = Entity {
+            key: Some(entity_key("test", &[("users", "u1"), ("orders", "o1")])),
+            properties: Default::default(),
+        };
+        let document = EntityDecoder::new().entity_to_document(&entity).unwrap();
+        assert_eq!(document.metadata.path, "users/u1/orders/o1");
+        assert_eq!(document.metadata.parent_path.as_deref(), Some("users/u1"));
+    }
+
+    #[test]
+    fn test_masked_crc32c_matches_real_export() {
+        use crate::leveldb_parser::parser::masked_crc32c;
+
+        // Known CRC32C check value, masked as LevelDB does
+        let crc = crc32c::crc32c(b"123456789");
+        assert_eq!(crc, 0xe306_9283);
+
+        // The first record of the emulator fixture carries a valid checksum
+        let fixture = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/.firestore-data/firestore_export/all_namespaces/all_kinds/output-0");
+        let bytes = std::fs::read(fixture).expect("Failed to read fixture");
+        let checksum = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let length = u16::from_le_bytes([bytes[4], bytes[5]]) as usize;
+        assert_eq!(masked_crc32c(bytes[6], &bytes[7..7 + length]), checksum);
+    }
+
+    #[tokio::test]
+    async fn test_leveldb_reader_checksum_modes() {
+        use crate::leveldb_parser::parser::ChecksumMode;
+
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let file_path = create_corrupted_leveldb_file(&temp_dir, "corrupted.leveldb")
+            .await
+            .expect("Failed to create corrupted test file");
+
+        let strict = LevelDBReader::new(file_path.clone()).read_file().await;
+        assert!(strict.unwrap_err().to_string().contains("Checksum mismatch"));
+
+        let warn = LevelDBReader::new(file_path.clone())
+            .with_checksum_mode(ChecksumMode::Warn)
+            .read_file()
+            .await
+            .expect("Warn mode should keep reading");
+        assert_eq!(warn[0].records.len(), 1);
+        assert_eq!(warn[0].records[0].checksum_valid, Some(false));
+        assert_eq!(&warn[0].records[0].data[..], b"invalid data");
+
+        let off = LevelDBReader::new(file_path)
+            .with_checksum_mode(ChecksumMode::Off)
+            .read_file()
+            .await
+            .expect("Off mode should keep reading");
+        assert_eq!(off[0].records[0].checksum_valid, None);
+    }
+
+    #[tokio::test]
+    async fn test_backup_validator_counts_checksum_failures() {
+        use crate::leveldb_parser::parser::ChecksumMode;
+
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let file_path = create_corrupted_leveldb_file(&temp_dir, "corrupted.leveldb")
+            .await
+            .expect("Failed to create corrupted test file");
+
+        let strict = BackupValidatorImpl::new(file_path.clone())
+            .validate_comprehensive(&file_path)
+            .await
+            .unwrap();
+        assert!(!strict.is_valid);
+        assert!(strict.errors.iter().any(|e| e.contains("Checksum mismatch")));
+
+        let warn = BackupValidatorImpl::new(file_path.clone())
+            .with_checksum_mode(ChecksumMode::Warn)
+            .validate_comprehensive(&file_path)
+            .await
+            .unwrap();
+        assert_eq!(warn.integrity_info.checksum_failures, 1);
+        assert!(warn.warnings.iter().any(|w| w.contains("failed checksum verification")));
+    }
+
+    #[tokio::test]
+    async fn test_backup_validator_counts_checksum_failures_in_every_shard() {
+        use crate::leveldb_parser::parser::ChecksumMode;
+        use crate::leveldb_parser::writer::LevelDBWriter;
+        use crate::types::FirestoreDocument;
+
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let kind_dir = "export/all_namespaces/kind_users";
+        std::fs::create_dir_all(temp_dir.path().join(kind_dir)).expect("Failed to create kind directory");
+
+        let mut document = FirestoreDocument::new("fine".to_string(), "users".to_string(), "users/fine".to_string());
+        document.add_field("note".to_string(), FirestoreValue::String("x".repeat(1024)));
+        let mut writer = LevelDBWriter::create(&temp_dir.path().join(kind_dir).join("output-0"))
+            .expect("Failed to create writer");
+        writer.add_document(&document).expect("Failed to write document");
+        writer.finish().expect("Failed to finish log");
+        for shard in ["output-1", "output-2"] {
+            create_corrupted_leveldb_file(&temp_dir, &format!("{}/{}", kind_dir, shard))
+                .await
+                .expect("Failed to create corrupted shard");
+        }
+        let root = temp_dir.path().join("export").to_string_lossy().to_string();
+
+        let warn = BackupValidatorImpl::new(root.clone())
+            .with_checksum_mode(ChecksumMode::Warn)
+            .validate_comprehensive(&root)
+            .await
+            .unwrap();
+        assert_eq!(warn.integrity_info.checksum_failures, 2);
+        assert!(warn.warnings.iter().any(|w| w.contains("2 of") && w.contains("failed checksum verification")));
+
+        let strict = BackupValidatorImpl::new(root.clone())
+            .validate_comprehensive(&root)
+            .await
+            .unwrap();
+        assert!(!strict.is_valid);
+        for shard in ["output-1", "output-2"] {
+            assert!(
+                strict.errors.iter().any(|e| e.contains(shard) && e.contains("Checksum mismatch")),
+                "no checksum error for {}: {:?}", shard, strict.errors
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_salvage_mode_recovers_intact_records() {
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+        let user1 = r#"{"name": "projects/test/databases/(default)/documents/users/user1", "fields": {}}"#;
+        let user2 = r#"{"name": "projects/test/databases/(default)/documents/users/user2", "fields": {}}"#;
+        let order = r#"{"name": "projects/test/databases/(default)/documents/orders/order1", "fields": {"total": {"integerValue": "3"}}}"#;
+        let post = r#"{"name": "projects/test/databases/(default)/documents/posts/post1", "fields": {}}"#;
+        let (order_head, order_rest) = order.as_bytes().split_at(72);
+        let (order_middle, order_tail) = order_rest.split_at(10);
+
+        fn append(file_bytes: &mut Vec<u8>, record_type: RecordType, data: &[u8], checksum: u32) {
+            file_bytes.extend(create_record_header(record_type, data.len() as u16, checksum));
+            file_bytes.extend_from_slice(data);
+        }
+        let valid = |record_type: RecordType, data: &[u8]| calculate_test_checksum(record_type as u8, data);
+
+        // Block 0: an intact document, then the start of a fragmented one
+        let mut file_bytes = Vec::new();
+        append(&mut file_bytes, RecordType::Full, user1.as_bytes(), valid(RecordType::Full, user1.as_bytes()));
+        append(&mut file_bytes, RecordType::First, order_head, valid(RecordType::First, order_head));
+        file_bytes.resize(32768, 0);
+        // Block 1: the middle fragment is corrupted
+        append(&mut file_bytes, RecordType::Middle, order_middle, 0xdeadbeef);
+        file_bytes.resize(65536, 0);
+        // Block 2: the orphaned last fragment, an intact document, and a
+        // fragmented record cut off by the end of the file
+        append(&mut file_bytes, RecordType::Last, order_tail, valid(RecordType::Last, order_tail));
+        append(&mut file_bytes, RecordType::Full, user2.as_bytes(), valid(RecordType::Full, user2.as_bytes()));
+        append(&mut file_bytes, RecordType::First, &post.as_bytes()[..70], valid(RecordType::First, &post.as_bytes()[..70]));
+
+        let file_path = temp_dir.path().join("corrupted.leveldb").to_string_lossy().to_string();
+        fs::write(&file_path, &file_bytes).await.expect("Failed to write test file");
+
+        let strict = FirestoreDocumentParser::new(file_path.clone()).parse_documents().await;
+        assert!(strict.is_err());
+
+        let result = FirestoreDocumentParser::new(file_path.clone())
+            .with_salvage_mode(true)
+            .parse_documents()
+            .await
+            .expect("Salvage mode should recover the intact records");
+        let ids: Vec<&str> = result.documents.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(ids, vec!["user1", "user2"]);
+
+        let report = result.salvage_report.expect("salvage report");
+        assert_eq!(report.recovered_records, 2);
+        assert_eq!(report.skipped_ranges.len(), 1);
+        assert_eq!((report.skipped_ranges[0].start, report.skipped_ranges[0].end), (32768, 65536));
+        assert!(report.skipped_ranges[0].reason.contains("Checksum mismatch"));
+
+        let dropped: Vec<(u64, Option<&str>)> = report.dropped_records.iter()
+            .map(|r| (r.offset, r.collection.as_deref()))
+            .collect();
+        let post_offset = 65536 + 7 + order_tail.len() as u64 + 7 + user2.len() as u64;
+        assert_eq!(dropped, vec![
+            (7 + user1.len() as u64, Some("orders")),
+            (65536, None),
+            (post_offset, Some("posts")),
+        ]);
+        assert_eq!(
+            report.affected_collections.iter().map(String::as_str).collect::<Vec<_>>(),
+            vec!["orders", "posts"]
+        );
+
+        let report_path = temp_dir.path().join("salvage-report.json");
+        report.write_to(&report_path).expect("Failed to write salvage report");
+        let written: serde_json::Value = serde_json::from_slice(&std::fs::read(&report_path).unwrap()).unwrap();
+        assert_eq!(written["dropped_records"].as_array().map(Vec::len), Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_parse_json_document_keeps_firestore_types() {
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+        let document = serde_json::json!({
+            "name": "projects/test/databases/(default)/documents/places/eiffel",
+            "fields": {
+                // Beyond the integers an f64 represents exactly
+                "visits": {"integerValue": "9007199254740993"},
+                "rating": {"doubleValue": 4.0},
+                "opened": {"timestampValue": "1889-03-31T12:00:00.000000001Z"},
+                "thumbnail": {"bytesValue": "AAEC"},
+                "owner": {"referenceValue": "projects/test/databases/(default)/documents/cities/paris"},
+                "location": {"geoPointValue": {"latitude": 48.8584, "longitude": 2.2945}},
+                "floors": {"arrayValue": {"values": [{"integerValue": "1"}, {"integerValue": "2"}]}},
+                "address": {"mapValue": {"fields": {"city": {"stringValue": "Paris"}}}}
+            }
+        });
+        let file_path = temp_dir.path().join("places.jsonl");
+        fs::write(&file_path, format!("{}\n", document)).await.expect("Failed to write test file");
+
+        let result = FirestoreDocumentParser::new(file_path.to_string_lossy().to_string())
+            .parse_documents()
+            .await
+            .expect("Failed to parse JSON document");
+        let data = &result.documents[0].data;
+
+        assert_eq!(data["visits"], FirestoreValue::Integer(9_007_199_254_740_993));
+        assert_eq!(data["rating"], FirestoreValue::Double(4.0));
+        let FirestoreValue::Timestamp(opened) = &data["opened"] else { panic!("expected a timestamp") };
+        assert_eq!(opened.timestamp_subsec_nanos(), 1);
+        assert_eq!(data["thumbnail"], FirestoreValue::Bytes(vec![0, 1, 2]));
+        assert_eq!(
+            data["owner"].reference_target(),
+            Some(("cities", "paris"))
+        );
+        assert_eq!(data["location"], FirestoreValue::GeoPoint { latitude: 48.8584, longitude: 2.2945 });
+        assert_eq!(data["floors"], FirestoreValue::Array(vec![FirestoreValue::Integer(1), FirestoreValue::Integer(2)]));
+        assert_eq!(data["address"].to_json(), serde_json::json!({"city": "Paris"}));
+
+        // Values serialize in the REST encoding they were read from
+        assert_eq!(serde_json::to_value(&data["floors"]).unwrap(), document["fields"]["floors"]);
+        assert_eq!(serde_json::to_value(&data["visits"]).unwrap(), document["fields"]["visits"]);
+        assert_eq!(serde_json::to_value(&data["address"]).unwrap(), document["fields"]["address"]);
+
+        // Documents round-trip through serde without losing types
+        let encoded = serde_json::to_string(&result.documents[0]).unwrap();
+        let decoded: crate::types::FirestoreDocument = serde_json::from_str(&encoded).unwrap();
+        assert_eq!(&decoded.data, data);
+    }
+
+    #[tokio::test]
+    async fn test_parallel_parse_keeps_document_order() {
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        create_test_export(&temp_dir).await;
+
+        let parse_ids = |path: String, threads: usize| async move {
+            let result = FirestoreDocumentParser::new(path)
+                .with_parse_threads(threads)
+                .parse_documents()
+                .await
+                .expect("Parsing failed");
+            let shard_docs: Vec<usize> = result.metadata.shards.iter().map(|s| s.document_count).collect();
+            let ids: Vec<String> = result.documents.into_iter().map(|d| d.id).collect();
+            (ids, shard_docs)
+        };
+
+        let export_path = temp_dir.path().to_string_lossy().to_string();
+        let (sequential, sequential_shards) = parse_ids(export_path.clone(), 1).await;
+        let (parallel, parallel_shards) = parse_ids(export_path, 4).await;
+        assert_eq!(sequential, vec!["post1", "user1", "user2", "user3"]);
+        assert_eq!(parallel, sequential);
+        assert_eq!(parallel_shards, sequential_shards);
+
+        // Enough records for several decode batches
+        let json_path = temp_dir.path().join("documents.jsonl");
+        let lines: String = (0..1500)
+            .map(|i| format!(
+                "{{\"name\": \"projects/test/databases/(default)/documents/items/item{}\", \"fields\": {{}}}}\n",
+                i
+            ))
+            .collect();
+        fs::write(&json_path, lines).await.expect("Failed to write JSON lines");
+
+        let json_path = json_path.to_string_lossy().to_string();
+        let (sequential, _) = parse_ids(json_path.clone(), 1).await;
+        let (parallel, parallel_shards) = parse_ids(json_path, 8).await;
+        assert_eq!(sequential.len(), 1500);
+        assert_eq!(parallel, sequential);
+        assert_eq!(parallel_shards, vec![1500]);
+    }
+
+    #[tokio::test]
+    async fn test_document_filter_drops_collections_and_fields() {
+        use crate::leveldb_parser::filter::DocumentFilter;
+        use crate::protos::google::datastore::admin::v1::EntityFilter;
+
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        create_test_export(&temp_dir).await;
+
+        // Shards of excluded kinds are not read at all
+        let filter = DocumentFilter::new()
+            .with_include_collections(["users"])
+            .with_exclude_fields(["users/name", "title"]);
+        let result = FirestoreDocumentParser::new(temp_dir.path().to_string_lossy().to_string())
+            .with_filter(filter.clone())
+            .parse_documents()
+            .await
+            .expect("Parsing failed");
+        let ids: Vec<&str> = result.documents.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(ids, vec!["user1", "user2", "user3"]);
+        assert!(result.documents.iter().all(|d| d.data.is_empty()));
+        assert_eq!(result.metadata.shards.len(), 2);
+        assert_eq!(result.metadata.documents_filtered, 0);
+
+        // Documents of mixed files are dropped after decoding
+        let json_path = temp_dir.path().join("documents.jsonl");
+        fs::write(&json_path, concat!(
+            r#"{"name": "projects/test/databases/(default)/documents/users/user1", "fields": {"name": {"stringValue": "Alice"}, "title": {"stringValue": "Dr"}}}"#, "\n",
+            r#"{"name": "projects/test/databases/(default)/documents/posts/post1", "fields": {"title": {"stringValue": "Hello"}}}"#, "\n",
+            r#"{"name": "projects/test/databases/(default)/documents/users/user1/posts/post2", "fields": {"title": {"stringValue": "Hi"}}}"#, "\n",
+        )).await.expect("Failed to write JSON lines");
+        let result = FirestoreDocumentParser::new(json_path.to_string_lossy().to_string())
+            .with_filter(DocumentFilter::new().with_exclude_collections(["posts"]).with_exclude_fields(["title"]))
+            .parse_documents()
+            .await
+            .expect("Parsing failed");
+        assert_eq!(result.documents.len(), 1);
+        assert_eq!(result.documents[0].data.keys().collect::<Vec<_>>(), vec!["name"]);
+        assert_eq!(result.metadata.documents_filtered, 2);
+        assert_eq!(result.metadata.records_processed, 3);
+
+        // Kinds map to included collections, as in an export's entity filter
+        let entity_filter = EntityFilter { kinds: vec!["users".to_string()], namespace_ids: vec![] };
+        let from_entity = DocumentFilter::from_entity_filter(&entity_filter);
+        assert!(from_entity.keeps_collection("users"));
+        assert!(!from_entity.keeps_collection("posts"));
+        assert!(from_entity.keeps_kind("all_kinds"));
+        assert_eq!(filter.entity_filter(), entity_filter);
+        assert!(DocumentFilter::new().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_documents_follow_policy() {
+        use crate::leveldb_parser::parser::DuplicatePolicy;
+
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+        // A rewrite with a later update time, then a stale copy of the same document
+        let json_path = temp_dir.path().join("documents.jsonl");
+        fs::write(&json_path, concat!(
+            r#"{"name": "projects/test/databases/(default)/documents/users/user1", "updateTime": "2024-01-01T00:00:00Z", "fields": {"name": {"stringValue": "v1"}}}"#, "\n",
+            r#"{"name": "projects/test/databases/(default)/documents/users/user2", "fields": {}}"#, "\n",
+            r#"{"name": "projects/test/databases/(default)/documents/users/user1", "updateTime": "2024-03-01T00:00:00Z", "fields": {"name": {"stringValue": "v3"}}}"#, "\n",
+            r#"{"name": "projects/test/databases/(default)/documents/users/user1", "updateTime": "2024-02-01T00:00:00Z", "fields": {"name": {"stringValue": "v2"}}}"#, "\n",
+        )).await.expect("Failed to write JSON lines");
+        let parse = |policy| {
+            let parser = FirestoreDocumentParser::new(json_path.to_string_lossy().to_string())
+                .with_duplicate_policy(policy);
+            async move { parser.parse_documents().await }
+        };
+
+        let latest = parse(DuplicatePolicy::LatestUpdate).await.expect("Parsing failed");
+        let ids: Vec<&str> = latest.documents.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(ids, vec!["user1", "user2"]);
+        assert_eq!(latest.documents[0].data["name"], FirestoreValue::String("v3".to_string()));
+        assert_eq!(latest.metadata.duplicate_count, 2);
+        assert_eq!(latest.metadata.document_count, 2);
+
+        let first = parse(DuplicatePolicy::FirstWins).await.expect("Parsing failed");
+        assert_eq!(first.documents[0].data["name"], FirestoreValue::String("v1".to_string()));
+        assert_eq!(first.metadata.duplicate_count, 2);
+
+        let error = parse(DuplicatePolicy::Error).await.err().expect("Duplicates should fail");
+        assert!(error.to_string().contains("users/user1"));
+    }
+
+    #[tokio::test]
+    async fn test_parse_realtime_database_export() {
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+        let tree = serde_json::json!({
+            "users": {
+                "alice": {
+                    "name": "Alice",
+                    "address": {"city": "Paris"},
+                    "posts": {
+                        "p1": {"title": "Hello"},
+                        "p2": {"title": "World"}
+                    }
+                },
+                "bob": {"name": "Bob"}
+            },
+            "members": {"alice": true},
+            "version": 3
+        });
+        let file_path = temp_dir.path().join("my-app-default-rtdb-export.json");
+        fs::write(&file_path, serde_json::to_string_pretty(&tree).unwrap()).await.expect("Failed to write export");
+        let file_path = file_path.to_string_lossy().to_string();
+
+        // One collection level: nested records stay fields
+        let parser = FirestoreDocumentParser::new(file_path.clone());
+        assert_eq!(parser.backup_format().await, BackupFormat::RealtimeDatabase);
+        let result = parser.parse_documents().await.expect("Parsing failed");
+        let paths: Vec<&str> = result.documents.iter().map(|d| d.metadata.path.as_str()).collect();
+        assert_eq!(paths, vec!["members/alice", "users/alice", "users/bob"]);
+        assert_eq!(result.documents[0].data["value"], FirestoreValue::Boolean(true));
+        assert!(matches!(result.documents[1].data["posts"], FirestoreValue::Map(_)));
+        assert_eq!(result.metadata.format, BackupFormat::RealtimeDatabase);
+
+        // Two collection levels: keyed children become subcollections
+        let result = FirestoreDocumentParser::new(file_path)
+            .with_realtime_database_depth(2)
+            .parse_documents()
+            .await
+            .expect("Parsing failed");
+        let paths: Vec<&str> = result.documents.iter().map(|d| d.metadata.path.as_str()).collect();
+        assert_eq!(paths, vec!["members/alice", "users/alice", "users/alice/posts/p1", "users/alice/posts/p2", "users/bob"]);
+        let alice = &result.documents[1];
+        assert!(!alice.data.contains_key("posts"));
+        assert!(matches!(alice.data["address"], FirestoreValue::Map(_)));
+        let post = &result.documents[2];
+        assert_eq!((post.collection.as_str(), post.id.as_str()), ("posts", "p1"));
+        assert_eq!(post.metadata.parent_path.as_deref(), Some("users/alice"));
+
+        // A compact single-line export is detected too; Firestore JSON lines are not
+        let compact_path = temp_dir.path().join("compact.json");
+        fs::write(&compact_path, tree.to_string()).await.expect("Failed to write export");
+        let parser = FirestoreDocumentParser::new(compact_path.to_string_lossy().to_string());
+        assert_eq!(parser.backup_format().await, BackupFormat::RealtimeDatabase);
+        let jsonl_path = temp_dir.path().join("documents.jsonl");
+        fs::write(&jsonl_path, concat!(
+            r#"{"name": "projects/test/databases/(default)/documents/users/user1", "fields": {}}"#, "\n",
+        )).await.expect("Failed to write JSON lines");
+        let parser = FirestoreDocumentParser::new(jsonl_path.to_string_lossy().to_string());
+        assert_eq!(parser.backup_format().await, BackupFormat::JsonLines);
+    }
+
+    #[tokio::test]
+    async fn test_id_collection_json_lines_are_not_a_realtime_database() {
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let jsonl_path = temp_dir.path().join("documents.json");
+        fs::write(&jsonl_path, concat!(
+            r#"{"id": "user1", "collection": "users", "data": {"name": "Ada"}}"#, "\n",
+            r#"{"id": "user2", "collection": "users", "data": {"name": "Bob"}}"#, "\n",
+        )).await.expect("Failed to write JSON lines");
+
+        let parser = FirestoreDocumentParser::new(jsonl_path.to_string_lossy().to_string());
+        assert_eq!(parser.backup_format().await, BackupFormat::JsonLines);
+        let result = parser.parse_documents().await.expect("Parsing failed");
+        assert_eq!(result.metadata.format, BackupFormat::JsonLines);
+        let ids: Vec<(&str, &str)> = result.documents.iter().map(|d| (d.collection.as_str(), d.id.as_str())).collect();
+        assert_eq!(ids, vec![("users", "user1"), ("users", "user2")]);
+
+        // A single such line is a document too, not a tree
+        let single_path = temp_dir.path().join("single.json");
+        fs::write(&single_path, concat!(
+            r#"{"id": "user1", "collection": "users", "data": {"name": "Ada"}}"#, "\n",
+        )).await.expect("Failed to write JSON lines");
+        let parser = FirestoreDocumentParser::new(single_path.to_string_lossy().to_string());
+        assert_eq!(parser.backup_format().await, BackupFormat::JsonLines);
+    }
+
+    #[tokio::test]
+    async fn test_datastore_namespaces_and_ancestor_keys() {
+        use crate::leveldb_parser::filter::DocumentFilter;
+        use crate::protos::google::datastore::v1::{value::ValueType, Entity};
+        use prost::Message;
+
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+        let entity = |namespace: &str, path: &[(&str, &str)]| {
+            let mut key = entity_key("legacy", path);
+            key.partition_id.as_mut().unwrap().namespace_id = namespace.to_string();
+            Entity {
+                key: Some(key),
+                properties: std::collections::HashMap::from([
+                    ("label".to_string(), entity_value(ValueType::StringValue(namespace.to_string()))),
+                ]),
+            }
+            .encode_to_vec()
+        };
+        let customer = entity("billing", &[("customers", "c1")]);
+        let invoice = entity("billing", &[("customers", "c1"), ("invoices", "i1")]);
+        // Same path in the default namespace: a different entity, not a duplicate
+        let default_customer = entity("", &[("customers", "c1")]);
+
+        let file_path = create_test_leveldb_file(
+            &temp_dir,
+            "datastore.leveldb",
+            vec![
+                (RecordType::Full, customer.as_slice()),
+                (RecordType::Full, invoice.as_slice()),
+                (RecordType::Full, default_customer.as_slice()),
+            ]
+        ).await.expect("Failed to create test file");
+
+        let parse_result = FirestoreDocumentParser::new(file_path.clone())
+            .parse_documents().await.expect("Failed to parse entity file");
+
+        assert_eq!(parse_result.documents.len(), 3);
+        assert_eq!(parse_result.metadata.duplicate_count, 0);
+        assert_eq!(parse_result.metadata.namespaces, vec!["billing".to_string()]);
+
+        let invoice = &parse_result.documents[1];
+        assert_eq!(invoice.metadata.namespace.as_deref(), Some("billing"));
+        assert_eq!(invoice.metadata.parent_path.as_deref(), Some("customers/c1"));
+        assert_eq!(invoice.qualified_collection(), "billing__customers_invoices");
+        assert_eq!(
+            invoice.parent_reference(),
+            Some(("billing__customers".to_string(), "c1".to_string()))
+        );
+        assert_eq!(parse_result.documents[0].qualified_collection(), "billing__customers");
+        assert_eq!(parse_result.documents[2].metadata.namespace, None);
+        assert_eq!(parse_result.documents[2].qualified_collection(), "customers");
+
+        // `""` selects the default namespace, as in an EntityFilter
+        let parse_result = FirestoreDocumentParser::new(file_path)
+            .with_filter(DocumentFilter::new().with_namespaces([""]))
+            .parse_documents().await.expect("Failed to parse entity file");
+        assert_eq!(parse_result.documents.len(), 1);
+        assert_eq!(parse_result.documents[0].data["label"], FirestoreValue::String(String::new()));
+        assert_eq!(parse_result.metadata.documents_filtered, 2);
+    }
+
+    #[test]
+    fn test_entity_partition_reads_namespace_from_field_4() {
+        use crate::leveldb_parser::entity_decoder::EntityDecoder;
+
+        // Length-delimited protobuf field
+        fn field(number: u8, bytes: &[u8]) -> Vec<u8> {
+            let mut encoded = vec![(number << 3) | 2, bytes.len() as u8];
+            encoded.extend_from_slice(bytes);
+            encoded
+        }
+
+        // PartitionId { project_id = 2, database_id = 3, namespace_id = 4 }
+        let partition = [field(2, b"legacy"), field(3, b"orders-db"), field(4, b"billing")].concat();
+        // PathElement { kind = 1, name = 3 }
+        let element = [field(1, b"customers"), field(3, b"c1")].concat();
+        let key = [field(1, &partition), field(2, &element)].concat();
+        let entity = field(1, &key);
+
+        let document = EntityDecoder::new().decode(&entity, 0)
+            .expect("Failed to decode entity")
+            .expect("entity has a key");
+        assert_eq!(document.metadata.path, "customers/c1");
+        assert_eq!(document.metadata.project.as_deref(), Some("legacy"));
+        assert_eq!(document.metadata.namespace.as_deref(), Some("billing"));
+    }
+
+    #[tokio::test]
+    async fn test_export_metadata_names_the_database_of_its_entities() {
+        use crate::leveldb_parser::writer::LevelDBWriter;
+        use crate::types::FirestoreDocument;
+
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path().join("orders_export");
+        let kind_dir = root.join("all_namespaces/kind_users");
+        std::fs::create_dir_all(&kind_dir).expect("Failed to create kind directory");
+
+        let mut user = FirestoreDocument::new("u1".to_string(), "users".to_string(), "users/u1".to_string());
+        user.metadata.project = Some("my-app".to_string());
+        let mut writer = LevelDBWriter::create(&kind_dir.join("output-0")).expect("Failed to create writer");
+        writer.add_document(&user).expect("Failed to write document");
+        writer.finish().expect("Failed to finish log");
+
+        let mut metadata = Vec::new();
+        prost::encoding::string::encode(1, &"projects/my-app/databases/orders".to_string(), &mut metadata);
+        let mut overall = LevelDBWriter::create(&root.join("orders_export.overall_export_metadata"))
+            .expect("Failed to create writer");
+        overall.add_record(&metadata).expect("Failed to write metadata");
+        overall.finish().expect("Failed to finish log");
+        let root = root.to_string_lossy().to_string();
+
+        let parser = FirestoreDocumentParser::new(root.clone());
+        let export = parser.export_manifest().expect("export is discovered");
+        assert_eq!(export.database, Some(("my-app".to_string(), "orders".to_string())));
+        let result = parser.parse_documents().await.expect("Failed to parse export");
+        assert_eq!(result.documents[0].metadata.database.as_deref(), Some("orders"));
+        assert_eq!(result.metadata.databases, vec!["my-app/orders".to_string()]);
+
+        // An explicit database wins over the export metadata
+        let result = FirestoreDocumentParser::new(root)
+            .with_database(Some("(default)".to_string()))
+            .parse_documents().await.expect("Failed to parse export");
+        assert_eq!(result.documents[0].metadata.database.as_deref(), Some("(default)"));
+    }
+
+    #[tokio::test]
+    async fn test_leveldb_writer_fragments_records_across_blocks() {
+        use crate::leveldb_parser::writer::{LevelDBWriter, BLOCK_SIZE, HEADER_SIZE};
+
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let file_path = temp_dir.path().join("written.leveldb");
+
+        // Small record, then one spanning three blocks, then one that leaves
+        // fewer than HEADER_SIZE bytes in its block so the next is padded over
+        let large: Vec<u8> = (0..2 * BLOCK_SIZE).map(|i| (i % 251) as u8).collect();
+        let used = (HEADER_SIZE + 10 + large.len() + 3 * HEADER_SIZE) % BLOCK_SIZE;
+        let filler_len = BLOCK_SIZE - used - HEADER_SIZE - 3;
+        let records: Vec<Vec<u8>> = vec![vec![7u8; 10], large, vec![1u8; filler_len], b"after padding".to_vec()];
+
+        let mut writer = LevelDBWriter::create(&file_path).expect("Failed to create writer");
+        for record in &records {
+            writer.add_record(record).expect("Failed to write record");
+        }
+        assert_eq!(writer.records_written(), 4);
+        let written = writer.bytes_written();
+        writer.finish().expect("Failed to finish log");
+        assert_eq!(std::fs::metadata(&file_path).unwrap().len(), written);
+
+        let reader = LevelDBReader::new(file_path.to_str().unwrap());
+        let blocks = reader.read_file().await.expect("Failed to read written log");
+        let types: Vec<RecordType> = blocks.iter()
+            .flat_map(|block| block.records.iter().map(|r| r.header.record_type))
+            .collect();
+        assert_eq!(types, vec![
+            RecordType::Full,
+            RecordType::First, RecordType::Middle, RecordType::Last,
+            RecordType::Full,
+            RecordType::Full,
+        ]);
+        assert!(blocks.iter().flat_map(|b| b.records.iter()).all(|r| r.checksum_valid == Some(true)));
+
+        let mut assembled = Vec::new();
+        let mut current = Vec::new();
+        for record in blocks.iter().flat_map(|block| block.records.iter()) {
+            current.extend_from_slice(&record.data);
+            if matches!(record.header.record_type, RecordType::Full | RecordType::Last) {
+                assembled.push(std::mem::take(&mut current));
+            }
+        }
+        assert_eq!(assembled, records);
+    }
+
+    #[tokio::test]
+    async fn test_generated_fixture_round_trips_through_parser() {
+        use crate::leveldb_parser::fixture::{FixtureSpec, SUBCOLLECTION_NAME};
+
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path().join("fixture_export");
+
+        let spec = FixtureSpec::new()
+            .with_collections(["users", "orders"])
+            .with_documents_per_collection(25)
+            .with_fields_per_document(11)
+            .with_nesting_depth(2)
+            .with_subcollection_documents(2)
+            .with_shards_per_kind(3);
+        let summary = spec.generate(&root).expect("Failed to generate fixture");
+        assert_eq!(summary.documents, 25 * 2 * 3);
+        assert_eq!(summary.shards, 3 * 3);
+
+        let parse_result = FirestoreDocumentParser::new(root.to_str().unwrap())
+            .parse_documents().await.expect("Failed to parse fixture");
+        assert_eq!(parse_result.metadata.format, BackupFormat::FirestoreExport);
+        assert_eq!(parse_result.documents.len(), summary.documents);
+        assert!(parse_result.errors.is_empty());
+        assert!(parse_result.metadata.kinds.iter().all(|kind| kind.shard_count == 3));
+
+        let item = parse_result.documents.iter()
+            .find(|document| document.collection == SUBCOLLECTION_NAME)
+            .expect("subcollection documents are read back");
+        assert!(item.metadata.parent_path.is_some());
+        let user = parse_result.documents.iter()
+            .find(|document| document.metadata.path == "users/users-000003")
+            .expect("generated document is read back");
+        assert_eq!(user.data.len(), 11);
+        assert_eq!(user.metadata.project.as_deref(), Some("fixture-project"));
+        assert_eq!(
+            user.data["field_7"],
+            FirestoreValue::Reference("projects/fixture-project/databases/(default)/documents/users/users-000002".to_string())
+        );
+        assert!(matches!(user.data["field_6"], FirestoreValue::Map(_)));
+
+        // Same seed, same documents
+        let again = temp_dir.path().join("fixture_again");
+        spec.generate(&again).expect("Failed to generate fixture");
+        let reparsed = FirestoreDocumentParser::new(again.to_str().unwrap())
+            .parse_documents().await.expect("Failed to parse fixture");
+        let same_user = reparsed.documents.iter()
+            .find(|document| document.metadata.path == "users/users-000003")
+            .expect("generated document is read back");
+        assert_eq!(same_user.data, user.data);
+    }
+
+    #[tokio::test]
+    async fn test_parsed_documents_carry_storage_size() {
+        use crate::protos::google::datastore::v1::{key, value::ValueType, Entity, Key, PartitionId};
+        use prost::Message;
+
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+        // The example of Firestore's storage size documentation: a 44 byte
+        // name, 71 bytes of fields and 32 bytes of overhead
+        let task = serde_json::json!({
+            "name": "projects/test/databases/(default)/documents/users/jeff/tasks/my_task_id",
+            "fields": {
+                "type": {"stringValue": "Personal"},
+                "done": {"booleanValue": false},
+                "priority": {"integerValue": "1"},
+                "description": {"stringValue": "Learn Cloud Firestore"}
+            }
+        });
+        let json_path = temp_dir.path().join("tasks.jsonl");
+        fs::write(&json_path, format!("{}\n", task)).await.expect("Failed to write test file");
+
+        let result = FirestoreDocumentParser::new(json_path.to_string_lossy().to_string())
+            .parse_documents().await.expect("Failed to parse JSON document");
+        assert_eq!(result.documents[0].metadata.size_bytes, Some(147));
+        assert_eq!(result.documents[0].storage_size(), 147);
+
+        // A numeric entity ID takes 8 bytes rather than the size of its digits
+        let entity = Entity {
+            key: Some(Key {
+                partition_id: Some(PartitionId { project_id: "legacy".to_string(), ..Default::default() }),
+                path: vec![key::PathElement {
+                    kind: "users".to_string(),
+                    id_type: Some(key::path_element::IdType::Id(42)),
+                }],
+            }),
+            properties: std::collections::HashMap::from([
+                ("n".to_string(), entity_value(ValueType::IntegerValue(7))),
+            ]),
+        }
+        .encode_to_vec();
+        let file_path = create_test_leveldb_file(
+            &temp_dir,
+            "numeric.leveldb",
+            vec![(RecordType::Full, entity.as_slice())]
+        ).await.expect("Failed to create test file");
+
+        let result = FirestoreDocumentParser::new(file_path)
+            .parse_documents().await.expect("Failed to parse entity file");
+        // users (6) + ID (8) + 16, then n (2) + integer (8), then 32
+        assert_eq!(result.documents[0].metadata.size_bytes, Some(72));
+    }
+
+    #[tokio::test]
+    async fn test_validator_reports_firestore_limit_violations() {
+        use crate::leveldb_parser::limits::LimitRule;
+        use crate::leveldb_parser::writer::LevelDBWriter;
+        use crate::types::FirestoreDocument;
+
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let document = |path: &str| {
+            let (collection, id) = path.rsplit_once('/').unwrap();
+            FirestoreDocument::new(id.to_string(), collection.to_string(), path.to_string())
+        };
+
+        let mut reserved = document("users/__admin__");
+        reserved.add_field("__secret__".to_string(), FirestoreValue::Boolean(true));
+        let dots = document("users/..");
+        let mut deep = document("users/deep");
+        let mut nested = FirestoreValue::Integer(1);
+        for _ in 0..21 {
+            nested = FirestoreValue::Map(std::collections::HashMap::from([("level".to_string(), nested)]));
+        }
+        deep.add_field("tree".to_string(), nested);
+        let mut tagged = document("users/tagged");
+        tagged.add_field("tags".to_string(), FirestoreValue::Array((0..40_001).map(FirestoreValue::Integer).collect()));
+        let mut large = document("files/large");
+        large.add_field("blob".to_string(), FirestoreValue::Bytes(vec![0; 1_000_000]));
+        let mut oversized = document("files/oversized");
+        oversized.add_field("blob".to_string(), FirestoreValue::Bytes(vec![0; 1_100_000]));
+        let mut fine = document("users/fine");
+        fine.add_field("name".to_string(), FirestoreValue::String("Ada".to_string()));
+
+        let file_path = temp_dir.path().join("limits.leveldb");
+        let mut writer = LevelDBWriter::create(&file_path).expect("Failed to create writer");
+        for document in [&reserved, &dots, &deep, &tagged, &large, &oversized, &fine] {
+            writer.add_document(document).expect("Failed to write document");
+        }
+        writer.finish().expect("Failed to finish log");
+        let file_path = file_path.to_string_lossy().to_string();
+
+        // Without limit checks the backup is valid
+        let result = BackupValidatorImpl::new(file_path.clone())
+            .validate_comprehensive(&file_path).await.expect("Validation should run");
+        assert!(result.is_valid, "unexpected errors: {:?}", result.errors);
+        assert!(result.limit_violations.is_empty());
+
+        let result = BackupValidatorImpl::new(file_path.clone())
+            .with_limit_checks(true)
+            .validate_comprehensive(&file_path).await.expect("Validation should run");
+        let mut found: Vec<(LimitRule, &str)> = result.limit_violations.iter()
+            .map(|violation| (violation.rule, violation.document_path.as_str()))
+            .collect();
+        found.sort_by_key(|(rule, path)| (rule.to_string(), path.to_string()));
+        assert_eq!(found, vec![
+            (LimitRule::DocumentId, "users/.."),
+            (LimitRule::DocumentSize, "files/oversized"),
+            (LimitRule::DocumentSizeNearLimit, "files/large"),
+            (LimitRule::IndexEntries, "users/tagged"),
+            (LimitRule::NestingDepth, "users/deep"),
+            (LimitRule::ReservedName, "users/__admin__"),
+            (LimitRule::ReservedName, "users/__admin__"),
+        ]);
+
+        // Errors name the document; the near-limit document is only a warning
+        assert!(!result.is_valid);
+        assert!(result.errors.iter().any(|error| error.contains("users/deep") && error.contains("21 levels")));
+        assert!(result.warnings.iter().any(|warning| warning.contains("files/large")));
+        assert!(!result.errors.iter().any(|error| error.contains("files/large")));
+        let summary = BackupValidatorImpl::new(file_path.clone()).generate_summary_report(&result);
+        assert!(summary.contains("reserved-name: 2"));
+    }
+
+    #[tokio::test]
+    async fn test_validator_checks_limits_in_every_shard_of_an_export() {
+        use crate::leveldb_parser::limits::LimitRule;
+        use crate::leveldb_parser::writer::LevelDBWriter;
+        use crate::types::FirestoreDocument;
+
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path().join("export");
+        let kind_dir = root.join("all_namespaces/kind_users");
+        std::fs::create_dir_all(&kind_dir).expect("Failed to create kind directory");
+
+        let mut fine = FirestoreDocument::new("fine".to_string(), "users".to_string(), "users/fine".to_string());
+        // Large enough for the validator's minimum file size
+        fine.add_field("note".to_string(), FirestoreValue::String("x".repeat(1024)));
+        let mut reserved = FirestoreDocument::new("reserved".to_string(), "users".to_string(), "users/reserved".to_string());
+        reserved.add_field("__secret__".to_string(), FirestoreValue::Boolean(true));
+
+        for (shard, document) in [("output-0", &fine), ("output-1", &reserved)] {
+            let mut writer = LevelDBWriter::create(&kind_dir.join(shard)).expect("Failed to create writer");
+            writer.add_document(document).expect("Failed to write document");
+            writer.finish().expect("Failed to finish log");
+        }
+        let root = root.to_string_lossy().to_string();
+
+        let result = BackupValidatorImpl::new(root.clone())
+            .with_limit_checks(true)
+            .validate_comprehensive(&root).await.expect("Validation should run");
+        let found: Vec<(LimitRule, &str)> = result.limit_violations.iter()
+            .map(|violation| (violation.rule, violation.document_path.as_str()))
+            .collect();
+        assert_eq!(found, vec![(LimitRule::ReservedName, "users/reserved")]);
+        assert!(!result.is_valid);
+    }
+
+    #[tokio::test]
+    async fn test_validator_reads_the_structure_of_every_shard() {
+        use crate::leveldb_parser::writer::LevelDBWriter;
+        use crate::types::FirestoreDocument;
+
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path().join("export");
+        let kind_dir = root.join("all_namespaces/kind_users");
+        std::fs::create_dir_all(&kind_dir).expect("Failed to create kind directory");
+
+        let mut shard_records = 0;
+        for (shard, id) in [("output-0", "first"), ("output-1", "second"), ("output-2", "third")] {
+            let mut document = FirestoreDocument::new(id.to_string(), "users".to_string(), format!("users/{}", id));
+            // Large enough for the validator's minimum file size
+            document.add_field("note".to_string(), FirestoreValue::String("x".repeat(1024)));
+            let shard_path = kind_dir.join(shard);
+            let mut writer = LevelDBWriter::create(&shard_path).expect("Failed to create writer");
+            writer.add_document(&document).expect("Failed to write document");
+            writer.finish().expect("Failed to finish log");
+
+            let blocks = LevelDBReader::new(shard_path.to_string_lossy().to_string())
+                .read_file().await.expect("Failed to read shard");
+            shard_records += blocks.iter().map(|block| block.records.len()).sum::<usize>();
+        }
+        let root = root.to_string_lossy().to_string();
+
+        let result = BackupValidatorImpl::new(root.clone())
+            .validate_comprehensive(&root).await.expect("Validation should run");
+        assert!(result.is_valid, "unexpected errors: {:?}", result.errors);
+        assert_eq!(result.structure_info.total_blocks, 3);
+        assert_eq!(result.structure_info.total_records, shard_records);
+        assert_eq!(result.structure_info.valid_records, shard_records);
+    }
+
+    #[tokio::test]
+    async fn test_validator_checks_references_across_every_shard_and_kind() {
+        use crate::leveldb_parser::fixture::FixtureSpec;
+
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path().join("fixture_export");
+        // Every field_7 references the previous users document, which is
+        // written to the other shard
+        let summary = FixtureSpec::new()
+            .with_documents_per_collection(20)
+            .with_shards_per_kind(2)
+            .generate(&root)
+            .expect("Failed to generate fixture");
+        assert_eq!((summary.documents, summary.shards), (60, 6));
+        let root = root.to_string_lossy().to_string();
+
+        let result = BackupValidatorImpl::new(root.clone())
+            .with_reference_checks(true)
+            .validate_comprehensive(&root).await.expect("Validation should run");
+        assert!(result.is_valid, "unexpected errors: {:?}", result.errors);
+        let report = result.referential_integrity.as_ref().expect("references were checked");
+        assert_eq!(report.documents_checked, summary.documents);
+        assert_eq!(report.references_checked, summary.documents);
+        assert_eq!(report.dangling_count(), 0, "dangling: {:?}", report.dangling_references);
+        assert!(report.shared_ids.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_validator_reports_dangling_references_and_shared_ids() {
+        use crate::leveldb_parser::references::ReferenceKind;
+        use crate::leveldb_parser::writer::LevelDBWriter;
+        use crate::types::FirestoreDocument;
+
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let document = |path: &str| {
+            let (collection, id) = path.rsplit_once('/').unwrap();
+            let collection = collection.rsplit('/').next().unwrap();
+            let mut document = FirestoreDocument::new(id.to_string(), collection.to_string(), path.to_string());
+            document.metadata.parent_path = crate::types::parent_document_path(path);
+            document
+        };
+        let reference = |path: &str| FirestoreValue::Reference(format!("projects/p/databases/(default)/documents/{}", path));
+
+        let mut ada = document("users/ada");
+        ada.add_field("manager".to_string(), reference("users/bob"));
+        let mut bob = document("users/bob");
+        bob.add_field("manager".to_string(), reference("users/carol"));
+        bob.add_field("friends".to_string(), FirestoreValue::Array(vec![reference("users/ada"), reference("users/dan")]));
+        let mut order = document("orders/o1");
+        order.add_field("customer".to_string(), FirestoreValue::String("users/eve".to_string()));
+        order.add_field("shipping".to_string(), FirestoreValue::Map(std::collections::HashMap::from([
+            ("contact".to_string(), FirestoreValue::String("users/ada".to_string())),
+        ])));
+        // Not paths into collections of the backup
+        order.add_field("mime".to_string(), FirestoreValue::String("image/png".to_string()));
+        order.add_field("date".to_string(), FirestoreValue::String("12/05".to_string()));
+        let mut ada_item = document("users/ada/items/i1");
+        // Large enough for the validator's minimum file size
+        ada_item.add_field("note".to_string(), FirestoreValue::String("x".repeat(1024)));
+        let bob_item = document("users/bob/items/i1");
+
+        let file_path = temp_dir.path().join("references.leveldb");
+        let mut writer = LevelDBWriter::create(&file_path).expect("Failed to create writer");
+        for document in [&ada, &bob, &order, &ada_item, &bob_item] {
+            writer.add_document(document).expect("Failed to write document");
+        }
+        writer.finish().expect("Failed to finish log");
+        let file_path = file_path.to_string_lossy().to_string();
+
+        let result = BackupValidatorImpl::new(file_path.clone())
+            .validate_comprehensive(&file_path).await.expect("Validation should run");
+        assert!(result.referential_integrity.is_none());
+
+        let result = BackupValidatorImpl::new(file_path.clone())
+            .with_reference_checks(true)
+            .validate_comprehensive(&file_path).await.expect("Validation should run");
+        // Dangling references are reported, but do not invalidate the backup
+        assert!(result.is_valid, "unexpected errors: {:?}", result.errors);
+        let report = result.referential_integrity.as_ref().expect("references were checked");
+
+        assert_eq!(report.documents_checked, 5);
+        assert_eq!(report.references_checked, 4);
+        assert_eq!(report.path_strings_checked, 2);
+        let dangling: Vec<(&str, &str, &str, ReferenceKind)> = report.dangling_references.iter()
+            .map(|d| (d.source_path.as_str(), d.field_path.as_str(), d.target_path.as_str(), d.kind))
+            .collect();
+        assert_eq!(dangling, vec![
+            ("orders/o1", "customer", "users/eve", ReferenceKind::PathString),
+            ("users/bob", "friends", "users/dan", ReferenceKind::Reference),
+            ("users/bob", "manager", "users/carol", ReferenceKind::Reference),
+        ]);
+
+        let manager = report.reference_counts.iter()
+            .find(|count| count.source_collection == "users" && count.field_path == "manager")
+            .unwrap();
+        assert_eq!((manager.references, manager.dangling, manager.target_collection.as_str()), (2, 1, "users"));
+        let contact = report.reference_counts.iter()
+            .find(|count| count.field_path == "shipping.contact")
+            .unwrap();
+        assert_eq!((contact.references, contact.dangling), (1, 0));
+
+        assert_eq!(report.shared_ids.len(), 1);
+        assert_eq!(report.shared_ids[0].collection, "users_items");
+        assert_eq!(report.shared_ids[0].paths, vec!["users/ada/items/i1", "users/bob/items/i1"]);
+        assert!(result.warnings.iter().any(|w| w.contains("1 of 2 references from users.manager to users are dangling")));
+
+        let text = report.to_text();
+        assert!(text.contains("Dangling References: 3"));
+        assert!(text.contains("users/bob manager -> users/carol"));
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["referential_integrity"]["dangling_references"][0]["kind"], "path_string");
+    }
+
+    #[tokio::test]
+    async fn test_repair_writes_cleaned_export() {
+        use crate::leveldb_parser::repair::{BackupRepairer, RepairFix, RepairOutputFormat};
+        use crate::leveldb_parser::writer::{LevelDBWriter, BLOCK_SIZE};
+        use crate::types::FirestoreDocument;
+
+        setup_monitoring();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let user = |id: &str, age: FirestoreValue| {
+            let mut document = FirestoreDocument::new(id.to_string(), "users".to_string(), format!("users/{}", id));
+            document.add_field("age".to_string(), age);
+            document
+        };
+        let block = |documents: &[FirestoreDocument]| {
+            let mut writer = LevelDBWriter::new(Vec::new());
+            for document in documents {
+                writer.add_document(document).expect("Failed to write document");
+            }
+            let mut bytes = writer.finish().expect("Failed to finish log");
+            bytes.resize(BLOCK_SIZE, 0);
+            bytes
+        };
+
+        let mut ada = user("a", FirestoreValue::Integer(30));
+        ada.add_field("__meta__".to_string(), FirestoreValue::Boolean(true));
+        ada.add_field("profile".to_string(), FirestoreValue::Map(std::collections::HashMap::from([
+            (String::new(), FirestoreValue::String("x".to_string())),
+            ("city".to_string(), FirestoreValue::String("Oslo".to_string())),
+        ])));
+        let mut file_bytes = block(&[
+            ada,
+            user("b", FirestoreValue::Integer(41)),
+            user("c", FirestoreValue::String("35".to_string())),
+            user("d", FirestoreValue::Double(52.0)),
+            user("e", FirestoreValue::Integer(20)),
+        ]);
+        // A record with a bad checksum, then a later copy of users/b
+        let mut corrupted = create_record_header(RecordType::Full, 12, 0x12345678).to_vec();
+        corrupted.extend_from_slice(b"invalid data");
+        corrupted.resize(BLOCK_SIZE, 0);
+        file_bytes.extend(corrupted);
+        file_bytes.extend(block(&[user("b", FirestoreValue::Integer(42))]));
+        let input = temp_dir.path().join("damaged.leveldb");
+        fs::write(&input, &file_bytes).await.expect("Failed to write test file");
+        let input = input.to_string_lossy().to_string();
+
+        // Without dropping unreadable records the repair stops
+        let output = temp_dir.path().join("strict.jsonl");
+        let strict = BackupRepairer::new(input.clone())
+            .with_fixes([RepairFix::Deduplicate])
+            .repair(&output).await;
+        assert!(strict.is_err());
+
+        let output = temp_dir.path().join("cleaned.jsonl");
+        let result = BackupRepairer::new(input.clone())
+            .repair(&output).await.expect("Repair should succeed");
+        let changes = |fix: RepairFix| -> Vec<(Option<&str>, &str)> {
+            result.repairs.iter()
+                .filter(|repair| repair.fix == fix)
+                .map(|repair| (repair.document_path.as_deref(), repair.description.as_str()))
+                .collect()
+        };
+        assert_eq!(changes(RepairFix::DropUnreadable).len(), 1);
+        assert_eq!(changes(RepairFix::Deduplicate), vec![(Some("users/b"), "Kept 1 of 2 copies (LatestUpdate)")]);
+        assert_eq!(changes(RepairFix::StripInvalidFields), vec![
+            (Some("users/a"), "Removed field '__meta__' (name matching the reserved __.*__ pattern)"),
+            (Some("users/a"), "Removed field 'profile.' (empty name)"),
+        ]);
+        assert_eq!(changes(RepairFix::CoerceTypes), vec![
+            (Some("users/c"), "Coerced field 'age' from string \"35\" to integer"),
+            (Some("users/d"), "Coerced field 'age' from number 52.0 to integer"),
+        ]);
+        assert_eq!(result.structure_info.document_records, 5);
+
+        // The cleaned export reads back with the fixes applied
+        let ages = |documents: &[crate::types::FirestoreDocument]| {
+            let mut ages: Vec<(String, FirestoreValue)> = documents.iter()
+                .map(|document| (document.id.clone(), document.data["age"].clone()))
+                .collect();
+            ages.sort_by(|a, b| a.0.cmp(&b.0));
+            ages
+        };
+        let expected: Vec<(String, FirestoreValue)> = [("a", 30), ("b", 42), ("c", 35), ("d", 52), ("e", 20)]
+            .into_iter()
+            .map(|(id, age)| (id.to_string(), FirestoreValue::Integer(age)))
+            .collect();
+        let cleaned = FirestoreDocumentParser::new(output.to_string_lossy().to_string())
+            .parse_documents().await.expect("Failed to parse cleaned export");
+        assert_eq!(ages(&cleaned.documents), expected);
+        let ada = cleaned.documents.iter().find(|document| document.id == "a").unwrap();
+        assert!(!ada.data.contains_key("__meta__"));
+        assert_eq!(ada.data["profile"].to_json(), serde_json::json!({"city": "Oslo"}));
+
+        let output = temp_dir.path().join("cleaned.leveldb");
+        BackupRepairer::new(input.clone())
+            .with_output_format(RepairOutputFormat::LevelDbLog)
+            .repair(&output).await.expect("Repair should succeed");
+        let cleaned = FirestoreDocumentParser::new(output.to_string_lossy().to_string())
+            .parse_documents().await.expect("Failed to parse cleaned log");
+        assert_eq!(ages(&cleaned.documents), expected);
+    }
+}
@@ -1,6 +1,10 @@
 // LevelDB backup validator implementation
 use crate::error::{FireupError, ErrorContext};
-use crate::leveldb_parser::parser::{LevelDBReader, FirestoreDocumentParser};
+use crate::leveldb_parser::export::ExportManifest;
+use crate::leveldb_parser::limits::{self, LimitViolation};
+use crate::leveldb_parser::references::ReferentialIntegrityReport;
+use crate::leveldb_parser::repair::RepairAction;
+use crate::leveldb_parser::parser::{ChecksumMode, LevelDBReader, FirestoreDocumentParser};
 use std::collections::HashMap;
 use std::path::Path;
 use tokio::fs;
@@ -15,6 +19,13 @@ pub struct ValidationResult {
     pub file_info: FileInfo,
     pub structure_info: StructureInfo,
     pub integrity_info: IntegrityInfo,
+    /// Documents breaking Firestore's data model limits, when limit checks
+    /// are enabled
+    pub limit_violations: Vec<LimitViolation>,
+    /// References between documents, when reference checks are enabled
+    pub referential_integrity: Option<ReferentialIntegrityReport>,
+    /// Changes made by `fireup repair`; empty for plain validation
+    pub repairs: Vec<RepairAction>,
 }
 
 /// Information about the backup file
@@ -80,18 +91,57 @@ impl ProgressCallback for LoggingProgressCallback {
 /// Backup validator implementation
 pub struct BackupValidatorImpl {
     reader: LevelDBReader,
+    /// The path as given, which may be an export directory the reader has
+    /// narrowed to its first shard
+    backup_path: String,
     progress_callback: Option<Box<dyn ProgressCallback>>,
+    check_limits: bool,
+    check_references: bool,
+}
+
+/// What the document-level checks found
+struct DocumentFindings {
+    errors: Vec<String>,
+    warnings: Vec<String>,
+    limit_violations: Vec<LimitViolation>,
+    referential_integrity: Option<ReferentialIntegrityReport>,
 }
 
 impl BackupValidatorImpl {
     /// Create a new backup validator
     pub fn new(file_path: impl Into<String>) -> Self {
+        let backup_path = file_path.into();
         Self {
-            reader: LevelDBReader::new(file_path),
+            reader: LevelDBReader::new(backup_path.clone()),
+            backup_path,
             progress_callback: None,
+            check_limits: false,
+            check_references: false,
         }
     }
     
+    /// Set how record checksums are verified
+    pub fn with_checksum_mode(mut self, checksum_mode: ChecksumMode) -> Self {
+        self.reader = self.reader.with_checksum_mode(checksum_mode);
+        self
+    }
+    
+    /// Check parsed documents against Firestore's data model limits (document
+    /// size, field names, nesting depth, index entries, reserved names and
+    /// document IDs)
+    pub fn with_limit_checks(mut self, check_limits: bool) -> Self {
+        self.check_limits = check_limits;
+        self
+    }
+    
+    /// Check that every reference (and path-like string) of the backup points
+    /// at a document of the backup, and that document IDs are unique within
+    /// each collection
+    pub fn with_reference_checks(mut self, check_references: bool) -> Self {
+        self.check_references = check_references;
+        self
+    }
+    
     /// Set a progress callback for long-running operations
     pub fn with_progress_callback(mut self, callback: Box<dyn ProgressCallback>) -> Self {
         self.progress_callback = Some(callback);
@@ -145,6 +195,9 @@ impl BackupValidatorImpl {
                         parsing_errors: 0,
                         overall_integrity_score: 0.0,
                     },
+                    limit_violations: Vec::new(),
+                    referential_integrity: None,
+                    repairs: Vec::new(),
                 });
             }
         };
@@ -163,9 +216,9 @@ impl BackupValidatorImpl {
         
         // Step 4: Validate Firestore document format
         self.report_progress("Validating Firestore format", 80, 100);
-        let (format_errors, format_warnings) = self.validate_firestore_format().await;
-        errors.extend(format_errors);
-        warnings.extend(format_warnings);
+        let findings = self.validate_firestore_format().await;
+        errors.extend(findings.errors);
+        warnings.extend(findings.warnings);
         
         self.report_progress("Validation complete", 100, 100);
         
@@ -185,6 +238,9 @@ impl BackupValidatorImpl {
             file_info,
             structure_info,
             integrity_info,
+            limit_violations: findings.limit_violations,
+            referential_integrity: findings.referential_integrity,
+            repairs: Vec::new(),
         })
     }
     
@@ -255,55 +311,74 @@ impl BackupValidatorImpl {
         })
     }
     
+    /// Readers for every file of the backup: each `output-N` shard of an
+    /// export directory, or the backup file itself
+    fn shard_readers(&self) -> Result<Vec<LevelDBReader>, FireupError> {
+        let Some(export) = ExportManifest::discover(Path::new(&self.backup_path))? else {
+            return Ok(vec![self.reader.clone()]);
+        };
+        Ok(export.shards()
+            .map(|shard| {
+                LevelDBReader::new(shard.path.to_string_lossy().to_string())
+                    .with_checksum_mode(self.reader.checksum_mode())
+            })
+            .collect())
+    }
+    
     /// Validate LevelDB structure
     async fn validate_structure(&self) -> (StructureInfo, Vec<String>, Vec<String>) {
         let mut errors = Vec::new();
         let mut warnings = Vec::new();
         
-        let blocks = match self.reader.read_file().await {
-            Ok(blocks) => blocks,
+        let readers = match self.shard_readers() {
+            Ok(readers) => readers,
             Err(e) => {
-                errors.push(format!("Failed to read LevelDB blocks: {}", e));
-                return (StructureInfo {
-                    total_blocks: 0,
-                    total_records: 0,
-                    valid_records: 0,
-                    corrupted_records: 0,
-                    metadata_records: 0,
-                    document_records: 0,
-                }, errors, warnings);
+                errors.push(format!("Failed to read export layout: {}", e));
+                Vec::new()
             }
         };
         
+        let mut total_blocks = 0;
         let mut total_records = 0;
         let mut valid_records = 0;
         let mut corrupted_records = 0;
         
-        for (block_index, block) in blocks.iter().enumerate() {
+        for (shard_index, reader) in readers.iter().enumerate() {
             self.report_progress(
                 "Validating block structure",
-                block_index as u64,
-                blocks.len() as u64
+                shard_index as u64,
+                readers.len() as u64
             );
             
-            total_records += block.records.len();
+            let blocks = match reader.read_file().await {
+                Ok(blocks) => blocks,
+                Err(e) => {
+                    errors.push(format!("Failed to read LevelDB blocks of {}: {}", reader.file_path, e));
+                    continue;
+                }
+            };
+            total_blocks += blocks.len();
             
-            for record in &block.records {
-                // Basic record validation
-                if record.header.length as usize == record.data.len() {
-                    valid_records += 1;
-                } else {
-                    corrupted_records += 1;
-                    warnings.push(format!(
-                        "Record length mismatch in block {}: header says {}, actual {}",
-                        block_index, record.header.length, record.data.len()
-                    ));
+            for (block_index, block) in blocks.iter().enumerate() {
+                total_records += block.records.len();
+                
+                for record in &block.records {
+                    // Basic record validation
+                    if record.header.length as usize == record.data.len() {
+                        valid_records += 1;
+                    } else {
+                        corrupted_records += 1;
+                        warnings.push(format!(
+                            "Record length mismatch in block {} of {}: header says {}, actual {}",
+                            block_index, reader.file_path, record.header.length, record.data.len()
+                        ));
+                    }
                 }
             }
         }
         
         // Validate overall structure
-        if blocks.is_empty() {
+        if total_blocks == 0 {
             errors.push("No blocks found in LevelDB file".to_string());
         }
         
@@ -320,12 +395,12 @@ impl BackupValidatorImpl {
         }
         
         debug!(
-            "Structure validation: {} blocks, {} records ({} valid, {} corrupted)",
-            blocks.len(), total_records, valid_records, corrupted_records
+            "Structure validation: {} files, {} blocks, {} records ({} valid, {} corrupted)",
+            readers.len(), total_blocks, total_records, valid_records, corrupted_records
         );
         
         (StructureInfo {
-            total_blocks: blocks.len(),
+            total_blocks,
             total_records,
             valid_records,
             corrupted_records,
@@ -338,40 +413,48 @@ impl BackupValidatorImpl {
     async fn validate_integrity(&self) -> (IntegrityInfo, Vec<String>, Vec<String>) {
         let mut errors = Vec::new();
         let mut warnings = Vec::new();
-        let checksum_failures = 0;
+        let mut checksum_failures = 0;
         let incomplete_records = 0;
         let parsing_errors = 0;
         
-        let blocks = match self.reader.read_file().await {
-            Ok(blocks) => blocks,
+        let readers = match self.shard_readers() {
+            Ok(readers) => readers,
             Err(e) => {
-                errors.push(format!("Failed to read file for integrity check: {}", e));
-                return (IntegrityInfo {
-                    checksum_failures: 0,
-                    incomplete_records: 0,
-                    parsing_errors: 0,
-                    overall_integrity_score: 0.0,
-                }, errors, warnings);
+                errors.push(format!("Failed to read export layout: {}", e));
+                Vec::new()
             }
         };
         
         let mut total_records = 0;
         
-        for block in &blocks {
-            total_records += block.records.len();
+        for reader in &readers {
+            let blocks = match reader.read_file().await {
+                Ok(blocks) => blocks,
+                Err(e) => {
+                    errors.push(format!("Failed to read {} for integrity check: {}", reader.file_path, e));
+                    continue;
+                }
+            };
             
-            for record in &block.records {
-                // Checksum validation is already done in the reader
-                // Here we just count any records that made it through
+            for block in &blocks {
+                total_records += block.records.len();
                 
-                // Check for incomplete fragmented records
-                match record.header.record_type {
-                    crate::leveldb_parser::parser::RecordType::First |
-                    crate::leveldb_parser::parser::RecordType::Middle => {
-                        // These should be followed by more parts
-                        // For now, we'll assume they're handled correctly by the parser
+                for record in &block.records {
+                    // Strict mode fails the read on a mismatch; in warn mode the
+                    // reader keeps the record and flags it
+                    if record.checksum_valid == Some(false) {
+                        checksum_failures += 1;
+                    }
+                    
+                    // Check for incomplete fragmented records
+                    match record.header.record_type {
+                        crate::leveldb_parser::parser::RecordType::First |
+                        crate::leveldb_parser::parser::RecordType::Middle => {
+                            // These should be followed by more parts
+                            // For now, we'll assume they're handled correctly by the parser
+                        }
+                        _ => {}
                     }
-                    _ => {}
                 }
             }
         }
@@ -384,6 +467,13 @@ impl BackupValidatorImpl {
             0.0
         };
         
+        if checksum_failures > 0 {
+            warnings.push(format!(
+                "{} of {} records failed checksum verification",
+                checksum_failures, total_records
+            ));
+        }
+        
         if integrity_score < 0.9 {
             warnings.push(format!(
                 "Low integrity score: {:.1}% - consider using a different backup file",
@@ -404,13 +494,18 @@ impl BackupValidatorImpl {
         }, errors, warnings)
     }
     
-    /// Validate Firestore document format
-    async fn validate_firestore_format(&self) -> (Vec<String>, Vec<String>) {
+    /// Validate Firestore document format, and the documents against
+    /// Firestore's limits and each other when enabled
+    async fn validate_firestore_format(&self) -> DocumentFindings {
         let mut errors = Vec::new();
         let mut warnings = Vec::new();
+        let mut violations = Vec::new();
+        let mut referential_integrity = None;
         
-        // Try to parse a few documents to validate format
-        let parser = FirestoreDocumentParser::new(&self.reader.file_path);
+        // Parse the backup as given, so every shard and kind of an export
+        // directory is checked
+        let parser = FirestoreDocumentParser::new(&self.backup_path)
+            .with_checksum_mode(self.reader.checksum_mode());
         
         match parser.parse_documents().await {
             Ok(parse_result) => {
@@ -426,13 +521,49 @@ impl BackupValidatorImpl {
                         parse_result.errors.len()
                     ));
                 }
+                
+                if self.check_limits {
+                    violations = limits::check_documents(&parse_result.documents);
+                    for violation in &violations {
+                        if violation.rule.is_error() {
+                            errors.push(violation.to_string());
+                        } else {
+                            warnings.push(violation.to_string());
+                        }
+                    }
+                    debug!("Found {} Firestore limit violations", violations.len());
+                }
+                
+                if self.check_references {
+                    let report = ReferentialIntegrityReport::check(&parse_result.documents)
+                        .with_duplicate_copies(parse_result.metadata.duplicate_count);
+                    for count in report.reference_counts.iter().filter(|count| count.dangling > 0) {
+                        warnings.push(format!(
+                            "{} of {} references from {}.{} to {} are dangling",
+                            count.dangling, count.references, count.source_collection,
+                            count.field_path, count.target_collection
+                        ));
+                    }
+                    for shared in &report.shared_ids {
+                        warnings.push(format!(
+                            "{} documents of {} share the ID {}: {}",
+                            shared.paths.len(), shared.collection, shared.document_id, shared.paths.join(", ")
+                        ));
+                    }
+                    referential_integrity = Some(report);
+                }
             }
             Err(e) => {
                 errors.push(format!("Failed to parse Firestore documents: {}", e));
             }
         }
         
-        (errors, warnings)
+        DocumentFindings {
+            errors,
+            warnings,
+            limit_violations: violations,
+            referential_integrity,
+        }
     }
     
     /// Report progress to callback if available
@@ -496,6 +627,41 @@ impl BackupValidatorImpl {
         report.push_str(&format!("Incomplete Records: {}\n", result.integrity_info.incomplete_records));
         report.push_str(&format!("Parsing Errors: {}\n", result.integrity_info.parsing_errors));
         
+        // Firestore limits
+        if !result.limit_violations.is_empty() {
+            report.push_str("\n--- Firestore Limit Violations ---\n");
+            let mut by_rule: Vec<(String, usize)> = Vec::new();
+            for violation in &result.limit_violations {
+                let rule = violation.rule.to_string();
+                match by_rule.iter_mut().find(|(name, _)| *name == rule) {
+                    Some((_, count)) => *count += 1,
+                    None => by_rule.push((rule, 1)),
+                }
+            }
+            for (rule, count) in by_rule {
+                report.push_str(&format!("{}: {}\n", rule, count));
+            }
+        }
+        
+        // References
+        if let Some(references) = &result.referential_integrity {
+            report.push_str("\n--- Referential Integrity ---\n");
+            report.push_str(&format!("References Checked: {}\n", references.references_checked + references.path_strings_checked));
+            report.push_str(&format!("Dangling References: {}\n", references.dangling_count()));
+            report.push_str(&format!("Shared Document IDs: {}\n", references.shared_ids.len()));
+        }
+        
+        // Repairs
+        if !result.repairs.is_empty() {
+            report.push_str("\n--- Repairs ---\n");
+            for (i, repair) in result.repairs.iter().enumerate() {
+                match &repair.document_path {
+                    Some(path) => report.push_str(&format!("{}. [{}] {}: {}\n", i + 1, repair.fix, path, repair.description)),
+                    None => report.push_str(&format!("{}. [{}] {}\n", i + 1, repair.fix, repair.description)),
+                }
+            }
+        }
+        
         // Errors
         if !result.errors.is_empty() {
             report.push_str("\n--- Errors ---\n");
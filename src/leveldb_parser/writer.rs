@@ -0,0 +1,222 @@
+// LevelDB log writer producing Firestore export shards
+use crate::error::{FireupError, ErrorContext};
+use crate::leveldb_parser::parser::{masked_crc32c, RecordType};
+use crate::protos::google::datastore::v1::{key, value, ArrayValue, Entity, Key, PartitionId, Value};
+use crate::protos::google::r#type::LatLng;
+use crate::types::{document_database, relative_document_path, FirestoreDocument, FirestoreValue};
+use prost::Message;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Size of a log block
+pub const BLOCK_SIZE: usize = 32768;
+/// Size of a record header: checksum (4), length (2) and type (1)
+pub const HEADER_SIZE: usize = 7;
+
+/// Writes LevelDB log files as read by [`crate::leveldb_parser::parser::LevelDBReader`].
+///
+/// Records are split into Full/First/Middle/Last fragments so that none
+/// crosses a 32 KB block boundary, and the tail of a block too short for a
+/// header is zero-padded, as LevelDB's own log writer does.
+pub struct LevelDBWriter<W: Write = BufWriter<File>> {
+    out: W,
+    /// Offset within the current block
+    block_offset: usize,
+    records_written: usize,
+    bytes_written: u64,
+}
+
+impl LevelDBWriter {
+    /// Create (or truncate) a log file
+    pub fn create(path: &Path) -> Result<Self, FireupError> {
+        let file = File::create(path).map_err(|e| FireupError::leveldb_parse(
+            format!("Failed to create log file {}: {}", path.display(), e),
+            Self::context("create_log_file", HashMap::from([
+                ("file_path".to_string(), path.display().to_string()),
+            ])),
+        ))?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+}
+
+impl<W: Write> LevelDBWriter<W> {
+    /// Write a log to any byte sink, starting at a block boundary
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            block_offset: 0,
+            records_written: 0,
+            bytes_written: 0,
+        }
+    }
+
+    /// Append one record, fragmenting it across blocks as needed
+    pub fn add_record(&mut self, data: &[u8]) -> Result<(), FireupError> {
+        let mut remaining = data;
+        let mut first = true;
+        loop {
+            let block_left = BLOCK_SIZE - self.block_offset;
+            if block_left < HEADER_SIZE {
+                self.write_bytes(&[0u8; HEADER_SIZE][..block_left])?;
+                self.block_offset = 0;
+            }
+
+            let available = BLOCK_SIZE - self.block_offset - HEADER_SIZE;
+            let (fragment, rest) = remaining.split_at(remaining.len().min(available));
+            let record_type = match (first, rest.is_empty()) {
+                (true, true) => RecordType::Full,
+                (true, false) => RecordType::First,
+                (false, false) => RecordType::Middle,
+                (false, true) => RecordType::Last,
+            };
+            self.write_fragment(record_type, fragment)?;
+
+            remaining = rest;
+            first = false;
+            if remaining.is_empty() {
+                break;
+            }
+        }
+        self.records_written += 1;
+        Ok(())
+    }
+
+    /// Append a document, and then its subcollection documents, as
+    /// `google.datastore.v1.Entity` records
+    pub fn add_document(&mut self, document: &FirestoreDocument) -> Result<(), FireupError> {
+        self.add_record(&document_entity(document).encode_to_vec())?;
+        for subdocument in &document.subcollections {
+            self.add_document(subdocument)?;
+        }
+        Ok(())
+    }
+
+    /// Number of records written
+    pub fn records_written(&self) -> usize {
+        self.records_written
+    }
+
+    /// Number of bytes written, headers and padding included
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Flush the log and return the underlying sink
+    pub fn finish(mut self) -> Result<W, FireupError> {
+        self.out.flush().map_err(|e| FireupError::leveldb_parse(
+            format!("Failed to flush log: {}", e),
+            Self::context("flush_log", HashMap::new()),
+        ))?;
+        Ok(self.out)
+    }
+
+    fn write_fragment(&mut self, record_type: RecordType, fragment: &[u8]) -> Result<(), FireupError> {
+        let mut header = [0u8; HEADER_SIZE];
+        header[..4].copy_from_slice(&masked_crc32c(record_type as u8, fragment).to_le_bytes());
+        header[4..6].copy_from_slice(&(fragment.len() as u16).to_le_bytes());
+        header[6] = record_type as u8;
+        self.write_bytes(&header)?;
+        self.write_bytes(fragment)?;
+        self.block_offset += HEADER_SIZE + fragment.len();
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), FireupError> {
+        self.out.write_all(bytes).map_err(|e| FireupError::leveldb_parse(
+            format!("Failed to write log record: {}", e),
+            Self::context("write_record", HashMap::from([
+                ("records_written".to_string(), self.records_written.to_string()),
+                ("bytes_written".to_string(), self.bytes_written.to_string()),
+            ])),
+        ))?;
+        self.bytes_written += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn context(operation: &str, metadata: HashMap<String, String>) -> ErrorContext {
+        ErrorContext {
+            operation: operation.to_string(),
+            metadata,
+            timestamp: chrono::Utc::now(),
+            call_path: vec!["leveldb_parser::writer::LevelDBWriter".to_string()],
+        }
+    }
+}
+
+/// Encode a document as the entity a Firestore export stores for it: the key
+/// holds the document path (ancestors included), project, database and namespace
+pub fn document_entity(document: &FirestoreDocument) -> Entity {
+    let path = document.document_path();
+    Entity {
+        key: Some(path_key(
+            &path,
+            document.metadata.project.as_deref(),
+            document.metadata.database.as_deref(),
+            document.metadata.namespace.as_deref(),
+        )),
+        properties: document.data.iter()
+            .map(|(name, value)| (name.clone(), entity_value(value)))
+            .collect(),
+    }
+}
+
+/// Encode a typed value as a Datastore value
+pub fn entity_value(value: &FirestoreValue) -> Value {
+    let value_type = match value {
+        FirestoreValue::Null => value::ValueType::NullValue(0),
+        FirestoreValue::Boolean(b) => value::ValueType::BooleanValue(*b),
+        FirestoreValue::Integer(i) => value::ValueType::IntegerValue(*i),
+        FirestoreValue::Double(d) => value::ValueType::DoubleValue(*d),
+        FirestoreValue::Timestamp(ts) => value::ValueType::TimestampValue(prost_types::Timestamp {
+            seconds: ts.timestamp(),
+            nanos: ts.timestamp_subsec_nanos() as i32,
+        }),
+        FirestoreValue::String(s) => value::ValueType::StringValue(s.clone()),
+        FirestoreValue::Bytes(bytes) => value::ValueType::BlobValue(bytes.clone()),
+        FirestoreValue::Reference(reference) => {
+            let path = relative_document_path(reference);
+            let segments = path.split('/').count();
+            if segments < 2 || !segments.is_multiple_of(2) {
+                // Not a document path; keep the text rather than lose it
+                value::ValueType::StringValue(reference.clone())
+            } else {
+                let (project, database) = document_database(reference).unzip();
+                value::ValueType::KeyValue(path_key(path, project.as_deref(), database.as_deref(), None))
+            }
+        }
+        FirestoreValue::GeoPoint { latitude, longitude } => value::ValueType::GeoPointValue(LatLng {
+            latitude: *latitude,
+            longitude: *longitude,
+        }),
+        FirestoreValue::Array(values) => value::ValueType::ArrayValue(ArrayValue {
+            values: values.iter().map(entity_value).collect(),
+        }),
+        FirestoreValue::Map(fields) => value::ValueType::EntityValue(Entity {
+            key: None,
+            properties: fields.iter()
+                .map(|(name, value)| (name.clone(), entity_value(value)))
+                .collect(),
+        }),
+    };
+    Value { value_type: Some(value_type) }
+}
+
+/// Key of a `collection/id/...` document path
+fn path_key(path: &str, project: Option<&str>, database: Option<&str>, namespace: Option<&str>) -> Key {
+    let segments: Vec<&str> = path.split('/').collect();
+    Key {
+        partition_id: Some(PartitionId {
+            project_id: project.unwrap_or_default().to_string(),
+            database_id: database.unwrap_or_default().to_string(),
+            namespace_id: namespace.unwrap_or_default().to_string(),
+        }),
+        path: segments.chunks(2)
+            .map(|pair| key::PathElement {
+                kind: pair[0].to_string(),
+                id_type: pair.get(1).map(|id| key::path_element::IdType::Name(id.to_string())),
+            })
+            .collect(),
+    }
+}
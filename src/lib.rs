@@ -3,4 +3,5 @@ pub mod types;
 pub mod leveldb_parser;
 pub mod schema_analyzer;
 pub mod data_importer;
-pub mod monitoring;
\ No newline at end of file
+pub mod monitoring;
+pub mod protos;
\ No newline at end of file
@@ -9,6 +9,9 @@ pub mod google {
             }
         }
     }
+    pub mod r#type {
+        include!(concat!(env!("OUT_DIR"), "/google.r#type.rs"));
+    }
 }
 
 
@@ -1,13 +1,22 @@
 use crate::error::FireupResult;
 use crate::monitoring::{get_monitoring_system, AuditOperationType, AuditResult};
+use crate::schema_analyzer::sampling::DocumentSample;
 use crate::types::{
-    CollectionAnalysis, DetectedRelationship, FieldTypeAnalysis, FirestoreDocument,
+    CollectionAnalysis, DetectedRelationship, FieldConfidence, FieldTypeAnalysis, FirestoreDocument, FirestoreValue, FunctionalDependency,
     NormalizationImpact, NormalizationOpportunity, NormalizationType, PostgreSQLType,
-    RelationshipType, SchemaAnalysis,
+    RelationshipEvidence, RelationshipType, SchemaAnalysis, SizeDistribution, document_path_string, relative_document_path,
 };
-use serde_json::Value;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use tracing::{debug, info, instrument};
+use uuid::Uuid;
+
+/// Share of a field's distinct values that must be document IDs of another
+/// collection for the field to be taken as a foreign key to it
+const MIN_INCLUSION_RATIO: f64 = 0.9;
+
+/// Determinant values that must occur in more than one document before a
+/// functional dependency is trusted
+const MIN_REPEATED_DETERMINANT_VALUES: usize = 2;
 
 /// Document structure analyzer that detects field types and structures
 pub struct DocumentStructureAnalyzer {
@@ -31,6 +40,31 @@ impl DocumentStructureAnalyzer {
     pub async fn analyze_documents(
         &self,
         documents: &[FirestoreDocument],
+    ) -> FireupResult<SchemaAnalysis> {
+        self.analyze(documents, None).await
+    }
+
+    /// Analyze a sample drawn by a
+    /// [`DocumentSampler`](crate::schema_analyzer::sampling::DocumentSampler).
+    ///
+    /// Document counts, sizes and field presence are exact. Field types come
+    /// from the sampled documents, with a [`FieldConfidence`] per field;
+    /// relationships and functional dependencies come from the uniform
+    /// sample only. A field holding IDs is checked against the IDs the
+    /// target collection's sample would hold, so that sampling both sides
+    /// does not hide the foreign key.
+    #[instrument(skip(self, sample))]
+    pub async fn analyze_sample(&self, sample: &DocumentSample) -> FireupResult<SchemaAnalysis> {
+        let mut analysis = self.analyze(sample.documents(), Some(sample)).await?;
+        analysis.metadata.total_documents = sample.total_documents();
+        analysis.sampling = Some(sample.summary());
+        Ok(analysis)
+    }
+
+    async fn analyze(
+        &self,
+        documents: &[FirestoreDocument],
+        sample: Option<&DocumentSample>,
     ) -> FireupResult<SchemaAnalysis> {
         let tracker = get_monitoring_system()
             .start_operation("schema_analysis")
@@ -50,6 +84,16 @@ impl DocumentStructureAnalyzer {
 
         // Group documents by collection
         let collections = self.group_documents_by_collection(documents);
+        let child_collections = self.collect_child_collections(documents);
+        // Collection IDs a path string has to point into to count as a reference
+        let collection_ids: HashSet<String> = documents.iter()
+            .flat_map(|doc| std::iter::once(doc).chain(&doc.subcollections))
+            .map(|doc| doc.collection.clone())
+            .collect();
+        // Document IDs of each collection, for fields holding bare IDs
+        let document_ids: HashMap<String, HashSet<String>> = collections.iter()
+            .map(|(name, docs)| (name.clone(), docs.iter().map(|doc| doc.id.clone()).collect()))
+            .collect();
 
         // Analyze each collection
         for (collection_name, collection_docs) in collections {
@@ -59,34 +103,73 @@ impl DocumentStructureAnalyzer {
                 collection_docs.len()
             );
 
-            let collection_analysis = self
+            let mut collection_analysis = self
                 .analyze_collection(&collection_name, &collection_docs)
                 .await?;
+            // Rare-field documents are for typing fields only
+            let uniform_docs: Vec<&FirestoreDocument> = match sample {
+                Some(sample) => collection_docs.iter()
+                    .copied()
+                    .filter(|doc| sample.in_uniform_sample(&collection_name, &doc.id))
+                    .collect(),
+                None => collection_docs.clone(),
+            };
+            let counts = sample.and_then(|sample| sample.collection_counts(&collection_name));
+            if let Some(counts) = counts {
+                let sizes = &mut collection_analysis.size_distribution;
+                sizes.min_bytes = counts.min_bytes;
+                sizes.max_bytes = counts.max_bytes;
+                sizes.total_bytes = counts.total_bytes;
+                sizes.near_limit_count = counts.near_limit_count;
+                collection_analysis.document_count = counts.document_count;
+                collection_analysis.avg_document_size = counts.total_bytes as f64 / counts.document_count.max(1) as f64;
+            }
+            if let Some(children) = child_collections.get(&collection_name) {
+                for child in children {
+                    if !collection_analysis.subcollections.contains(child) {
+                        collection_analysis.subcollections.push(child.clone());
+                    }
+                }
+            }
             analysis.add_collection(collection_analysis);
 
             // Analyze field types for this collection
             let field_types = self
                 .analyze_field_types(&collection_name, &collection_docs)
                 .await?;
-            for field_type in field_types {
+            for mut field_type in field_types {
+                if let Some(counts) = counts {
+                    let total = counts.field_counts.get(&field_type.field_path).copied().unwrap_or(0);
+                    let dominant = field_type.type_frequencies.values().max().copied().unwrap_or(0);
+                    field_type.presence_percentage = total as f64 / counts.document_count.max(1) as f64 * 100.0;
+                    field_type.confidence = Some(FieldConfidence::estimate(
+                        total,
+                        field_type.total_occurrences as u64,
+                        dominant as u64,
+                    ));
+                }
                 analysis.add_field_type(field_type);
             }
 
             // Detect relationships within this collection
             let relationships = self
-                .detect_relationships(&collection_name, &collection_docs)
+                .detect_relationships(&collection_name, &uniform_docs, &collection_ids, &document_ids, sample)
                 .await?;
             for relationship in relationships {
                 analysis.add_relationship(relationship);
             }
 
-            // Find normalization opportunities
+            // Find functional dependencies and normalization opportunities
+            let dependencies = self.find_functional_dependencies(&collection_name, &uniform_docs);
             let opportunities = self
-                .find_normalization_opportunities(&collection_name, &collection_docs)
+                .find_normalization_opportunities(&collection_name, &uniform_docs, &dependencies)
                 .await?;
             for opportunity in opportunities {
                 analysis.add_normalization_opportunity(opportunity);
             }
+            for dependency in dependencies {
+                analysis.add_functional_dependency(dependency);
+            }
         }
 
         analysis.complete();
@@ -141,8 +224,11 @@ impl DocumentStructureAnalyzer {
         let mut collections = HashMap::new();
 
         for doc in documents {
+            // Documents in subcollections are grouped under their qualified
+            // name (e.g. `users_orders`) so they don't merge with a top-level
+            // collection of the same name
             collections
-                .entry(doc.collection.clone())
+                .entry(doc.qualified_collection())
                 .or_insert_with(Vec::new)
                 .push(doc);
 
@@ -159,6 +245,26 @@ impl DocumentStructureAnalyzer {
         collections
     }
 
+    /// Map each qualified collection to the subcollections found beneath its
+    /// documents, based on the documents' parent paths
+    fn collect_child_collections(
+        &self,
+        documents: &[FirestoreDocument],
+    ) -> HashMap<String, HashSet<String>> {
+        let mut children: HashMap<String, HashSet<String>> = HashMap::new();
+
+        for doc in documents {
+            if let Some((parent_collection, _)) = doc.parent_reference() {
+                children
+                    .entry(parent_collection)
+                    .or_default()
+                    .insert(doc.collection.clone());
+            }
+        }
+
+        children
+    }
+
     /// Analyze a specific collection
     async fn analyze_collection(
         &self,
@@ -166,7 +272,7 @@ impl DocumentStructureAnalyzer {
         documents: &[&FirestoreDocument],
     ) -> FireupResult<CollectionAnalysis> {
         let mut field_names = HashSet::new();
-        let mut total_size = 0u64;
+        let mut sizes = Vec::with_capacity(documents.len());
         let mut subcollections = HashSet::new();
 
         for doc in documents {
@@ -175,10 +281,8 @@ impl DocumentStructureAnalyzer {
                 field_names.insert(key.clone());
             }
 
-            // Calculate document size
-            if let Some(size) = doc.metadata.size_bytes {
-                total_size += size;
-            }
+            // Documents built in code carry no size; compute it the same way
+            sizes.push(doc.metadata.size_bytes.unwrap_or_else(|| doc.storage_size()));
 
             // Collect subcollection names
             for subdoc in &doc.subcollections {
@@ -186,10 +290,11 @@ impl DocumentStructureAnalyzer {
             }
         }
 
+        let size_distribution = SizeDistribution::from_sizes(sizes);
         let avg_document_size = if documents.is_empty() {
             0.0
         } else {
-            total_size as f64 / documents.len() as f64
+            size_distribution.total_bytes as f64 / documents.len() as f64
         };
 
         Ok(CollectionAnalysis {
@@ -197,6 +302,7 @@ impl DocumentStructureAnalyzer {
             document_count: documents.len() as u64,
             field_names: field_names.into_iter().collect(),
             avg_document_size,
+            size_distribution,
             subcollections: subcollections.into_iter().collect(),
         })
     }
@@ -226,6 +332,7 @@ impl DocumentStructureAnalyzer {
                 total_occurrences,
                 presence_percentage,
                 recommended_type,
+                confidence: None,
             });
         }
 
@@ -236,7 +343,7 @@ impl DocumentStructureAnalyzer {
     fn analyze_document_fields(
         &self,
         field_analysis: &mut HashMap<String, HashMap<String, u32>>,
-        data: &HashMap<String, Value>,
+        data: &HashMap<String, FirestoreValue>,
         collection_name: &str,
         parent_path: &str,
     ) -> FireupResult<()> {
@@ -256,16 +363,11 @@ impl DocumentStructureAnalyzer {
                 .and_modify(|count| *count += 1)
                 .or_insert(1);
 
-            // Recursively analyze nested objects
-            if let Value::Object(nested_obj) = value {
-                // Convert serde_json::Map to HashMap for recursive call
-                let nested_hashmap: HashMap<String, Value> = nested_obj
-                    .iter()
-                    .map(|(k, v)| (k.clone(), v.clone()))
-                    .collect();
+            // Recursively analyze nested maps
+            if let FirestoreValue::Map(nested) = value {
                 self.analyze_document_fields(
                     field_analysis,
-                    &nested_hashmap,
+                    nested,
                     collection_name,
                     &field_path,
                 )?;
@@ -275,30 +377,12 @@ impl DocumentStructureAnalyzer {
         Ok(())
     }
 
-    /// Get the type name of a JSON value
-    fn get_value_type_name(&self, value: &Value) -> String {
+    /// Get the type name of a Firestore value. Types come from the value
+    /// itself; the only string refinement is for canonical UUIDs.
+    fn get_value_type_name(&self, value: &FirestoreValue) -> String {
         match value {
-            Value::Null => "null".to_string(),
-            Value::Bool(_) => "boolean".to_string(),
-            Value::Number(n) => {
-                if n.is_i64() || n.is_u64() {
-                    "integer".to_string()
-                } else {
-                    "number".to_string()
-                }
-            }
-            Value::String(s) => {
-                // Try to detect special string types
-                if s.len() == 36 && s.chars().filter(|c| *c == '-').count() == 4 {
-                    "uuid".to_string()
-                } else if s.contains('T') && s.contains('Z') {
-                    "timestamp".to_string()
-                } else {
-                    "string".to_string()
-                }
-            }
-            Value::Array(_) => "array".to_string(),
-            Value::Object(_) => "object".to_string(),
+            FirestoreValue::String(s) if s.len() == 36 && Uuid::parse_str(s).is_ok() => "uuid".to_string(),
+            _ => value.type_name().to_string(),
         }
     }
 
@@ -326,6 +410,9 @@ impl DocumentStructureAnalyzer {
             "number" => PostgreSQLType::Numeric(None, None),
             "uuid" => PostgreSQLType::Uuid,
             "timestamp" => PostgreSQLType::Timestamp,
+            "bytes" => PostgreSQLType::Bytea,
+            "reference" => PostgreSQLType::Text,
+            "geopoint" => PostgreSQLType::Jsonb,
             "string" => {
                 // Determine appropriate string type based on length analysis
                 PostgreSQLType::Text
@@ -336,18 +423,23 @@ impl DocumentStructureAnalyzer {
         }
     }
 
-    /// Detect relationships between collections
+    /// Detect relationships between collections, from reference values and
+    /// path strings, and from fields holding the IDs of another collection's
+    /// documents
     async fn detect_relationships(
         &self,
         collection_name: &str,
         documents: &[&FirestoreDocument],
+        collection_ids: &HashSet<String>,
+        document_ids: &HashMap<String, HashSet<String>>,
+        sample: Option<&DocumentSample>,
     ) -> FireupResult<Vec<DetectedRelationship>> {
         let mut relationships = Vec::new();
         let mut reference_patterns = HashMap::new();
 
         // Analyze reference patterns
         for doc in documents {
-            self.analyze_reference_patterns(&mut reference_patterns, &doc.data, collection_name)?;
+            self.analyze_reference_patterns(&mut reference_patterns, &doc.data, collection_ids)?;
         }
 
         // Convert patterns to relationships
@@ -356,8 +448,15 @@ impl DocumentStructureAnalyzer {
                 let confidence = count as f64 / documents.len() as f64;
 
                 if confidence >= self.relationship_confidence_threshold {
+                    let targets_of = |value: &FirestoreValue| -> Vec<String> {
+                        referenced_paths(value, collection_ids)
+                            .into_iter()
+                            .filter(|path| path_collection(path) == Some(target_collection.as_str()))
+                            .map(str::to_string)
+                            .collect()
+                    };
                     let relationship_type = self
-                        .determine_relationship_type(collection_name, &target_collection, documents)
+                        .determine_relationship_type(&field_path, &target_collection, documents, targets_of)
                         .await?;
 
                     relationships.push(DetectedRelationship {
@@ -366,11 +465,103 @@ impl DocumentStructureAnalyzer {
                         reference_field: field_path.clone(),
                         relationship_type,
                         confidence,
+                        evidence: RelationshipEvidence::References,
                     });
                 }
             }
         }
 
+        // Fields already holding references are not looked at again
+        for relationship in self.detect_inclusion_dependencies(collection_name, documents, document_ids, sample).await? {
+            if !relationships.iter().any(|r| r.reference_field == relationship.reference_field) {
+                relationships.push(relationship);
+            }
+        }
+
+        Ok(relationships)
+    }
+
+    /// Find fields holding bare document IDs: a field is taken as a foreign
+    /// key to the collection whose document IDs contain (almost) all of its
+    /// distinct values.
+    ///
+    /// The confidence is `matching / (distinct + 1)`, so that a handful of
+    /// matching values is not taken as certain: 3 of 3 values give 0.75,
+    /// 19 of 19 give 0.95.
+    ///
+    /// With a sample, only the values the target collection's uniform
+    /// sample would hold are compared with its IDs.
+    async fn detect_inclusion_dependencies(
+        &self,
+        collection_name: &str,
+        documents: &[&FirestoreDocument],
+        document_ids: &HashMap<String, HashSet<String>>,
+        sample: Option<&DocumentSample>,
+    ) -> FireupResult<Vec<DetectedRelationship>> {
+        let mut field_values: HashMap<&str, HashSet<String>> = HashMap::new();
+        for doc in documents {
+            for (key, value) in &doc.data {
+                // A document's own ID is not a reference
+                let values: Vec<String> = id_values(value).into_iter().filter(|v| *v != doc.id).collect();
+                if !values.is_empty() {
+                    field_values.entry(key.as_str()).or_default().extend(values);
+                }
+            }
+        }
+
+        let mut relationships = Vec::new();
+        let mut fields: Vec<_> = field_values.into_iter().collect();
+        fields.sort_by(|a, b| a.0.cmp(b.0));
+        for (field_path, distinct) in fields {
+            // The target collection holding the most of the field's values
+            let best_match = document_ids.iter()
+                .filter(|(target_collection, _)| target_collection.as_str() != collection_name)
+                .map(|(target_collection, ids)| {
+                    let compared: HashSet<&String> = match sample {
+                        Some(sample) => distinct.iter()
+                            .filter(|value| sample.in_uniform_sample(target_collection, value))
+                            .collect(),
+                        None => distinct.iter().collect(),
+                    };
+                    let matching = compared.iter().filter(|value| ids.contains(value.as_str())).count();
+                    (target_collection, ids, compared.len(), matching)
+                })
+                .max_by(|a, b| a.3.cmp(&b.3).then_with(|| b.0.cmp(a.0)));
+            let Some((target_collection, ids, compared, matching)) = best_match else { continue };
+            if compared == 0 {
+                continue;
+            }
+
+            let inclusion_ratio = matching as f64 / compared as f64;
+            let confidence = matching as f64 / (compared + 1) as f64;
+            if inclusion_ratio < MIN_INCLUSION_RATIO || confidence < self.relationship_confidence_threshold {
+                continue;
+            }
+            debug!(
+                "{} of {} distinct values of {}.{} are document IDs of {}",
+                matching, compared, collection_name, field_path, target_collection
+            );
+
+            let targets_of = |value: &FirestoreValue| -> Vec<String> {
+                id_values(value).into_iter().filter(|id| ids.contains(id)).collect()
+            };
+            let relationship_type = self
+                .determine_relationship_type(field_path, target_collection, documents, targets_of)
+                .await?;
+
+            relationships.push(DetectedRelationship {
+                from_collection: collection_name.to_string(),
+                to_collection: target_collection.clone(),
+                reference_field: field_path.to_string(),
+                relationship_type,
+                confidence,
+                evidence: RelationshipEvidence::InclusionDependency {
+                    distinct_values: compared as u64,
+                    matching_values: matching as u64,
+                },
+            });
+        }
+
         Ok(relationships)
     }
 
@@ -378,42 +569,72 @@ impl DocumentStructureAnalyzer {
     fn analyze_reference_patterns(
         &self,
         patterns: &mut HashMap<String, HashMap<String, u32>>,
-        data: &HashMap<String, Value>,
-        collection_name: &str,
+        data: &HashMap<String, FirestoreValue>,
+        collection_ids: &HashSet<String>,
     ) -> FireupResult<()> {
         for (key, value) in data {
-            if let Value::String(s) = value {
-                // Look for reference-like patterns (e.g., collection/document_id)
-                if s.contains('/') {
-                    let parts: Vec<&str> = s.split('/').collect();
-                    if parts.len() >= 2 {
-                        let target_collection = parts[0].to_string();
-                        if target_collection != collection_name {
-                            patterns
-                                .entry(key.clone())
-                                .or_insert_with(HashMap::new)
-                                .entry(target_collection)
-                                .and_modify(|count| *count += 1)
-                                .or_insert(1);
-                        }
-                    }
-                }
+            // Reference values, and strings holding the path of a document in
+            // one of the analyzed collections (its own included); strings that
+            // merely contain a slash are not treated as references
+            let target_collections: HashSet<&str> = referenced_paths(value, collection_ids)
+                .into_iter()
+                .filter_map(path_collection)
+                .collect();
+            for target_collection in target_collections {
+                patterns
+                    .entry(key.clone())
+                    .or_insert_with(HashMap::new)
+                    .entry(target_collection.to_string())
+                    .and_modify(|count| *count += 1)
+                    .or_insert(1);
             }
         }
 
         Ok(())
     }
 
-    /// Determine the type of relationship between collections
+    /// Determine the cardinality of the references a field holds to another
+    /// collection, seen from the referencing collection. `targets_of` gives
+    /// the target documents a value of the field points at.
+    ///
+    /// A field holding arrays of references is many-to-many. A field holding
+    /// single references is many-to-one when some target document is
+    /// referenced by several documents, and one-to-one otherwise.
     async fn determine_relationship_type(
         &self,
-        _from_collection: &str,
-        _to_collection: &str,
-        _documents: &[&FirestoreDocument],
+        field_path: &str,
+        to_collection: &str,
+        documents: &[&FirestoreDocument],
+        targets_of: impl Fn(&FirestoreValue) -> Vec<String>,
     ) -> FireupResult<RelationshipType> {
-        // For now, assume most relationships are one-to-many
-        // This could be enhanced with more sophisticated analysis
-        Ok(RelationshipType::OneToMany)
+        let mut multi_valued = false;
+        let mut sources_per_target: HashMap<String, u32> = HashMap::new();
+
+        for doc in documents {
+            let Some(value) = doc.data.get(field_path) else { continue };
+            let targets: HashSet<String> = targets_of(value).into_iter().collect();
+            if targets.is_empty() {
+                continue;
+            }
+            if matches!(value, FirestoreValue::Array(_)) {
+                multi_valued = true;
+            }
+            for target in targets {
+                *sources_per_target.entry(target).or_insert(0) += 1;
+            }
+        }
+
+        let shared_targets = sources_per_target.values().any(|sources| *sources > 1);
+        debug!(
+            "Field {} -> {}: multi-valued {}, {} targets, shared targets {}",
+            field_path, to_collection, multi_valued, sources_per_target.len(), shared_targets
+        );
+
+        Ok(match (multi_valued, shared_targets) {
+            (true, _) => RelationshipType::ManyToMany,
+            (false, true) => RelationshipType::ManyToOne,
+            (false, false) => RelationshipType::OneToOne,
+        })
     }
 
     /// Find normalization opportunities in a collection
@@ -421,6 +642,7 @@ impl DocumentStructureAnalyzer {
         &self,
         collection_name: &str,
         documents: &[&FirestoreDocument],
+        dependencies: &[FunctionalDependency],
     ) -> FireupResult<Vec<NormalizationOpportunity>> {
         let mut opportunities = Vec::new();
 
@@ -432,13 +654,13 @@ impl DocumentStructureAnalyzer {
 
         // Analyze for 2NF violations (partial dependencies)
         let second_nf_opportunities = self
-            .find_second_normal_form_opportunities(collection_name, documents)
+            .find_second_normal_form_opportunities(collection_name, dependencies)
             .await?;
         opportunities.extend(second_nf_opportunities);
 
         // Analyze for 3NF violations (transitive dependencies)
         let third_nf_opportunities = self
-            .find_third_normal_form_opportunities(collection_name, documents)
+            .find_third_normal_form_opportunities(collection_name, dependencies)
             .await?;
         opportunities.extend(third_nf_opportunities);
 
@@ -487,7 +709,7 @@ impl DocumentStructureAnalyzer {
     fn find_array_fields(
         &self,
         array_fields: &mut HashMap<String, u32>,
-        data: &HashMap<String, Value>,
+        data: &HashMap<String, FirestoreValue>,
         parent_path: &str,
     ) -> FireupResult<()> {
         for (key, value) in data {
@@ -498,19 +720,14 @@ impl DocumentStructureAnalyzer {
             };
 
             match value {
-                Value::Array(_) => {
+                FirestoreValue::Array(_) => {
                     array_fields
                         .entry(field_path)
                         .and_modify(|count| *count += 1)
                         .or_insert(1);
                 }
-                Value::Object(nested_obj) => {
-                    // Convert serde_json::Map to HashMap for recursive call
-                    let nested_hashmap: HashMap<String, Value> = nested_obj
-                        .iter()
-                        .map(|(k, v)| (k.clone(), v.clone()))
-                        .collect();
-                    self.find_array_fields(array_fields, &nested_hashmap, &field_path)?;
+                FirestoreValue::Map(nested) => {
+                    self.find_array_fields(array_fields, nested, &field_path)?;
                 }
                 _ => {}
             }
@@ -519,53 +736,219 @@ impl DocumentStructureAnalyzer {
         Ok(())
     }
 
-    /// Find Second Normal Form opportunities (eliminate partial dependencies)
+    /// Find Second Normal Form opportunities (eliminate partial dependencies):
+    /// fields of subcollection documents that depend on the parent document
+    /// alone
     async fn find_second_normal_form_opportunities(
         &self,
         collection_name: &str,
-        _documents: &[&FirestoreDocument],
+        dependencies: &[FunctionalDependency],
     ) -> FireupResult<Vec<NormalizationOpportunity>> {
-        let mut opportunities = Vec::new();
-
-        // For now, we'll add a placeholder opportunity
-        // This would require more sophisticated analysis of composite keys and dependencies
-        opportunities.push(NormalizationOpportunity {
-            collection: collection_name.to_string(),
-            field_path: "composite_key_analysis".to_string(),
-            normalization_type: NormalizationType::SecondNormalForm,
-            description: "Potential partial dependency detected - requires manual review"
-                .to_string(),
-            impact: NormalizationImpact::Medium,
-        });
-
-        Ok(opportunities)
+        Ok(dependency_opportunities(collection_name, dependencies, NormalizationType::SecondNormalForm))
     }
 
-    /// Find Third Normal Form opportunities (eliminate transitive dependencies)
+    /// Find Third Normal Form opportunities (eliminate transitive
+    /// dependencies): fields that depend on another non-key field
     async fn find_third_normal_form_opportunities(
         &self,
         collection_name: &str,
-        _documents: &[&FirestoreDocument],
+        dependencies: &[FunctionalDependency],
     ) -> FireupResult<Vec<NormalizationOpportunity>> {
-        let mut opportunities = Vec::new();
+        Ok(dependency_opportunities(collection_name, dependencies, NormalizationType::ThirdNormalForm))
+    }
+
+    /// Mine functional dependencies `X -> Y` between the top-level scalar
+    /// fields of a collection, plus the parent document of subcollection
+    /// documents as a determinant.
+    ///
+    /// A dependency holds when every document with the same value of `X`
+    /// has the same value of `Y` (absent counts as a value), at least
+    /// [`MIN_REPEATED_DETERMINANT_VALUES`] values of `X` occur more than once
+    /// and `Y` takes at least two values. Of fields determining each other
+    /// (`customerId` and `customerEmail`), one is kept as the determinant;
+    /// dependents reached through another determinant (`productId ->
+    /// categoryId -> categoryName`) are left to that determinant.
+    fn find_functional_dependencies(
+        &self,
+        collection_name: &str,
+        documents: &[&FirestoreDocument],
+    ) -> Vec<FunctionalDependency> {
+        // Scalar values of each document, as comparable strings
+        let mut rows: Vec<HashMap<String, String>> = Vec::with_capacity(documents.len());
+        let mut nested_fields = HashSet::new();
+        let parent_key = documents.iter()
+            .find_map(|doc| doc.parent_reference())
+            .map(|(parent_collection, _)| format!("{}_id", parent_collection));
+        for doc in documents {
+            let mut row = HashMap::new();
+            for (key, value) in &doc.data {
+                if matches!(value, FirestoreValue::Array(_) | FirestoreValue::Map(_)) {
+                    nested_fields.insert(key.clone());
+                } else {
+                    row.insert(key.clone(), value.to_json().to_string());
+                }
+            }
+            if let (Some(parent_key), Some((_, parent_id))) = (&parent_key, doc.parent_reference()) {
+                row.insert(parent_key.clone(), parent_id);
+            }
+            rows.push(row);
+        }
+        let fields: BTreeSet<&str> = rows.iter()
+            .flat_map(|row| row.keys().map(String::as_str))
+            .filter(|field| !nested_fields.contains(*field))
+            .collect();
+
+        // Dependents, documents and distinct values of each determinant
+        let mut dependencies: BTreeMap<&str, (BTreeSet<&str>, u64, u64)> = BTreeMap::new();
+        for &determinant in &fields {
+            let mut groups: HashMap<&str, Vec<&HashMap<String, String>>> = HashMap::new();
+            for row in &rows {
+                if let Some(value) = row.get(determinant) {
+                    groups.entry(value.as_str()).or_default().push(row);
+                }
+            }
+            let repeated = groups.values().filter(|group| group.len() > 1).count();
+            if repeated < MIN_REPEATED_DETERMINANT_VALUES {
+                continue;
+            }
 
-        // For now, we'll add a placeholder opportunity
-        // This would require analysis of transitive dependencies
-        opportunities.push(NormalizationOpportunity {
-            collection: collection_name.to_string(),
-            field_path: "transitive_dependency_analysis".to_string(),
-            normalization_type: NormalizationType::ThirdNormalForm,
-            description: "Potential transitive dependency detected - requires manual review"
-                .to_string(),
-            impact: NormalizationImpact::Low,
-        });
+            let dependents: BTreeSet<&str> = fields.iter()
+                .copied()
+                .filter(|field| *field != determinant && Some(*field) != parent_key.as_deref())
+                .filter(|field| {
+                    let mut values = HashSet::new();
+                    let consistent = groups.values().all(|group| {
+                        let value = group[0].get(*field);
+                        values.extend(value);
+                        group.iter().all(|row| row.get(*field) == value)
+                    });
+                    consistent && values.len() > 1
+                })
+                .collect();
+            if !dependents.is_empty() {
+                let document_count = groups.values().map(|group| group.len() as u64).sum();
+                dependencies.insert(determinant, (dependents, document_count, groups.len() as u64));
+            }
+        }
 
-        Ok(opportunities)
+        // Of two fields determining each other, keep the parent document,
+        // then an ID-like field, then the first by name
+        let looks_like_id = |field: &str| field == "id" || ["Id", "ID", "_id"].iter().any(|suffix| field.ends_with(suffix));
+        let rank = |field: &str| (Some(field) != parent_key.as_deref(), !looks_like_id(field), field.to_string());
+        let determinants: BTreeSet<&str> = dependencies.iter()
+            .filter(|(determinant, (dependents, _, _))| !dependents.iter().any(|dependent| {
+                dependencies.get(dependent).is_some_and(|(other, _, _)| other.contains(*determinant))
+                    && rank(dependent) < rank(determinant)
+            }))
+            .map(|(determinant, _)| *determinant)
+            .collect();
+
+        let mut found = Vec::new();
+        for &determinant in &determinants {
+            let (dependents, document_count, distinct_values) = &dependencies[determinant];
+            let direct: Vec<String> = dependents.iter()
+                .filter(|dependent| !dependents.iter().any(|via| {
+                    via != *dependent
+                        && determinants.contains(via)
+                        && dependencies[via].0.contains(*dependent)
+                }))
+                .map(|dependent| dependent.to_string())
+                .collect();
+            if direct.is_empty() {
+                continue;
+            }
+            debug!("{}: {} determines {}", collection_name, determinant, direct.join(", "));
+            found.push(FunctionalDependency {
+                collection: collection_name.to_string(),
+                determinant: determinant.to_string(),
+                dependents: direct,
+                document_count: *document_count,
+                distinct_values: *distinct_values,
+                normalization_type: if Some(determinant) == parent_key.as_deref() {
+                    NormalizationType::SecondNormalForm
+                } else {
+                    NormalizationType::ThirdNormalForm
+                },
+            });
+        }
+        found
     }
 }
 
+/// Normalization opportunities for the dependencies of one normal form
+fn dependency_opportunities(
+    collection_name: &str,
+    dependencies: &[FunctionalDependency],
+    normalization_type: NormalizationType,
+) -> Vec<NormalizationOpportunity> {
+    dependencies.iter()
+        .filter(|dependency| dependency.normalization_type == normalization_type)
+        .map(|dependency| {
+            let redundancy = dependency.redundancy();
+            NormalizationOpportunity {
+                collection: collection_name.to_string(),
+                field_path: dependency.determinant.clone(),
+                normalization_type: normalization_type.clone(),
+                description: format!(
+                    "{} determines {} in all {} documents holding it ({} distinct values)",
+                    dependency.determinant,
+                    dependency.dependents.join(", "),
+                    dependency.document_count,
+                    dependency.distinct_values
+                ),
+                impact: if redundancy > 0.5 {
+                    NormalizationImpact::High
+                } else if redundancy > 0.3 {
+                    NormalizationImpact::Medium
+                } else {
+                    NormalizationImpact::Low
+                },
+            }
+        })
+        .collect()
+}
+
 impl Default for DocumentStructureAnalyzer {
     fn default() -> Self {
         Self::new()
     }
 }
+
+/// Paths of the documents a field points at: the value itself, or the
+/// elements of an array, when they are references or document path strings
+fn referenced_paths<'a>(value: &'a FirestoreValue, collection_ids: &HashSet<String>) -> Vec<&'a str> {
+    let values = match value {
+        FirestoreValue::Array(values) => values.as_slice(),
+        other => std::slice::from_ref(other),
+    };
+    values.iter()
+        .filter_map(|value| match value {
+            FirestoreValue::Reference(path) => {
+                value.reference_target().map(|_| relative_document_path(path))
+            }
+            FirestoreValue::String(s) => document_path_string(s, collection_ids),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Values of a field that may be bare document IDs: strings without a `/`
+/// and integers (numeric IDs), alone or as array elements
+fn id_values(value: &FirestoreValue) -> Vec<String> {
+    let values = match value {
+        FirestoreValue::Array(values) => values.as_slice(),
+        other => std::slice::from_ref(other),
+    };
+    values.iter()
+        .filter_map(|value| match value {
+            FirestoreValue::String(s) if !s.is_empty() && !s.contains('/') => Some(s.clone()),
+            FirestoreValue::Integer(i) => Some(i.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Collection ID of a document path
+fn path_collection(path: &str) -> Option<&str> {
+    path.rsplit('/').nth(1).filter(|collection| !collection.is_empty())
+}
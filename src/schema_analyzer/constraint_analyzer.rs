@@ -1,9 +1,8 @@
 use crate::error::FireupResult;
 use crate::types::{
-    FirestoreDocument, SchemaAnalysis, Constraint, ConstraintType
+    FirestoreDocument, FirestoreValue, SchemaAnalysis, Constraint, ConstraintType
 };
 use std::collections::{HashMap, HashSet};
-use serde_json::Value;
 use tracing::{info, debug, warn};
 
 /// Constraint analyzer for determining column constraints
@@ -160,7 +159,7 @@ impl ConstraintAnalyzer {
     fn collect_field_values(
         &self,
         field_values: &mut HashMap<String, HashSet<String>>,
-        data: &HashMap<String, Value>,
+        data: &HashMap<String, FirestoreValue>,
         parent_path: &str,
     ) -> FireupResult<()> {
         for (key, value) in data {
@@ -172,20 +171,15 @@ impl ConstraintAnalyzer {
             
             // Convert value to string for uniqueness analysis
             let value_str = match value {
-                Value::Null => continue, // Skip null values
-                Value::Bool(b) => b.to_string(),
-                Value::Number(n) => n.to_string(),
-                Value::String(s) => s.clone(),
-                Value::Array(_) => continue, // Skip arrays for uniqueness
-                Value::Object(nested_obj) => {
-                    // Recursively analyze nested objects
-                    // Convert serde_json::Map to HashMap for recursive call
-                    let nested_hashmap: HashMap<String, Value> = nested_obj.iter()
-                        .map(|(k, v)| (k.clone(), v.clone()))
-                        .collect();
-                    self.collect_field_values(field_values, &nested_hashmap, &field_path)?;
+                FirestoreValue::Null => continue, // Skip null values
+                FirestoreValue::Array(_) => continue, // Skip arrays for uniqueness
+                FirestoreValue::Map(nested) => {
+                    // Recursively analyze nested maps
+                    self.collect_field_values(field_values, nested, &field_path)?;
                     continue;
                 }
+                FirestoreValue::String(s) | FirestoreValue::Reference(s) => s.clone(),
+                other => other.to_json().to_string(),
             };
             
             field_values.entry(field_path)
@@ -222,7 +216,7 @@ impl ConstraintAnalyzer {
     fn analyze_field_ranges(
         &self,
         field_ranges: &mut HashMap<String, FieldRange>,
-        data: &HashMap<String, Value>,
+        data: &HashMap<String, FirestoreValue>,
         parent_path: &str,
     ) -> FireupResult<()> {
         for (key, value) in data {
@@ -233,19 +227,15 @@ impl ConstraintAnalyzer {
             };
             
             match value {
-                Value::Number(n) => {
+                FirestoreValue::Integer(_) | FirestoreValue::Double(_) => {
                     let range = field_ranges.entry(field_path).or_insert_with(FieldRange::new);
                     
-                    if let Some(f) = n.as_f64() {
+                    if let Some(f) = value.as_f64() {
                         range.update(f);
                     }
                 }
-                Value::Object(nested_obj) => {
-                    // Convert serde_json::Map to HashMap for recursive call
-                    let nested_hashmap: HashMap<String, Value> = nested_obj.iter()
-                        .map(|(k, v)| (k.clone(), v.clone()))
-                        .collect();
-                    self.analyze_field_ranges(field_ranges, &nested_hashmap, &field_path)?;
+                FirestoreValue::Map(nested) => {
+                    self.analyze_field_ranges(field_ranges, nested, &field_path)?;
                 }
                 _ => {} // Skip non-numeric values
             }
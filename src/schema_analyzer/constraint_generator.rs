@@ -1,6 +1,6 @@
 use crate::types::{
     NormalizedSchema, Constraint, ConstraintType,
-    SchemaAnalysis, FieldTypeAnalysis
+    SchemaAnalysis, FieldTypeAnalysis, PostgreSQLType, RelationshipType, RelationshipEvidence
 };
 use crate::error::FireupResult;
 use std::collections::{HashMap, HashSet};
@@ -330,6 +330,19 @@ impl ConstraintGenerator {
             .collect();
 
         for relationship in &analysis.relationships {
+            // Many-to-many references live in junction tables, not in a column
+            if matches!(relationship.relationship_type, RelationshipType::ManyToMany) {
+                continue;
+            }
+            // A column still holding document paths or IDs rather than UUID
+            // keys cannot reference the `id` of the other table
+            let holds_keys = schema.tables.iter()
+                .find(|table| table.name == relationship.from_collection)
+                .and_then(|table| table.columns.iter().find(|column| column.name == relationship.reference_field))
+                .is_some_and(|column| matches!(column.column_type, PostgreSQLType::Uuid));
+            if !holds_keys {
+                continue;
+            }
             let fk_key = format!("{}:{}", relationship.reference_field, relationship.to_collection);
             
             if !existing_fks.contains(&fk_key) && relationship.confidence >= 0.8 {
@@ -352,10 +365,17 @@ impl ConstraintGenerator {
                         constraint_type: ConstraintType::ForeignKey,
                         table: relationship.from_collection.clone(),
                         columns: vec![relationship.reference_field.clone()],
-                        reason: format!(
-                            "Detected relationship with {:.1}% confidence",
-                            relationship.confidence * 100.0
-                        ),
+                        reason: match &relationship.evidence {
+                            RelationshipEvidence::References => format!(
+                                "Detected relationship with {:.1}% confidence",
+                                relationship.confidence * 100.0
+                            ),
+                            RelationshipEvidence::InclusionDependency { distinct_values, matching_values } => format!(
+                                "{} of {} distinct values are document IDs of {}; detected relationship with {:.1}% confidence",
+                                matching_values, distinct_values, relationship.to_collection,
+                                relationship.confidence * 100.0
+                            ),
+                        },
                         confidence: relationship.confidence,
                         suggested_definition: format!(
                             "ALTER TABLE {} ADD CONSTRAINT fk_{}_{} FOREIGN KEY ({}) REFERENCES {} (id);",
@@ -427,6 +447,7 @@ mod tests {
             total_occurrences: 100,
             presence_percentage: 98.0, // High presence > 95% threshold
             recommended_type: PostgreSQLType::Varchar(Some(255)),
+            confidence: None,
         });
         
         // Medium presence field - should generate recommendation
@@ -436,6 +457,7 @@ mod tests {
             total_occurrences: 80,
             presence_percentage: 85.0, // Medium presence (80-95%)
             recommended_type: PostgreSQLType::Integer,
+            confidence: None,
         });
         
         // Low presence field - should not generate constraint
@@ -445,6 +467,7 @@ mod tests {
             total_occurrences: 30,
             presence_percentage: 30.0, // Low presence < 80%
             recommended_type: PostgreSQLType::Varchar(Some(20)),
+            confidence: None,
         });
 
         // Add relationship for foreign key constraint testing (using a field not already covered by existing FK)
@@ -454,6 +477,7 @@ mod tests {
             reference_field: "author_id".to_string(),
             relationship_type: RelationshipType::ManyToOne,
             confidence: 0.96, // High confidence > 0.95 to generate constraint
+            evidence: RelationshipEvidence::References,
         });
         
         analysis
@@ -469,6 +493,7 @@ mod tests {
             total_occurrences: 95,
             presence_percentage: 95.0, // Exactly at threshold
             recommended_type: PostgreSQLType::Varchar(Some(100)),
+            confidence: None,
         });
         
         analysis
@@ -758,7 +783,8 @@ mod tests {
     #[test]
     fn test_medium_confidence_relationship_recommendation() {
         let generator = ConstraintGenerator::new();
-        let schema = create_test_schema();
+        let mut schema = create_test_schema();
+        schema.tables[1].add_column(ColumnDefinition::new("reviewer_id".to_string(), PostgreSQLType::Uuid));
         let mut analysis = create_test_analysis();
         
         // Add medium confidence relationship
@@ -768,6 +794,7 @@ mod tests {
             reference_field: "reviewer_id".to_string(),
             relationship_type: RelationshipType::ManyToOne,
             confidence: 0.85, // Medium confidence (between 0.8 and 0.95)
+            evidence: RelationshipEvidence::References,
         });
         
         let result = generator.generate_constraints(&schema, &analysis).unwrap();
@@ -1,5 +1,5 @@
 use crate::types::{
-    NormalizedSchema, SchemaAnalysis, WarningLevel
+    NormalizedSchema, SchemaAnalysis, SizeDistribution, WarningLevel
 };
 use crate::schema_analyzer::{
     DDLGenerator, GeneratedDDL, ConstraintGenerator, IndexGenerator,
@@ -86,6 +86,8 @@ pub struct CollectionSummary {
     pub field_count: usize,
     /// Average document size
     pub avg_size_bytes: f64,
+    /// Distribution of document sizes
+    pub size_distribution: SizeDistribution,
     /// Nested subcollections
     pub subcollections: Vec<String>,
 }
@@ -243,6 +245,7 @@ impl DDLOutputManager {
                 document_count: col.document_count,
                 field_count: col.field_names.len(),
                 avg_size_bytes: col.avg_document_size,
+                size_distribution: col.size_distribution.clone(),
                 subcollections: col.subcollections.clone(),
             })
             .collect();
@@ -538,6 +541,18 @@ impl DDLOutputManager {
             content.push_str(&format!("- **Documents**: {}\n", collection.document_count));
             content.push_str(&format!("- **Fields**: {}\n", collection.field_count));
             content.push_str(&format!("- **Avg Size**: {:.2} bytes\n", collection.avg_size_bytes));
+            let sizes = &collection.size_distribution;
+            content.push_str(&format!(
+                "- **Size Distribution**: min {} / median {} / p95 {} / p99 {} / max {} bytes\n",
+                sizes.min_bytes, sizes.median_bytes, sizes.p95_bytes, sizes.p99_bytes, sizes.max_bytes
+            ));
+            content.push_str(&format!("- **Total Size**: {} bytes\n", sizes.total_bytes));
+            if sizes.near_limit_count > 0 {
+                content.push_str(&format!(
+                    "- **Near 1 MiB Limit**: {} documents\n",
+                    sizes.near_limit_count
+                ));
+            }
             if !collection.subcollections.is_empty() {
                 content.push_str(&format!("- **Subcollections**: {}\n", collection.subcollections.join(", ")));
             }
@@ -721,6 +736,7 @@ mod tests {
             document_count: 1000,
             field_names: vec!["id".to_string(), "email".to_string(), "name".to_string(), "created_at".to_string()],
             avg_document_size: 512.0,
+            size_distribution: SizeDistribution::default(),
             subcollections: vec!["posts".to_string()],
         });
         
@@ -729,6 +745,7 @@ mod tests {
             document_count: 5000,
             field_names: vec!["id".to_string(), "user_id".to_string(), "title".to_string(), "content".to_string()],
             avg_document_size: 1024.0,
+            size_distribution: SizeDistribution::default(),
             subcollections: Vec::new(),
         });
         
@@ -739,6 +756,7 @@ mod tests {
             total_occurrences: 1000,
             presence_percentage: 100.0,
             recommended_type: PostgreSQLType::Varchar(Some(255)),
+            confidence: None,
         });
         
         // Add type conflict
@@ -748,6 +766,7 @@ mod tests {
             total_occurrences: 1000,
             presence_percentage: 90.0,
             recommended_type: PostgreSQLType::Integer,
+            confidence: None,
         });
         
         // Add relationship
@@ -757,6 +776,7 @@ mod tests {
             reference_field: "user_id".to_string(),
             relationship_type: RelationshipType::ManyToOne,
             confidence: 0.95,
+            evidence: RelationshipEvidence::References,
         });
         
         // Add normalization opportunity
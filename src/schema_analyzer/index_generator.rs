@@ -477,7 +477,7 @@ impl IndexGenerator {
     /// Assess storage overhead of an index
     fn assess_storage_overhead(&self, column: &ColumnDefinition, _field_analysis: &FieldTypeAnalysis) -> StorageOverhead {
         match &column.column_type {
-            PostgreSQLType::Text => StorageOverhead::High,
+            PostgreSQLType::Text | PostgreSQLType::Bytea => StorageOverhead::High,
             PostgreSQLType::Varchar(Some(len)) if *len > 100 => StorageOverhead::Medium,
             PostgreSQLType::Varchar(_) => StorageOverhead::Low,
             PostgreSQLType::Jsonb => StorageOverhead::High,
@@ -624,6 +624,7 @@ mod tests {
             total_occurrences: 100,
             presence_percentage: 98.0, // High presence
             recommended_type: PostgreSQLType::Varchar(Some(255)),
+            confidence: None,
         });
         
         analysis.add_field_type(FieldTypeAnalysis {
@@ -632,6 +633,7 @@ mod tests {
             total_occurrences: 95,
             presence_percentage: 95.0, // High presence
             recommended_type: PostgreSQLType::Varchar(Some(100)),
+            confidence: None,
         });
         
         analysis.add_field_type(FieldTypeAnalysis {
@@ -640,6 +642,7 @@ mod tests {
             total_occurrences: 100,
             presence_percentage: 95.0, // High presence
             recommended_type: PostgreSQLType::Varchar(Some(50)),
+            confidence: None,
         });
         
         analysis.add_field_type(FieldTypeAnalysis {
@@ -648,6 +651,7 @@ mod tests {
             total_occurrences: 100,
             presence_percentage: 100.0, // Perfect presence
             recommended_type: PostgreSQLType::Timestamp,
+            confidence: None,
         });
         
         // Medium presence field
@@ -657,6 +661,7 @@ mod tests {
             total_occurrences: 80,
             presence_percentage: 80.0, // Medium presence
             recommended_type: PostgreSQLType::Varchar(Some(100)),
+            confidence: None,
         });
         
         // Posts table fields
@@ -666,6 +671,7 @@ mod tests {
             total_occurrences: 100,
             presence_percentage: 100.0,
             recommended_type: PostgreSQLType::Text,
+            confidence: None,
         });
         
         analysis
@@ -758,6 +764,7 @@ mod tests {
             total_occurrences: 100,
             presence_percentage: 98.0,
             recommended_type: PostgreSQLType::Varchar(Some(255)),
+            confidence: None,
         };
         
         let (should_index, confidence) = generator.should_index_field(&email_column, &email_analysis);
@@ -772,6 +779,7 @@ mod tests {
             total_occurrences: 100,
             presence_percentage: 100.0,
             recommended_type: PostgreSQLType::Timestamp,
+            confidence: None,
         };
         
         let (should_index, confidence) = generator.should_index_field(&timestamp_column, &timestamp_analysis);
@@ -786,6 +794,7 @@ mod tests {
             total_occurrences: 10,
             presence_percentage: 10.0,
             recommended_type: PostgreSQLType::Text,
+            confidence: None,
         };
         
         let (should_index, confidence) = generator.should_index_field(&rare_column, &rare_analysis);
@@ -901,6 +910,7 @@ mod tests {
             total_occurrences: 100,
             presence_percentage: 98.0,
             recommended_type: PostgreSQLType::Varchar(Some(255)),
+            confidence: None,
         };
         
         let impact = generator.assess_performance_impact(&email_column, &email_analysis);
@@ -914,6 +924,7 @@ mod tests {
             total_occurrences: 80,
             presence_percentage: 80.0,
             recommended_type: PostgreSQLType::Timestamp,
+            confidence: None,
         };
         
         let impact = generator.assess_performance_impact(&timestamp_column, &timestamp_analysis);
@@ -927,6 +938,7 @@ mod tests {
             total_occurrences: 50,
             presence_percentage: 50.0,
             recommended_type: PostgreSQLType::Integer,
+            confidence: None,
         };
         
         let impact = generator.assess_performance_impact(&low_column, &low_analysis);
@@ -942,6 +954,7 @@ mod tests {
             total_occurrences: 100,
             presence_percentage: 100.0,
             recommended_type: PostgreSQLType::Text,
+            confidence: None,
         };
         
         // High overhead (TEXT)
@@ -6,6 +6,7 @@ pub mod ddl_generator;
 pub mod ddl_output;
 pub mod index_generator;
 pub mod normalizer;
+pub mod sampling;
 pub mod type_conflict_resolver;
 
 #[cfg(test)]
@@ -16,3 +17,4 @@ pub use constraint_generator::*;
 pub use ddl_generator::*;
 pub use index_generator::*;
 pub use normalizer::*;
+pub use sampling::*;
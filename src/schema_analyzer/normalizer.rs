@@ -2,7 +2,8 @@ use crate::error::FireupResult;
 use crate::types::{
     SchemaAnalysis, NormalizedSchema, TableDefinition, ColumnDefinition, 
     PostgreSQLType, ForeignKeyDefinition, IndexDefinition, Relationship, SchemaWarning, WarningLevel, SchemaMetadata,
-    RelationshipType, NormalizationType, NormalizationOpportunity, PrimaryKeyDefinition
+    RelationshipType, NormalizationType, NormalizationOpportunity, PrimaryKeyDefinition, DetectedRelationship,
+    FunctionalDependency
 };
 use tracing::{info, debug};
 use chrono::Utc;
@@ -54,6 +55,9 @@ impl NormalizationEngine {
         // Apply First Normal Form (1NF) - eliminate repeating groups
         self.apply_first_normal_form(analysis, &mut normalized_schema)?;
         
+        // Link subcollection tables to their parent collection's table
+        self.link_subcollection_tables(analysis, &mut normalized_schema)?;
+        
         // Apply Second Normal Form (2NF) - eliminate partial dependencies
         self.apply_second_normal_form(analysis, &mut normalized_schema)?;
         
@@ -99,8 +103,11 @@ impl NormalizationEngine {
                     .strip_prefix(&format!("{}.", collection.name))
                     .unwrap_or(&field_type.field_path);
                 
-                // Check if this field represents an array that should be normalized
-                if self.should_normalize_array(field_type, &analysis.normalization_opportunities) {
+                // Arrays of references to another collection become junction
+                // tables; check if any other array should be normalized
+                if let Some(relationship) = many_to_many_relationship(analysis, &collection.name, field_name) {
+                    array_tables.push(self.create_junction_table(relationship, schema));
+                } else if self.should_normalize_array(field_type, &analysis.normalization_opportunities) {
                     // Create separate table for array elements
                     let array_table_name = format!("{}_{}", collection.name, field_name);
                     let mut array_table = TableDefinition::new(array_table_name.clone());
@@ -158,6 +165,89 @@ impl NormalizationEngine {
         Ok(())
     }
 
+    /// Create the junction table of a many-to-many relationship, named
+    /// `{collection}_{field}`, with a foreign key to each side and a composite
+    /// primary key over both. The owning side comes first; when an array
+    /// references its own collection, the referenced side is named after the
+    /// field (`users_id`, `friendIds_id`).
+    fn create_junction_table(&self, relationship: &DetectedRelationship, schema: &mut NormalizedSchema) -> TableDefinition {
+        let table_name = format!("{}_{}", relationship.from_collection, relationship.reference_field);
+        let mut junction_table = TableDefinition::new(table_name.clone());
+        
+        let target_name = if relationship.from_collection == relationship.to_collection {
+            &relationship.reference_field
+        } else {
+            &relationship.to_collection
+        };
+        let sides = [
+            (&relationship.from_collection, &relationship.from_collection),
+            (target_name, &relationship.to_collection),
+        ];
+        for (name, side) in sides {
+            let column = format!("{}_id", name);
+            junction_table.add_column(ColumnDefinition::new(column.clone(), PostgreSQLType::Uuid).not_null());
+            junction_table.add_foreign_key(ForeignKeyDefinition {
+                column: column.clone(),
+                referenced_table: side.clone(),
+                referenced_column: "id".to_string(),
+                constraint_name: format!("fk_{}_{}", table_name, name),
+            });
+            schema.relationships.push(Relationship {
+                from_table: table_name.clone(),
+                to_table: side.clone(),
+                from_column: column,
+                to_column: "id".to_string(),
+                relationship_type: RelationshipType::ManyToOne,
+            });
+        }
+        junction_table.set_primary_key(PrimaryKeyDefinition {
+            name: format!("{}_pkey", table_name),
+            columns: sides.iter().map(|(name, _)| format!("{}_id", name)).collect(),
+        });
+        
+        junction_table
+    }
+
+    /// Add a foreign key from each subcollection table (`{parent}_{subcollection}`)
+    /// to the table of its parent collection
+    fn link_subcollection_tables(&self, analysis: &SchemaAnalysis, schema: &mut NormalizedSchema) -> FireupResult<()> {
+        debug!("Linking subcollection tables to parent tables");
+        
+        for collection in &analysis.collections {
+            for subcollection in &collection.subcollections {
+                let child_table_name = format!("{}_{}", collection.name, subcollection);
+                let parent_column = format!("{}_id", collection.name);
+                
+                let Some(child_table) = schema.tables.iter_mut().find(|t| t.name == child_table_name) else {
+                    continue;
+                };
+                if child_table.foreign_keys.iter().any(|fk| fk.column == parent_column) {
+                    continue;
+                }
+                
+                if !child_table.columns.iter().any(|c| c.name == parent_column) {
+                    child_table.add_column(ColumnDefinition::new(parent_column.clone(), PostgreSQLType::Uuid).not_null());
+                }
+                child_table.add_foreign_key(ForeignKeyDefinition {
+                    column: parent_column.clone(),
+                    referenced_table: collection.name.clone(),
+                    referenced_column: "id".to_string(),
+                    constraint_name: format!("fk_{}_{}", child_table_name, collection.name),
+                });
+                
+                schema.relationships.push(Relationship {
+                    from_table: child_table_name,
+                    to_table: collection.name.clone(),
+                    from_column: parent_column,
+                    to_column: "id".to_string(),
+                    relationship_type: RelationshipType::ManyToOne,
+                });
+            }
+        }
+        
+        Ok(())
+    }
+
     /// Check if an array field should be normalized into a separate table
     fn should_normalize_array(&self, field_type: &crate::types::FieldTypeAnalysis, opportunities: &[NormalizationOpportunity]) -> bool {
         // Check if there's a 1NF opportunity for this field
@@ -171,17 +261,10 @@ impl NormalizationEngine {
     fn apply_second_normal_form(&self, analysis: &SchemaAnalysis, schema: &mut NormalizedSchema) -> FireupResult<()> {
         debug!("Applying Second Normal Form (2NF)");
         
-        // Look for composite key dependencies
-        for opportunity in &analysis.normalization_opportunities {
-            if matches!(opportunity.normalization_type, NormalizationType::SecondNormalForm) {
-                // For now, add a warning that manual review is needed
-                schema.warnings.push(SchemaWarning {
-                    level: WarningLevel::Warning,
-                    message: format!("Potential 2NF violation in collection '{}': {}", 
-                                   opportunity.collection, opportunity.description),
-                    context: opportunity.field_path.clone(),
-                    suggestion: Some("Review for partial dependencies and consider extracting to separate table".to_string()),
-                });
+        // Fields of subcollection documents that depend on the parent document alone
+        for dependency in &analysis.functional_dependencies {
+            if dependency.normalization_type == NormalizationType::SecondNormalForm {
+                self.extract_dependency(dependency, schema);
             }
         }
         
@@ -192,92 +275,170 @@ impl NormalizationEngine {
     fn apply_third_normal_form(&self, analysis: &SchemaAnalysis, schema: &mut NormalizedSchema) -> FireupResult<()> {
         debug!("Applying Third Normal Form (3NF)");
         
-        // Extract transitive dependencies based on detected relationships
+        // Single-valued references become foreign keys on their own column;
+        // many-to-many relationships already have their junction table
         for relationship in &analysis.relationships {
-            if relationship.confidence > 0.8 {
-                // Create lookup table for high-confidence relationships
-                self.create_lookup_table(relationship, schema)?;
+            if relationship.confidence > 0.8 && !matches!(relationship.relationship_type, RelationshipType::ManyToMany) {
+                self.add_reference_foreign_key(relationship, schema);
             }
         }
         
-        // Look for transitive dependency opportunities
-        for opportunity in &analysis.normalization_opportunities {
-            if matches!(opportunity.normalization_type, NormalizationType::ThirdNormalForm) {
-                schema.warnings.push(SchemaWarning {
-                    level: WarningLevel::Info,
-                    message: format!("Potential 3NF optimization in collection '{}': {}", 
-                                   opportunity.collection, opportunity.description),
-                    context: opportunity.field_path.clone(),
-                    suggestion: Some("Consider extracting transitive dependencies to reference tables".to_string()),
-                });
+        // Fields that depend on another non-key field
+        for dependency in &analysis.functional_dependencies {
+            if dependency.normalization_type == NormalizationType::ThirdNormalForm {
+                self.extract_dependency(dependency, schema);
             }
         }
         
         Ok(())
     }
 
-    /// Create a lookup table for a detected relationship
-    fn create_lookup_table(&self, relationship: &crate::types::DetectedRelationship, schema: &mut NormalizedSchema) -> FireupResult<()> {
-        let lookup_table_name = format!("{}_{}_lookup", relationship.from_collection, relationship.to_collection);
+    /// Move the dependents of a functional dependency out of the collection's
+    /// table into a lookup table keyed by the determinant
+    /// (`{collection}_{determinant}_lookup`), which the collection's table
+    /// references through its determinant column
+    fn extract_dependency(&self, dependency: &FunctionalDependency, schema: &mut NormalizedSchema) {
+        let lookup_table_name = format!("{}_{}_lookup", dependency.collection, dependency.determinant);
+        let context = format!("{}.{}", dependency.collection, dependency.determinant);
         
-        // Check if table already exists
+        if dependency.redundancy() < self.separation_threshold {
+            schema.warnings.push(SchemaWarning {
+                level: WarningLevel::Info,
+                message: format!(
+                    "{} determines {} in collection '{}', with too few repeated values to extract",
+                    dependency.determinant, dependency.dependents.join(", "), dependency.collection
+                ),
+                context,
+                suggestion: Some("Consider extracting these fields to a reference table as the data grows".to_string()),
+            });
+            return;
+        }
         if schema.tables.iter().any(|t| t.name == lookup_table_name) {
-            return Ok(());
+            return;
+        }
+        let Some(table) = schema.tables.iter_mut().find(|t| t.name == dependency.collection) else {
+            return;
+        };
+        let Some(key_column) = table.columns.iter().find(|c| c.name == dependency.determinant).cloned() else {
+            return;
+        };
+        let moved: Vec<ColumnDefinition> = table.columns.iter()
+            .filter(|c| dependency.dependents.contains(&c.name))
+            .cloned()
+            .collect();
+        if moved.is_empty() {
+            return;
         }
         
-        let mut lookup_table = TableDefinition::new(lookup_table_name.clone());
-        
-        // Add columns
-        lookup_table.add_column(ColumnDefinition::new("id".to_string(), PostgreSQLType::Uuid).not_null());
-        lookup_table.add_column(ColumnDefinition::new(
-            format!("{}_id", relationship.from_collection), 
-            PostgreSQLType::Uuid
-        ).not_null());
-        lookup_table.add_column(ColumnDefinition::new(
-            format!("{}_id", relationship.to_collection), 
-            PostgreSQLType::Uuid
-        ).not_null());
+        // Foreign keys of moved columns move with them
+        let moved_foreign_keys: Vec<ForeignKeyDefinition> = table.foreign_keys.iter()
+            .filter(|fk| dependency.dependents.contains(&fk.column))
+            .cloned()
+            .collect();
+        table.columns.retain(|c| !dependency.dependents.contains(&c.name));
+        table.foreign_keys.retain(|fk| !dependency.dependents.contains(&fk.column));
+        table.add_foreign_key(ForeignKeyDefinition {
+            column: key_column.name.clone(),
+            referenced_table: lookup_table_name.clone(),
+            referenced_column: key_column.name.clone(),
+            constraint_name: format!("fk_{}_{}", dependency.collection, lookup_table_name),
+        });
         
+        let mut lookup_table = TableDefinition::new(lookup_table_name.clone());
+        lookup_table.add_column(ColumnDefinition::new(key_column.name.clone(), key_column.column_type.clone()).not_null());
+        for column in moved {
+            lookup_table.add_column(ColumnDefinition::new(column.name, column.column_type));
+        }
         lookup_table.set_primary_key(PrimaryKeyDefinition {
             name: format!("{}_pkey", lookup_table_name),
-            columns: vec!["id".to_string()],
+            columns: vec![key_column.name.clone()],
         });
+        for fk in moved_foreign_keys {
+            lookup_table.add_foreign_key(ForeignKeyDefinition {
+                constraint_name: format!("fk_{}_{}", lookup_table_name, fk.referenced_table),
+                ..fk
+            });
+        }
+        schema.tables.push(lookup_table);
         
-        // Add foreign keys
-        lookup_table.add_foreign_key(ForeignKeyDefinition {
-            column: format!("{}_id", relationship.from_collection),
-            referenced_table: relationship.from_collection.clone(),
-            referenced_column: "id".to_string(),
-            constraint_name: format!("fk_{}_{}", lookup_table_name, relationship.from_collection),
+        for relationship in &mut schema.relationships {
+            if relationship.from_table == dependency.collection && dependency.dependents.contains(&relationship.from_column) {
+                relationship.from_table = lookup_table_name.clone();
+            }
+        }
+        schema.relationships.push(Relationship {
+            from_table: dependency.collection.clone(),
+            to_table: lookup_table_name.clone(),
+            from_column: key_column.name.clone(),
+            to_column: key_column.name,
+            relationship_type: RelationshipType::ManyToOne,
         });
-        
-        lookup_table.add_foreign_key(ForeignKeyDefinition {
-            column: format!("{}_id", relationship.to_collection),
-            referenced_table: relationship.to_collection.clone(),
-            referenced_column: "id".to_string(),
-            constraint_name: format!("fk_{}_{}", lookup_table_name, relationship.to_collection),
+        schema.warnings.push(SchemaWarning {
+            level: WarningLevel::Info,
+            message: format!(
+                "Extracted {} of collection '{}' into {}, keyed by {}",
+                dependency.dependents.join(", "), dependency.collection, lookup_table_name, dependency.determinant
+            ),
+            context,
+            suggestion: None,
         });
+    }
+
+    /// Type the column holding a single-valued reference as UUID and make it
+    /// a foreign key to the referenced collection's table, so the importer
+    /// resolves its paths and bare IDs to the referenced rows' keys.
+    ///
+    /// References within a collection, and references closing a cycle
+    /// between tables, are left without a foreign key: their rows could not
+    /// be loaded one table after the other.
+    fn add_reference_foreign_key(&self, relationship: &DetectedRelationship, schema: &mut NormalizedSchema) {
+        let column_name = relationship.reference_field.replace('.', "_");
+        let context = format!("{}.{}", relationship.from_collection, relationship.reference_field);
         
-        schema.tables.push(lookup_table);
+        if !schema.tables.iter().any(|t| t.name == relationship.to_collection) {
+            return;
+        }
+        if relationship.from_collection == relationship.to_collection
+            || references_table(schema, &relationship.to_collection, &relationship.from_collection)
+        {
+            let reason = if relationship.from_collection == relationship.to_collection {
+                format!("references documents of {}", relationship.to_collection)
+            } else {
+                format!("references {}, which references {} in turn", relationship.to_collection, relationship.from_collection)
+            };
+            schema.warnings.push(SchemaWarning {
+                level: WarningLevel::Warning,
+                message: format!("{} {}; kept without a foreign key", context, reason),
+                context,
+                suggestion: Some("Add the foreign key once the data is loaded".to_string()),
+            });
+            return;
+        }
         
-        // Add relationships
-        schema.relationships.push(Relationship {
-            from_table: lookup_table_name.clone(),
-            to_table: relationship.from_collection.clone(),
-            from_column: format!("{}_id", relationship.from_collection),
-            to_column: "id".to_string(),
-            relationship_type: RelationshipType::ManyToOne,
+        let Some(table) = schema.tables.iter_mut().find(|t| t.name == relationship.from_collection) else {
+            return;
+        };
+        if table.foreign_keys.iter().any(|fk| fk.column == column_name) {
+            return;
+        }
+        let Some(column) = table.columns.iter_mut().find(|c| c.name == column_name) else {
+            return;
+        };
+        column.column_type = PostgreSQLType::Uuid;
+        table.add_foreign_key(ForeignKeyDefinition {
+            column: column_name.clone(),
+            referenced_table: relationship.to_collection.clone(),
+            referenced_column: "id".to_string(),
+            constraint_name: format!("fk_{}_{}", relationship.from_collection, column_name),
         });
         
         schema.relationships.push(Relationship {
-            from_table: lookup_table_name,
+            from_table: relationship.from_collection.clone(),
             to_table: relationship.to_collection.clone(),
-            from_column: format!("{}_id", relationship.to_collection),
+            from_column: column_name,
             to_column: "id".to_string(),
-            relationship_type: RelationshipType::ManyToOne,
+            relationship_type: relationship.relationship_type.clone(),
         });
-        
-        Ok(())
     }
 
     /// Generate recommended indexes for performance
@@ -285,14 +446,20 @@ impl NormalizationEngine {
         debug!("Generating recommended indexes");
         
         for table in &mut schema.tables {
-            // Add indexes for foreign key columns
+            // Add indexes for foreign key columns; a one-to-one reference
+            // points at each row at most once
             let foreign_keys = table.foreign_keys.clone(); // Clone to avoid borrow checker issues
             for fk in &foreign_keys {
                 let index_name = format!("idx_{}_{}", table.name, fk.column);
+                let one_to_one = schema.relationships.iter().any(|relationship| {
+                    relationship.from_table == table.name
+                        && relationship.from_column == fk.column
+                        && matches!(relationship.relationship_type, RelationshipType::OneToOne)
+                });
                 table.add_index(IndexDefinition {
                     name: index_name,
                     columns: vec![fk.column.clone()],
-                    unique: false,
+                    unique: one_to_one,
                     index_type: Some("btree".to_string()),
                 });
             }
@@ -313,6 +480,42 @@ impl NormalizationEngine {
     }
 }
 
+/// The many-to-many relationship detected for an array field of a
+/// collection, when the referenced collection has a table of its own
+fn many_to_many_relationship<'a>(
+    analysis: &'a SchemaAnalysis,
+    collection: &str,
+    field_name: &str,
+) -> Option<&'a DetectedRelationship> {
+    analysis.relationships.iter().find(|relationship| {
+        relationship.from_collection == collection
+            && relationship.reference_field == field_name
+            && matches!(relationship.relationship_type, RelationshipType::ManyToMany)
+            && analysis.collections.iter().any(|c| c.name == relationship.to_collection)
+    })
+}
+
+/// Whether `from` references `target` through one or more foreign keys
+fn references_table(schema: &NormalizedSchema, from: &str, target: &str) -> bool {
+    let mut pending = vec![from];
+    let mut visited = std::collections::HashSet::new();
+    while let Some(table_name) = pending.pop() {
+        if !visited.insert(table_name) {
+            continue;
+        }
+        let Some(table) = schema.tables.iter().find(|t| t.name == table_name) else {
+            continue;
+        };
+        for fk in &table.foreign_keys {
+            if fk.referenced_table == target {
+                return true;
+            }
+            pending.push(&fk.referenced_table);
+        }
+    }
+    false
+}
+
 impl Default for NormalizationEngine {
     fn default() -> Self {
         Self::new()
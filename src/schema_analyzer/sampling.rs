@@ -0,0 +1,414 @@
+// Document sampling for schema analysis of large exports
+use crate::error::FireupResult;
+use crate::leveldb_parser::parser::{document_key, duplicate_document_error, DocumentKey, DuplicatePolicy};
+use crate::types::{CollectionSampling, FirestoreDocument, FirestoreValue, SamplingSummary, NEAR_LIMIT_DOCUMENT_SIZE};
+use chrono::{DateTime, Utc};
+use std::collections::{BinaryHeap, HashMap};
+
+/// How documents are sampled for schema analysis.
+///
+/// Every document gets a random key derived from the seed, its collection
+/// and its ID. Each collection keeps the documents whose key falls below
+/// `sample_rate` (a uniform sample), and at least `min_sample_size` of them
+/// (those with the smallest keys), so that small collections are analyzed
+/// in full. On top of that, each field keeps the `rare_field_sample_size`
+/// documents with the smallest keys among those holding it, so that fields
+/// too rare to show up in the uniform sample are still typed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SamplingConfig {
+    sample_rate: f64,
+    min_sample_size: usize,
+    rare_field_sample_size: usize,
+    seed: u64,
+}
+
+impl SamplingConfig {
+    /// Sample 1% of each collection, at least 1000 documents, and 20
+    /// documents for each field
+    pub fn new() -> Self {
+        Self {
+            sample_rate: 0.01,
+            min_sample_size: 1000,
+            rare_field_sample_size: 20,
+            seed: 0,
+        }
+    }
+
+    /// Share of each collection's documents to sample, between 0 and 1
+    pub fn with_sample_rate(mut self, sample_rate: f64) -> Self {
+        self.sample_rate = sample_rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Documents sampled from each collection however small the rate
+    pub fn with_min_sample_size(mut self, min_sample_size: usize) -> Self {
+        self.min_sample_size = min_sample_size;
+        self
+    }
+
+    /// Documents kept for each field; 0 disables the rare-field sample
+    pub fn with_rare_field_sample_size(mut self, rare_field_sample_size: usize) -> Self {
+        self.rare_field_sample_size = rare_field_sample_size;
+        self
+    }
+
+    /// Seed of the document keys; the same seed draws the same documents
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    pub fn min_sample_size(&self) -> usize {
+        self.min_sample_size
+    }
+
+    pub fn rare_field_sample_size(&self) -> usize {
+        self.rare_field_sample_size
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Largest key sampled by the rate alone
+    fn rate_threshold(&self) -> u64 {
+        if self.sample_rate >= 1.0 {
+            u64::MAX
+        } else {
+            (self.sample_rate * u64::MAX as f64) as u64
+        }
+    }
+
+    /// Random key of a document: the same for a collection and ID whatever
+    /// else the document holds, so that a value holding the ID of a document
+    /// can be checked against the sample without the document itself
+    fn document_key(&self, collection: &str, id: &str) -> u64 {
+        // FNV-1a, then a splitmix64 finalizer to spread the bits
+        let mut hash = 0xcbf2_9ce4_8422_2325u64 ^ self.seed;
+        for byte in collection.bytes().chain(std::iter::once(b'/')).chain(id.bytes()) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        hash = hash.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        hash ^ (hash >> 31)
+    }
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Exact figures of a collection, counted over every document
+#[derive(Debug, Clone, Default)]
+pub struct CollectionCounts {
+    pub document_count: u64,
+    /// Documents holding each field, by field path (`collection.field`)
+    pub field_counts: HashMap<String, u64>,
+    pub total_bytes: u64,
+    pub min_bytes: u64,
+    pub max_bytes: u64,
+    pub near_limit_count: u64,
+}
+
+/// Keys and sequence numbers of sampled documents, largest key on top
+type Reservoir = BinaryHeap<(u64, u64)>;
+
+#[derive(Default)]
+struct CollectionReservoir {
+    counts: CollectionCounts,
+    uniform: Reservoir,
+    fields: HashMap<String, Reservoir>,
+}
+
+/// Draws a [`DocumentSample`] from documents fed one at a time, holding only
+/// the sampled documents (and the paths of the documents seen, unless every
+/// copy of a document is kept) in memory
+pub struct DocumentSampler {
+    config: SamplingConfig,
+    collections: HashMap<String, CollectionReservoir>,
+    /// Sampled documents by sequence number, with the number of reservoirs
+    /// holding them
+    documents: HashMap<u64, (FirestoreDocument, usize)>,
+    next_sequence: u64,
+    duplicate_policy: DuplicatePolicy,
+    /// Sequence number and update time of the copy counted for each
+    /// document seen
+    seen: HashMap<DocumentKey, (u64, Option<DateTime<Utc>>)>,
+    duplicate_count: u64,
+}
+
+impl DocumentSampler {
+    pub fn new(config: SamplingConfig) -> Self {
+        Self {
+            config,
+            collections: HashMap::new(),
+            documents: HashMap::new(),
+            next_sequence: 0,
+            duplicate_policy: DuplicatePolicy::default(),
+            seen: HashMap::new(),
+            duplicate_count: 0,
+        }
+    }
+
+    /// Set which copy of a repeated document path is sampled, as the parser
+    /// does for a full parse. A repeated copy is never counted again; with
+    /// [`DuplicatePolicy::LatestUpdate`] it takes the place of the sampled
+    /// copy when it is newer.
+    pub fn with_duplicate_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
+    /// Count a document, and its subcollection documents, and keep it if
+    /// it is drawn. Fails on a repeated document path under
+    /// [`DuplicatePolicy::Error`].
+    pub fn add(&mut self, mut document: FirestoreDocument) -> FireupResult<()> {
+        for subdocument in std::mem::take(&mut document.subcollections) {
+            self.add(subdocument)?;
+        }
+
+        if self.duplicate_policy != DuplicatePolicy::KeepAll {
+            let key = document_key(&document);
+            if let Some((sequence, updated_at)) = self.seen.get_mut(&key) {
+                self.duplicate_count += 1;
+                match self.duplicate_policy {
+                    DuplicatePolicy::LatestUpdate => {
+                        if document.metadata.updated_at >= *updated_at {
+                            *updated_at = document.metadata.updated_at;
+                            if let Some((held, _)) = self.documents.get_mut(sequence) {
+                                *held = document;
+                            }
+                        }
+                    }
+                    DuplicatePolicy::FirstWins | DuplicatePolicy::KeepAll => {}
+                    DuplicatePolicy::Error => return Err(duplicate_document_error(key.3)),
+                }
+                return Ok(());
+            }
+            self.seen.insert(key, (self.next_sequence, document.metadata.updated_at));
+        }
+
+        let collection = document.qualified_collection();
+        let key = self.config.document_key(&collection, &document.id);
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+
+        let mut field_paths = Vec::new();
+        collect_field_paths(&document.data, &collection, &mut field_paths);
+        let size = document.metadata.size_bytes.unwrap_or_else(|| document.storage_size());
+
+        let rate_threshold = self.config.rate_threshold();
+        let reservoir = self.collections.entry(collection).or_default();
+        let counts = &mut reservoir.counts;
+        counts.min_bytes = if counts.document_count == 0 { size } else { counts.min_bytes.min(size) };
+        counts.max_bytes = counts.max_bytes.max(size);
+        counts.total_bytes += size;
+        if size >= NEAR_LIMIT_DOCUMENT_SIZE {
+            counts.near_limit_count += 1;
+        }
+        counts.document_count += 1;
+
+        let mut evicted = Vec::new();
+        let mut holders = 0;
+        if offer_uniform(&mut reservoir.uniform, key, sequence, rate_threshold, self.config.min_sample_size, &mut evicted) {
+            holders += 1;
+        }
+        for field_path in field_paths {
+            *reservoir.counts.field_counts.entry(field_path.clone()).or_default() += 1;
+            let field_reservoir = reservoir.fields.entry(field_path).or_default();
+            if offer_bounded(field_reservoir, key, sequence, self.config.rare_field_sample_size, &mut evicted) {
+                holders += 1;
+            }
+        }
+
+        if holders > 0 {
+            self.documents.insert(sequence, (document, holders));
+        }
+        for sequence in evicted {
+            if let Some((_, holders)) = self.documents.get_mut(&sequence) {
+                *holders -= 1;
+                if *holders == 0 {
+                    self.documents.remove(&sequence);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Documents counted so far
+    pub fn documents_seen(&self) -> u64 {
+        self.next_sequence
+    }
+
+    /// Repeated copies of documents passed over so far
+    pub fn duplicate_count(&self) -> u64 {
+        self.duplicate_count
+    }
+
+    /// Documents held in memory
+    pub fn documents_sampled(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// The sample, in the order its documents were added
+    pub fn finish(self) -> DocumentSample {
+        let mut thresholds = HashMap::new();
+        let mut collections = HashMap::new();
+        for (name, reservoir) in self.collections {
+            // Every document with a key up to the largest uniform key is in
+            // the uniform sample
+            let threshold = if reservoir.uniform.len() as u64 >= reservoir.counts.document_count {
+                u64::MAX
+            } else {
+                reservoir.uniform.peek().map_or(0, |&(key, _)| key)
+            };
+            thresholds.insert(name.clone(), (threshold, reservoir.uniform.len() as u64));
+            collections.insert(name, reservoir.counts);
+        }
+
+        let mut documents: Vec<(u64, FirestoreDocument)> = self.documents.into_iter()
+            .map(|(sequence, (document, _))| (sequence, document))
+            .collect();
+        documents.sort_by_key(|(sequence, _)| *sequence);
+
+        DocumentSample {
+            config: self.config,
+            documents: documents.into_iter().map(|(_, document)| document).collect(),
+            collections,
+            thresholds,
+            total_documents: self.next_sequence,
+        }
+    }
+}
+
+/// Keep a document if its key is below the rate, or among the
+/// `min_size` smallest; evict documents no longer needed for either
+fn offer_uniform(
+    reservoir: &mut Reservoir,
+    key: u64,
+    sequence: u64,
+    rate_threshold: u64,
+    min_size: usize,
+    evicted: &mut Vec<u64>,
+) -> bool {
+    let smallest = reservoir.len() < min_size || reservoir.peek().is_some_and(|&(top, _)| key < top);
+    if key >= rate_threshold && !smallest {
+        return false;
+    }
+    reservoir.push((key, sequence));
+    while reservoir.len() > min_size && reservoir.peek().is_some_and(|&(top, _)| top >= rate_threshold) {
+        if let Some((_, sequence)) = reservoir.pop() {
+            evicted.push(sequence);
+        }
+    }
+    true
+}
+
+/// Keep a document if its key is among the `size` smallest
+fn offer_bounded(reservoir: &mut Reservoir, key: u64, sequence: u64, size: usize, evicted: &mut Vec<u64>) -> bool {
+    if size == 0 || (reservoir.len() >= size && reservoir.peek().is_some_and(|&(top, _)| key >= top)) {
+        return false;
+    }
+    reservoir.push((key, sequence));
+    if reservoir.len() > size {
+        if let Some((_, sequence)) = reservoir.pop() {
+            evicted.push(sequence);
+        }
+    }
+    true
+}
+
+/// Field paths as named by the analyzer: `collection.field`, with nested
+/// map fields as `collection.field.nested`
+fn collect_field_paths(fields: &HashMap<String, FirestoreValue>, prefix: &str, out: &mut Vec<String>) {
+    for (name, value) in fields {
+        let path = format!("{}.{}", prefix, name);
+        if let FirestoreValue::Map(nested) = value {
+            collect_field_paths(nested, &path, out);
+        }
+        out.push(path);
+    }
+}
+
+/// Documents drawn by a [`DocumentSampler`], with exact counts of the
+/// documents they were drawn from
+pub struct DocumentSample {
+    config: SamplingConfig,
+    documents: Vec<FirestoreDocument>,
+    collections: HashMap<String, CollectionCounts>,
+    /// Largest key of each collection's uniform sample, and its size
+    thresholds: HashMap<String, (u64, u64)>,
+    total_documents: u64,
+}
+
+impl DocumentSample {
+    /// Sampled documents, uniform and rare-field samples together
+    pub fn documents(&self) -> &[FirestoreDocument] {
+        &self.documents
+    }
+
+    pub fn config(&self) -> &SamplingConfig {
+        &self.config
+    }
+
+    /// Documents the sample was drawn from
+    pub fn total_documents(&self) -> u64 {
+        self.total_documents
+    }
+
+    /// Exact figures of a collection
+    pub fn collection_counts(&self, collection: &str) -> Option<&CollectionCounts> {
+        self.collections.get(collection)
+    }
+
+    /// Whether the document with this ID would be in the collection's
+    /// uniform sample, had the collection held it
+    pub fn in_uniform_sample(&self, collection: &str, id: &str) -> bool {
+        self.thresholds.get(collection)
+            .is_some_and(|&(threshold, _)| self.config.document_key(collection, id) <= threshold)
+    }
+
+    /// Whether every document was sampled
+    pub fn is_complete(&self) -> bool {
+        self.documents.len() as u64 >= self.total_documents
+    }
+
+    /// Summary for the schema analysis
+    pub fn summary(&self) -> SamplingSummary {
+        let mut held: HashMap<String, u64> = HashMap::new();
+        for document in &self.documents {
+            *held.entry(document.qualified_collection()).or_default() += 1;
+        }
+        let mut collections: Vec<CollectionSampling> = self.collections.iter()
+            .map(|(name, counts)| {
+                let sampled_documents = self.thresholds.get(name).map_or(0, |&(_, size)| size);
+                let held = held.get(name).copied().unwrap_or(0);
+                CollectionSampling {
+                    collection: name.clone(),
+                    document_count: counts.document_count,
+                    sampled_documents,
+                    rare_field_documents: held.saturating_sub(sampled_documents),
+                }
+            })
+            .collect();
+        collections.sort_by(|a, b| a.collection.cmp(&b.collection));
+
+        SamplingSummary {
+            sample_rate: self.config.sample_rate,
+            min_sample_size: self.config.min_sample_size,
+            rare_field_sample_size: self.config.rare_field_sample_size,
+            seed: self.config.seed,
+            total_documents: self.total_documents,
+            sampled_documents: self.documents.len() as u64,
+            collections,
+        }
+    }
+}
@@ -8,7 +8,7 @@ use std::collections::HashMap;
 /// Helper function to create a test Firestore document
 fn create_test_document(id: &str, collection: &str, data: HashMap<String, Value>) -> FirestoreDocument {
     let mut doc = FirestoreDocument::new(id.to_string(), collection.to_string(), format!("{}/{}", collection, id));
-    doc.data = data;
+    doc.data = data.into_iter().map(|(key, value)| (key, value.into())).collect();
     doc.metadata.size_bytes = Some(1024);
     doc
 }
@@ -168,20 +168,21 @@ mod analyzer_tests {
         let analyzer = DocumentStructureAnalyzer::new();
         
         // Create documents with reference patterns
-        let documents = vec![
+        let mut documents = vec![
             create_test_document("order1", "orders", {
                 let mut data = HashMap::new();
-                data.insert("user_ref".to_string(), json!("users/user1"));
                 data.insert("total".to_string(), json!(100.0));
                 data
             }),
             create_test_document("order2", "orders", {
                 let mut data = HashMap::new();
-                data.insert("user_ref".to_string(), json!("users/user2"));
                 data.insert("total".to_string(), json!(200.0));
                 data
             }),
         ];
+        for (doc, user) in documents.iter_mut().zip(["user1", "user2"]) {
+            doc.add_field("user_ref".to_string(), FirestoreValue::Reference(format!("users/{}", user)));
+        }
         
         let analysis = analyzer.analyze_documents(&documents).await.unwrap();
         
@@ -196,6 +197,232 @@ mod analyzer_tests {
         assert!(relationship.confidence >= 0.7);
     }
 
+    #[tokio::test]
+    async fn test_relationship_cardinality() {
+        initialize_monitoring(MonitoringConfig::default());
+        let analyzer = DocumentStructureAnalyzer::new();
+        let reference = |path: &str| FirestoreValue::Reference(path.to_string());
+
+        let mut documents = Vec::new();
+        for (id, user) in [("p1", "u1"), ("p2", "u2"), ("p3", "u3")] {
+            let mut profile = create_test_document(id, "profiles", HashMap::new());
+            profile.add_field("user".to_string(), reference(&format!("users/{}", user)));
+            documents.push(profile);
+        }
+        for (id, user) in [("o1", "u1"), ("o2", "u1"), ("o3", "u2")] {
+            let mut order = create_test_document(id, "orders", HashMap::new());
+            order.add_field("customer".to_string(), reference(&format!("users/{}", user)));
+            documents.push(order);
+        }
+        // Each post holds a single tag, but as an array of references
+        for (id, tag) in [("a", "t1"), ("b", "t2")] {
+            let mut post = create_test_document(id, "posts", HashMap::new());
+            post.add_field("tags".to_string(), FirestoreValue::Array(vec![reference(&format!("tags/{}", tag))]));
+            documents.push(post);
+        }
+
+        let analysis = analyzer.analyze_documents(&documents).await.unwrap();
+        let relationship_type = |from: &str, field: &str| {
+            analysis.relationships.iter()
+                .find(|r| r.from_collection == from && r.reference_field == field)
+                .map(|r| r.relationship_type.clone())
+        };
+
+        assert!(matches!(relationship_type("profiles", "user"), Some(RelationshipType::OneToOne)));
+        assert!(matches!(relationship_type("orders", "customer"), Some(RelationshipType::ManyToOne)));
+        assert!(matches!(relationship_type("posts", "tags"), Some(RelationshipType::ManyToMany)));
+        let tags = analysis.relationships.iter().find(|r| r.from_collection == "posts").unwrap();
+        assert_eq!(tags.to_collection, "tags");
+        assert_eq!(tags.confidence, 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_bare_id_foreign_keys() {
+        initialize_monitoring(MonitoringConfig::default());
+        let mut documents: Vec<FirestoreDocument> = (1..=20)
+            .map(|n| create_test_document(&format!("user{:02}", n), "users", HashMap::from([
+                ("name".to_string(), json!(format!("User {}", n))),
+            ])))
+            .collect();
+        // 19 distinct customers, one unknown coupon among three, and a
+        // reviewer field with too few values to be sure about
+        for n in 1..=24 {
+            let mut data = HashMap::from([
+                ("userId".to_string(), json!(format!("user{:02}", n.min(19)))),
+                ("status".to_string(), json!("shipped")),
+                ("couponCode".to_string(), json!(["user01", "SPRING", "FALL"][n % 3])),
+            ]);
+            if n <= 3 {
+                data.insert("reviewerId".to_string(), json!(format!("user{:02}", n)));
+            }
+            documents.push(create_test_document(&format!("order{}", n), "orders", data));
+        }
+
+        let analysis = DocumentStructureAnalyzer::new().analyze_documents(&documents).await.unwrap();
+        let relationship = |field: &str| analysis.relationships.iter()
+            .find(|r| r.from_collection == "orders" && r.reference_field == field);
+
+        let customer = relationship("userId").expect("userId should reference users");
+        assert_eq!(customer.to_collection, "users");
+        assert!(matches!(customer.relationship_type, RelationshipType::ManyToOne));
+        assert_eq!(customer.confidence, 0.95);
+        assert_eq!(customer.evidence, RelationshipEvidence::InclusionDependency {
+            distinct_values: 19,
+            matching_values: 19,
+        });
+        let reviewer = relationship("reviewerId").expect("reviewerId should reference users");
+        assert_eq!(reviewer.confidence, 0.75);
+        assert!(relationship("status").is_none());
+        assert!(relationship("couponCode").is_none());
+
+        // userId becomes a UUID foreign key on the orders table itself
+        let schema = NormalizationEngine::new().normalize_schema(&analysis).unwrap();
+        assert!(!schema.tables.iter().any(|t| t.name.ends_with("_lookup")));
+        let orders = schema.tables.iter().find(|t| t.name == "orders").expect("orders table");
+        let user_id = orders.columns.iter().find(|c| c.name == "userId").expect("userId column");
+        assert!(matches!(user_id.column_type, PostgreSQLType::Uuid));
+        let foreign_keys: Vec<(&str, &str)> = orders.foreign_keys.iter()
+            .map(|fk| (fk.column.as_str(), fk.referenced_table.as_str()))
+            .collect();
+        assert_eq!(foreign_keys, vec![("userId", "users")]);
+
+        // The constraint is part of the table, and reviewerId is not sure
+        // enough to suggest one for
+        let result = ConstraintGenerator::new().generate_constraints(&schema, &analysis).unwrap();
+        assert!(!result.constraints.iter().any(|c| matches!(c.constraint_type, ConstraintType::ForeignKey)));
+        assert!(!result.recommendations.iter().any(|r| matches!(r.constraint_type, ConstraintType::ForeignKey)));
+
+        // Bare IDs resolve to the keys of the users rows
+        use crate::data_importer::DocumentTransformer;
+        let transformed = DocumentTransformer::new().transform_documents(&documents, &schema).unwrap();
+        let user_keys: HashMap<String, &Value> = transformed.table_data["users"].iter()
+            .map(|row| (row.columns["name"].as_str().unwrap().to_string(), &row.primary_key))
+            .collect();
+        let order_rows = &transformed.table_data["orders"];
+        assert_eq!(order_rows.len(), 24);
+        for row in order_rows {
+            let key = &row.columns["userId"];
+            assert_eq!(row.foreign_keys["userId"], *key);
+            assert!(user_keys.values().any(|user_key| *user_key == key), "dangling userId {}", key);
+        }
+        // order1 belongs to user01
+        assert_eq!(order_rows[0].columns["userId"], *user_keys["User 1"]);
+    }
+
+    #[tokio::test]
+    async fn test_sampled_analysis_reports_confidence() {
+        initialize_monitoring(MonitoringConfig::default());
+        let documents = || (0..2000).flat_map(|n| {
+            let mut user = HashMap::from([("email".to_string(), json!(format!("user{}@example.com", n)))]);
+            // Too rare for the uniform sample
+            if n % 700 == 0 {
+                user.insert("legacyCode".to_string(), json!(n));
+            }
+            let order = HashMap::from([("userId".to_string(), json!(format!("user{}", (n * 7) % 2000)))]);
+            [
+                create_test_document(&format!("user{}", n), "users", user),
+                create_test_document(&format!("order{}", n), "orders", order),
+            ]
+        });
+        let config = SamplingConfig::new()
+            .with_sample_rate(0.1)
+            .with_min_sample_size(50)
+            .with_rare_field_sample_size(5)
+            .with_seed(7);
+        let draw = || {
+            let mut sampler = DocumentSampler::new(config.clone());
+            documents().for_each(|document| sampler.add(document).unwrap());
+            sampler.finish()
+        };
+        let sample = draw();
+        let ids = |sample: &DocumentSample| sample.documents().iter().map(|doc| doc.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&sample), ids(&draw()));
+        assert_eq!(sample.total_documents(), 4000);
+        assert!(!sample.is_complete());
+
+        let analysis = DocumentStructureAnalyzer::new().analyze_sample(&sample).await.unwrap();
+        assert_eq!(analysis.metadata.total_documents, 4000);
+        let users = analysis.collections.iter().find(|c| c.name == "users").unwrap();
+        assert_eq!(users.document_count, 2000);
+        assert_eq!(users.size_distribution.total_bytes, 2000 * 1024);
+
+        let summary = analysis.sampling.as_ref().unwrap();
+        let sampled_users = summary.collections.iter().find(|c| c.collection == "users").unwrap();
+        assert!(sampled_users.sampled_documents > 100 && sampled_users.sampled_documents < 300);
+        assert!(summary.sampled_fraction() < 0.2);
+
+        let field = |path: &str| analysis.field_types.iter().find(|f| f.field_path == path).unwrap();
+        // Every rare value is typed, and its presence is exact
+        let legacy = field("users.legacyCode");
+        let legacy_confidence = legacy.confidence.as_ref().unwrap();
+        assert!(legacy_confidence.is_exact());
+        assert_eq!(legacy_confidence.total_occurrences, 3);
+        assert_eq!(legacy.presence_percentage, 0.15);
+        let email = field("users.email");
+        let email_confidence = email.confidence.as_ref().unwrap();
+        assert!(!email_confidence.is_exact());
+        assert_eq!(email.presence_percentage, 100.0);
+        assert_eq!(email_confidence.dominant_type_share, 1.0);
+        assert!(email_confidence.max_unseen_type_share > 0.0);
+
+        // IDs are compared with the IDs the users sample would hold
+        let customer = analysis.relationships.iter()
+            .find(|r| r.from_collection == "orders" && r.reference_field == "userId")
+            .expect("userId should reference users");
+        assert_eq!(customer.to_collection, "users");
+        match &customer.evidence {
+            RelationshipEvidence::InclusionDependency { distinct_values, matching_values } => {
+                assert_eq!(distinct_values, matching_values);
+            }
+            evidence => panic!("unexpected evidence {:?}", evidence),
+        }
+
+        let estimate = FieldConfidence::estimate(1000, 100, 90);
+        assert!((estimate.margin_of_error - 0.0558).abs() < 0.001);
+        assert_eq!(estimate.max_unseen_type_share, 0.03);
+    }
+
+    #[test]
+    fn test_sampler_applies_the_duplicate_policy() {
+        use crate::leveldb_parser::parser::DuplicatePolicy;
+
+        let copy = |name: &str, updated: &str| {
+            let mut document = create_test_document("user1", "users", HashMap::from([
+                ("name".to_string(), json!(name)),
+            ]));
+            document.metadata.updated_at = Some(chrono::DateTime::parse_from_rfc3339(updated).unwrap().into());
+            document
+        };
+        let draw = |policy: DuplicatePolicy| {
+            let mut sampler = DocumentSampler::new(SamplingConfig::new()).with_duplicate_policy(policy);
+            for document in [
+                copy("older", "2024-01-01T00:00:00Z"),
+                copy("newer", "2024-03-01T00:00:00Z"),
+                create_test_document("user2", "users", HashMap::new()),
+            ] {
+                sampler.add(document)?;
+            }
+            Ok::<_, crate::error::FireupError>((sampler.duplicate_count(), sampler.finish()))
+        };
+        let names = |sample: &DocumentSample| sample.documents().iter()
+            .filter_map(|doc| match doc.data.get("name") {
+                Some(FirestoreValue::String(name)) => Some(name.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let (duplicates, latest) = draw(DuplicatePolicy::LatestUpdate).unwrap();
+        assert_eq!(duplicates, 1);
+        assert_eq!(latest.total_documents(), 2);
+        assert_eq!(latest.collection_counts("users").unwrap().document_count, 2);
+        assert_eq!(names(&latest), vec!["newer"]);
+        assert_eq!(names(&draw(DuplicatePolicy::FirstWins).unwrap().1), vec!["older"]);
+
+        let (_, kept) = draw(DuplicatePolicy::KeepAll).unwrap();
+        assert_eq!(kept.total_documents(), 3);
+        assert!(draw(DuplicatePolicy::Error).is_err());
+    }
+
     #[tokio::test]
     async fn test_empty_documents() {
         initialize_monitoring(MonitoringConfig::default());
@@ -211,6 +438,100 @@ mod analyzer_tests {
         assert_eq!(analysis.metadata.total_documents, 0);
     }
 
+    #[tokio::test]
+    async fn test_field_types_come_from_typed_values() {
+        initialize_monitoring(MonitoringConfig::default());
+        let analyzer = DocumentStructureAnalyzer::new();
+
+        let mut event = create_test_document("event1", "events", HashMap::from([
+            // Looks like a timestamp and a path, but is just a string
+            ("label".to_string(), json!("2023-01-01T00:00:00Z")),
+            ("path".to_string(), json!("users/user1")),
+        ]));
+        event.add_field("at".to_string(), FirestoreValue::Timestamp(chrono::Utc::now()));
+        event.add_field("payload".to_string(), FirestoreValue::Bytes(vec![1, 2, 3]));
+        event.add_field("place".to_string(), FirestoreValue::GeoPoint { latitude: 1.0, longitude: 2.0 });
+
+        let analysis = analyzer.analyze_documents(&[event]).await.unwrap();
+        let type_of = |field: &str| {
+            let analysis = analysis.field_types.iter()
+                .find(|ft| ft.field_path == format!("events.{}", field))
+                .unwrap();
+            (analysis.type_frequencies.keys().next().unwrap().clone(), analysis.recommended_type.clone())
+        };
+
+        assert!(matches!(type_of("label"), (t, PostgreSQLType::Text) if t == "string"));
+        assert!(matches!(type_of("at"), (t, PostgreSQLType::Timestamp) if t == "timestamp"));
+        assert!(matches!(type_of("payload"), (t, PostgreSQLType::Bytea) if t == "bytes"));
+        assert!(matches!(type_of("place"), (t, PostgreSQLType::Jsonb) if t == "geopoint"));
+        assert!(analysis.relationships.is_empty());
+    }
+
+    #[test]
+    fn test_storage_size_follows_firestore_rules() {
+        assert_eq!(document_name_size("users/jeff/tasks/my_task_id"), 44);
+        assert_eq!(
+            document_name_size("projects/p/databases/(default)/documents/users/jeff/tasks/my_task_id"),
+            44
+        );
+
+        assert_eq!(FirestoreValue::Null.storage_size(), 1);
+        assert_eq!(FirestoreValue::Boolean(true).storage_size(), 1);
+        assert_eq!(FirestoreValue::Integer(1).storage_size(), 8);
+        assert_eq!(FirestoreValue::Double(1.5).storage_size(), 8);
+        assert_eq!(FirestoreValue::Timestamp(chrono::Utc::now()).storage_size(), 8);
+        assert_eq!(FirestoreValue::GeoPoint { latitude: 0.0, longitude: 0.0 }.storage_size(), 16);
+        assert_eq!(FirestoreValue::String("héllo".to_string()).storage_size(), 7);
+        assert_eq!(FirestoreValue::Bytes(vec![0; 10]).storage_size(), 10);
+        assert_eq!(FirestoreValue::Reference("users/jeff".to_string()).storage_size(), 27);
+        assert_eq!(
+            FirestoreValue::Array(vec![FirestoreValue::Integer(1), FirestoreValue::Null]).storage_size(),
+            9
+        );
+        // Key "city" (5) and value "Paris" (6)
+        assert_eq!(FirestoreValue::from(json!({"city": "Paris"})).storage_size(), 11);
+
+        let mut task = FirestoreDocument::new("my_task_id".to_string(), "tasks".to_string(), "users/jeff/tasks/my_task_id".to_string());
+        task.add_field("type".to_string(), FirestoreValue::String("Personal".to_string()));
+        task.add_field("done".to_string(), FirestoreValue::Boolean(false));
+        task.add_field("priority".to_string(), FirestoreValue::Integer(1));
+        task.add_field("description".to_string(), FirestoreValue::String("Learn Cloud Firestore".to_string()));
+        assert_eq!(task.storage_size(), 147);
+    }
+
+    #[tokio::test]
+    async fn test_collection_size_distribution() {
+        initialize_monitoring(MonitoringConfig::default());
+        let analyzer = DocumentStructureAnalyzer::new();
+
+        let mut documents: Vec<FirestoreDocument> = (1..=100u64)
+            .map(|i| {
+                let mut doc = FirestoreDocument::new(format!("d{}", i), "logs".to_string(), format!("logs/d{}", i));
+                doc.metadata.size_bytes = Some(i * 100);
+                doc
+            })
+            .collect();
+        documents[99].metadata.size_bytes = Some(MAX_DOCUMENT_SIZE - 1);
+        // No stored size: computed from the document itself
+        let mut unsized_doc = FirestoreDocument::new("x".to_string(), "notes".to_string(), "notes/x".to_string());
+        unsized_doc.add_field("text".to_string(), FirestoreValue::String("abc".to_string()));
+        documents.push(unsized_doc);
+
+        let analysis = analyzer.analyze_documents(&documents).await.unwrap();
+        let logs = analysis.collections.iter().find(|c| c.name == "logs").unwrap();
+        assert_eq!(logs.size_distribution.min_bytes, 100);
+        assert_eq!(logs.size_distribution.median_bytes, 5_000);
+        assert_eq!(logs.size_distribution.p95_bytes, 9_500);
+        assert_eq!(logs.size_distribution.p99_bytes, 9_900);
+        assert_eq!(logs.size_distribution.max_bytes, MAX_DOCUMENT_SIZE - 1);
+        assert_eq!(logs.size_distribution.near_limit_count, 1);
+        assert_eq!(logs.avg_document_size, logs.size_distribution.total_bytes as f64 / 100.0);
+
+        // notes (6) + x (2) + 16, then text (5) + abc (4), then 32
+        let notes = analysis.collections.iter().find(|c| c.name == "notes").unwrap();
+        assert_eq!(notes.size_distribution.total_bytes, 65);
+    }
+
     #[tokio::test]
     async fn test_nested_field_analysis() {
         initialize_monitoring(MonitoringConfig::default());
@@ -251,6 +572,7 @@ mod normalizer_tests {
             document_count: 3,
             field_names: vec!["name".to_string(), "email".to_string(), "tags".to_string()],
             avg_document_size: 1024.0,
+            size_distribution: SizeDistribution::default(),
             subcollections: vec![],
         });
         
@@ -265,6 +587,7 @@ mod normalizer_tests {
             total_occurrences: 3,
             presence_percentage: 100.0,
             recommended_type: PostgreSQLType::Text,
+            confidence: None,
         });
         
         analysis.add_field_type(FieldTypeAnalysis {
@@ -277,6 +600,7 @@ mod normalizer_tests {
             total_occurrences: 3,
             presence_percentage: 100.0,
             recommended_type: PostgreSQLType::Text,
+            confidence: None,
         });
         
         // Add normalization opportunity for array field
@@ -336,6 +660,7 @@ mod normalizer_tests {
             total_occurrences: 2,
             presence_percentage: 66.7,
             recommended_type: PostgreSQLType::Text,
+            confidence: None,
         });
         
         let schema = normalizer.normalize_schema(&analysis).unwrap();
@@ -400,6 +725,288 @@ mod normalizer_tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_subcollection_tables_reference_parent() {
+        initialize_monitoring(MonitoringConfig::default());
+        let mut documents = create_test_documents();
+        for (id, parent, total) in [("order1", "user1", 10), ("order2", "user1", 20), ("order3", "user2", 30)] {
+            let mut data = HashMap::new();
+            data.insert("total".to_string(), json!(total));
+            let mut order = create_test_document(id, "orders", data);
+            order.metadata.path = format!("users/{}/orders/{}", parent, id);
+            order.metadata.parent_path = Some(format!("users/{}", parent));
+            documents.push(order);
+        }
+
+        let analysis = DocumentStructureAnalyzer::new().analyze_documents(&documents).await.unwrap();
+
+        // Subcollection documents don't merge into a top-level "orders" collection
+        assert!(analysis.collections.iter().all(|c| c.name != "orders"));
+        let orders = analysis.collections.iter()
+            .find(|c| c.name == "users_orders")
+            .expect("Subcollection should be analyzed under its qualified name");
+        assert_eq!(orders.document_count, 3);
+        let users = analysis.collections.iter().find(|c| c.name == "users").unwrap();
+        assert_eq!(users.subcollections, vec!["orders".to_string()]);
+
+        let schema = NormalizationEngine::new().normalize_schema(&analysis).unwrap();
+        let orders_table = schema.tables.iter()
+            .find(|t| t.name == "users_orders")
+            .expect("Subcollection table should be created");
+        assert!(orders_table.columns.iter().any(|c| c.name == "users_id" && !c.nullable));
+        let fk = orders_table.foreign_keys.iter()
+            .find(|fk| fk.column == "users_id")
+            .expect("Subcollection table should reference its parent");
+        assert_eq!(fk.referenced_table, "users");
+        assert!(schema.relationships.iter().any(|r| r.from_table == "users_orders" && r.to_table == "users"));
+    }
+
+    #[tokio::test]
+    async fn test_reference_arrays_become_junction_tables() {
+        use crate::data_importer::DocumentTransformer;
+
+        initialize_monitoring(MonitoringConfig::default());
+        let mut documents: Vec<FirestoreDocument> = ["t1", "t2", "t3"].iter()
+            .map(|id| create_test_document(id, "tags", HashMap::from([("label".to_string(), json!(id))])))
+            .collect();
+        // Path strings and references both point at tags; t1 is listed twice
+        documents.push(create_test_document("p1", "posts", HashMap::from([
+            ("title".to_string(), json!("First")),
+            ("tagIds".to_string(), json!(["tags/t1", "tags/t2", "tags/t1"])),
+        ])));
+        let mut second = create_test_document("p2", "posts", HashMap::from([("title".to_string(), json!("Second"))]));
+        second.add_field("tagIds".to_string(), FirestoreValue::Array(vec![
+            FirestoreValue::Reference("projects/demo/databases/(default)/documents/tags/t2".to_string()),
+        ]));
+        documents.push(second);
+
+        let analysis = DocumentStructureAnalyzer::new().analyze_documents(&documents).await.unwrap();
+        let relationship = analysis.relationships.iter()
+            .find(|r| r.from_collection == "posts" && r.reference_field == "tagIds")
+            .expect("Array of tag paths should be detected as a relationship");
+        assert_eq!(relationship.to_collection, "tags");
+        assert!(matches!(relationship.relationship_type, RelationshipType::ManyToMany));
+
+        let schema = NormalizationEngine::new().normalize_schema(&analysis).unwrap();
+        let junction = schema.tables.iter()
+            .find(|t| t.name == "posts_tagIds")
+            .expect("Junction table should be created");
+        let column_names: Vec<&str> = junction.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(column_names, vec!["posts_id", "tags_id"]);
+        assert_eq!(junction.primary_key.as_ref().unwrap().columns, vec!["posts_id".to_string(), "tags_id".to_string()]);
+        let referenced: Vec<&str> = junction.foreign_keys.iter().map(|fk| fk.referenced_table.as_str()).collect();
+        assert_eq!(referenced, vec!["posts", "tags"]);
+        let posts = schema.tables.iter().find(|t| t.name == "posts").unwrap();
+        assert!(posts.columns.iter().all(|c| c.name != "tagIds"));
+        assert!(schema.tables.iter().all(|t| t.name != "posts_tags_lookup"));
+
+        let mut transformer = DocumentTransformer::new();
+        let result = transformer.transform_documents(&documents, &schema).unwrap();
+        let row_id = |table: &str, field: &str, value: &str| {
+            result.table_data[table].iter()
+                .find(|row| row.columns.get(field) == Some(&json!(value)))
+                .map(|row| row.primary_key.clone())
+                .unwrap()
+        };
+        let mut pairs: Vec<(Value, Value)> = result.table_data["posts_tagIds"].iter()
+            .map(|row| (row.columns["posts_id"].clone(), row.columns["tags_id"].clone()))
+            .collect();
+        pairs.sort_by_key(|pair| (pair.0.to_string(), pair.1.to_string()));
+        let mut expected = vec![
+            (row_id("posts", "title", "First"), row_id("tags", "label", "t1")),
+            (row_id("posts", "title", "First"), row_id("tags", "label", "t2")),
+            (row_id("posts", "title", "Second"), row_id("tags", "label", "t2")),
+        ];
+        expected.sort_by_key(|pair| (pair.0.to_string(), pair.1.to_string()));
+        assert_eq!(pairs, expected);
+    }
+
+    #[tokio::test]
+    async fn test_one_to_one_references_get_a_unique_index() {
+        initialize_monitoring(MonitoringConfig::default());
+        let mut documents = Vec::new();
+        for n in 1..=10 {
+            documents.push(create_test_document(&format!("u{}", n), "users", HashMap::from([
+                ("name".to_string(), json!(format!("User {}", n))),
+            ])));
+            // One profile per user, and several orders per user
+            documents.push(create_test_document(&format!("p{}", n), "profiles", HashMap::from([
+                ("owner".to_string(), json!(format!("users/u{}", n))),
+            ])));
+            documents.push(create_test_document(&format!("o{}", n), "orders", HashMap::from([
+                ("customer".to_string(), json!(format!("users/u{}", (n + 1) / 2))),
+            ])));
+        }
+
+        let analysis = DocumentStructureAnalyzer::new().analyze_documents(&documents).await.unwrap();
+        let schema = NormalizationEngine::new().normalize_schema(&analysis).unwrap();
+        assert!(!schema.tables.iter().any(|t| t.name.ends_with("_lookup")));
+
+        let index = |table: &str, column: &str| schema.tables.iter()
+            .find(|t| t.name == table)
+            .and_then(|t| t.indexes.iter().find(|i| i.columns == vec![column.to_string()]))
+            .unwrap_or_else(|| panic!("no index on {}.{}", table, column))
+            .clone();
+        assert!(index("profiles", "owner").unique);
+        assert!(!index("orders", "customer").unique);
+    }
+
+    #[tokio::test]
+    async fn test_reference_cycles_keep_one_foreign_key() {
+        use crate::data_importer::tables_in_dependency_order;
+
+        initialize_monitoring(MonitoringConfig::default());
+        // Every order references its user, and every user their last order
+        let mut documents = Vec::new();
+        for n in 1..=10 {
+            documents.push(create_test_document(&format!("u{}", n), "users", HashMap::from([
+                ("lastOrder".to_string(), json!(format!("orders/o{}", n))),
+            ])));
+            documents.push(create_test_document(&format!("o{}", n), "orders", HashMap::from([
+                ("customer".to_string(), json!(format!("users/u{}", n))),
+            ])));
+        }
+
+        let analysis = DocumentStructureAnalyzer::new().analyze_documents(&documents).await.unwrap();
+        let schema = NormalizationEngine::new().normalize_schema(&analysis).unwrap();
+
+        let foreign_keys: Vec<(&str, &str)> = schema.tables.iter()
+            .flat_map(|t| t.foreign_keys.iter().map(move |fk| (t.name.as_str(), fk.column.as_str())))
+            .collect();
+        assert_eq!(foreign_keys.len(), 1, "foreign keys: {:?}", foreign_keys);
+        assert!(schema.warnings.iter().any(|w| w.message.contains("kept without a foreign key")));
+        assert!(tables_in_dependency_order(&schema).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_self_referencing_array_gets_distinct_junction_columns() {
+        use crate::data_importer::DocumentTransformer;
+
+        initialize_monitoring(MonitoringConfig::default());
+        let friends = [("u1", vec!["users/u2", "users/u3"]), ("u2", vec!["users/u1"]), ("u3", vec!["users/u1", "users/u2"])];
+        let documents: Vec<FirestoreDocument> = friends.iter()
+            .map(|(id, friend_ids)| create_test_document(id, "users", HashMap::from([
+                ("name".to_string(), json!(id)),
+                ("friendIds".to_string(), json!(friend_ids)),
+            ])))
+            .collect();
+
+        let analysis = DocumentStructureAnalyzer::new().analyze_documents(&documents).await.unwrap();
+        let schema = NormalizationEngine::new().normalize_schema(&analysis).unwrap();
+        let junction = schema.tables.iter()
+            .find(|t| t.name == "users_friendIds")
+            .expect("Junction table should be created");
+        let column_names: Vec<&str> = junction.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(column_names, vec!["users_id", "friendIds_id"]);
+        assert_eq!(junction.primary_key.as_ref().unwrap().columns, vec!["users_id".to_string(), "friendIds_id".to_string()]);
+        let constraint_names: Vec<&str> = junction.foreign_keys.iter().map(|fk| fk.constraint_name.as_str()).collect();
+        assert_eq!(constraint_names, vec!["fk_users_friendIds_users", "fk_users_friendIds_friendIds"]);
+        assert!(junction.foreign_keys.iter().all(|fk| fk.referenced_table == "users"));
+
+        let mut transformer = DocumentTransformer::new();
+        let result = transformer.transform_documents(&documents, &schema).unwrap();
+        assert!(result.warnings.iter().all(|w| !w.contains("Failed to transform")), "{:?}", result.warnings);
+        let user_id = |name: &str| result.table_data["users"].iter()
+            .find(|row| row.columns["name"] == json!(name))
+            .map(|row| row.primary_key.clone())
+            .unwrap();
+        let rows = &result.table_data["users_friendIds"];
+        assert_eq!(rows.len(), 5);
+        assert!(rows.iter().any(|row| row.columns["users_id"] == user_id("u2") && row.columns["friendIds_id"] == user_id("u1")));
+    }
+
+    #[tokio::test]
+    async fn test_functional_dependencies_become_lookup_tables() {
+        use crate::data_importer::DocumentTransformer;
+
+        initialize_monitoring(MonitoringConfig::default());
+        let customers = [("c1", "Ann", "ann@example.com"), ("c2", "Bob", "bob@example.com"), ("c3", "Cy", "cy@example.com")];
+        let products = [("p1", "Pen", "k1"), ("p2", "Ink", "k1"), ("p3", "Pad", "k2")];
+        let categories = [("k1", "Office"), ("k2", "Paper")];
+        let sales = [(0, 0), (0, 1), (1, 0), (1, 2), (2, 2), (0, 2), (1, 1), (2, 0)];
+        let mut documents: Vec<FirestoreDocument> = sales.iter().enumerate()
+            .map(|(n, &(customer, product))| {
+                let (customer_id, customer_name, customer_email) = customers[customer];
+                let (product_id, product_name, category_id) = products[product];
+                let category_name = categories.iter().find(|(id, _)| *id == category_id).unwrap().1;
+                create_test_document(&format!("o{}", n), "orders", HashMap::from([
+                    ("quantity".to_string(), json!(n + 1)),
+                    ("customerId".to_string(), json!(customer_id)),
+                    ("customerName".to_string(), json!(customer_name)),
+                    ("customerEmail".to_string(), json!(customer_email)),
+                    ("productId".to_string(), json!(product_id)),
+                    ("productName".to_string(), json!(product_name)),
+                    ("categoryId".to_string(), json!(category_id)),
+                    ("categoryName".to_string(), json!(category_name)),
+                ]))
+            })
+            .collect();
+        // Purchases copy the name of the user they belong to
+        for (id, user) in [("u1", "Ann"), ("u2", "Bob")] {
+            documents.push(create_test_document(id, "users", HashMap::from([("name".to_string(), json!(user))])));
+        }
+        for (n, (parent, user)) in [("u1", "Ann"), ("u1", "Ann"), ("u1", "Ann"), ("u2", "Bob"), ("u2", "Bob")].iter().enumerate() {
+            let mut purchase = create_test_document(&format!("b{}", n), "purchases", HashMap::from([
+                ("amount".to_string(), json!(n * 10)),
+                ("userName".to_string(), json!(user)),
+            ]));
+            purchase.metadata.path = format!("users/{}/purchases/b{}", parent, n);
+            purchase.metadata.parent_path = Some(format!("users/{}", parent));
+            documents.push(purchase);
+        }
+
+        let analysis = DocumentStructureAnalyzer::new().analyze_documents(&documents).await.unwrap();
+        let mut dependencies: Vec<(&str, &str, Vec<&str>)> = analysis.functional_dependencies.iter()
+            .map(|fd| (fd.collection.as_str(), fd.determinant.as_str(), fd.dependents.iter().map(String::as_str).collect()))
+            .collect();
+        dependencies.sort();
+        assert_eq!(dependencies, vec![
+            ("orders", "categoryId", vec!["categoryName"]),
+            ("orders", "customerId", vec!["customerEmail", "customerName"]),
+            ("orders", "productId", vec!["categoryId", "productName"]),
+            ("users_purchases", "users_id", vec!["userName"]),
+        ]);
+        let second_nf: Vec<_> = analysis.normalization_opportunities.iter()
+            .filter(|o| o.normalization_type == NormalizationType::SecondNormalForm)
+            .map(|o| (o.collection.as_str(), o.field_path.as_str()))
+            .collect();
+        assert_eq!(second_nf, vec![("users_purchases", "users_id")]);
+
+        let schema = NormalizationEngine::new().normalize_schema(&analysis).unwrap();
+        let table = |name: &str| schema.tables.iter().find(|t| t.name == name).unwrap_or_else(|| panic!("missing table {}", name));
+        let column_names = |name: &str| {
+            let mut names: Vec<String> = table(name).columns.iter().map(|c| c.name.clone()).collect();
+            names.sort();
+            names
+        };
+        assert_eq!(column_names("orders"), vec!["customerId", "id", "productId", "quantity"]);
+        assert_eq!(column_names("orders_productId_lookup"), vec!["categoryId", "productId", "productName"]);
+        assert_eq!(column_names("orders_categoryId_lookup"), vec!["categoryId", "categoryName"]);
+        assert_eq!(column_names("users_purchases_users_id_lookup"), vec!["userName", "users_id"]);
+        let product_fk = &table("orders_productId_lookup").foreign_keys[0];
+        assert_eq!((product_fk.column.as_str(), product_fk.referenced_table.as_str()), ("categoryId", "orders_categoryId_lookup"));
+
+        let mut transformer = DocumentTransformer::new();
+        let result = transformer.transform_documents(&documents, &schema).unwrap();
+        let rows = |name: &str| &result.table_data[name];
+        assert_eq!(rows("orders").len(), 8);
+        assert_eq!(rows("orders_customerId_lookup").len(), 3);
+        assert_eq!(rows("orders_productId_lookup").len(), 3);
+        assert_eq!(rows("orders_categoryId_lookup").len(), 2);
+        let bob = rows("orders_customerId_lookup").iter().find(|row| row.primary_key == json!("c2")).unwrap();
+        assert_eq!(bob.columns["customerEmail"], json!("bob@example.com"));
+        let pad = rows("orders_productId_lookup").iter().find(|row| row.primary_key == json!("p3")).unwrap();
+        assert_eq!(pad.columns["categoryId"], json!("k2"));
+        let first_order = rows("orders").iter().find(|row| row.columns["quantity"] == json!(1)).unwrap();
+        assert_eq!(first_order.columns["customerId"], json!("c1"));
+
+        let user_ids: Vec<Value> = rows("users").iter().map(|row| row.primary_key.clone()).collect();
+        let purchase_users = rows("users_purchases_users_id_lookup");
+        assert_eq!(purchase_users.len(), 2);
+        assert!(purchase_users.iter().all(|row| user_ids.contains(&row.columns["users_id"])));
+        assert!(result.sql_statements.iter().any(|sql| sql.starts_with("INSERT INTO orders_categoryId_lookup")));
+    }
+
     #[test]
     fn test_schema_metadata() {
         let normalizer = NormalizationEngine::new();
@@ -434,6 +1041,7 @@ mod type_conflict_resolver_tests {
             total_occurrences: 10,
             presence_percentage: 100.0,
             recommended_type: PostgreSQLType::Integer,
+            confidence: None,
         });
         
         // Add field with severe conflict
@@ -450,6 +1058,7 @@ mod type_conflict_resolver_tests {
             total_occurrences: 10,
             presence_percentage: 100.0,
             recommended_type: PostgreSQLType::Jsonb,
+            confidence: None,
         });
         
         // Add field with compatible types
@@ -464,6 +1073,7 @@ mod type_conflict_resolver_tests {
             total_occurrences: 10,
             presence_percentage: 100.0,
             recommended_type: PostgreSQLType::Numeric(None, None),
+            confidence: None,
         });
         
         analysis
@@ -570,6 +1180,7 @@ mod type_conflict_resolver_tests {
             total_occurrences: 10,
             presence_percentage: 100.0,
             recommended_type: PostgreSQLType::Text,
+            confidence: None,
         });
         
         let conflicts = resolver.detect_and_resolve_conflicts(&analysis).unwrap();
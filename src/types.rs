@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use base64::Engine;
 
 /// Core data structure representing a Firestore document
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,7 +12,7 @@ pub struct FirestoreDocument {
     /// Collection name
     pub collection: String,
     /// Document data as key-value pairs
-    pub data: HashMap<String, serde_json::Value>,
+    pub data: HashMap<String, FirestoreValue>,
     /// Nested subcollections
     pub subcollections: Vec<FirestoreDocument>,
     /// Document metadata
@@ -27,10 +28,67 @@ pub struct DocumentMetadata {
     pub updated_at: Option<DateTime<Utc>>,
     /// Document path in Firestore hierarchy
     pub path: String,
-    /// Document size in bytes
+    /// Path of the parent document for documents in subcollections
+    /// (e.g. `users/u1` for `users/u1/orders/o1`)
+    #[serde(default)]
+    pub parent_path: Option<String>,
+    /// Datastore namespace of the document; `None` for the default namespace
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// Project the document was exported from, when its name or key carries it
+    #[serde(default)]
+    pub project: Option<String>,
+    /// Database the document was exported from (`(default)` or a named
+    /// database), when its resource name carries it
+    #[serde(default)]
+    pub database: Option<String>,
+    /// Document storage size in bytes, as computed by
+    /// [`FirestoreDocument::storage_size`]
     pub size_bytes: Option<u64>,
 }
 
+/// Maximum size of a Firestore document in bytes (1 MiB)
+pub const MAX_DOCUMENT_SIZE: u64 = 1_048_576;
+
+/// Size from which a document is reported as close to [`MAX_DOCUMENT_SIZE`]
+/// (90% of the limit)
+pub const NEAR_LIMIT_DOCUMENT_SIZE: u64 = MAX_DOCUMENT_SIZE / 10 * 9;
+
+/// A Firestore field value with its Firestore type preserved.
+///
+/// Serializes in the Firestore REST encoding of
+/// [`FirestoreValue::to_firestore_json`] (`{"integerValue": "1"}`,
+/// `{"arrayValue": {"values": [...]}}`), so documents round-trip without
+/// losing types.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FirestoreValue {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Double(f64),
+    /// Timestamp with nanosecond precision
+    Timestamp(DateTime<Utc>),
+    String(String),
+    Bytes(Vec<u8>),
+    /// Document reference, as a path or full resource name
+    Reference(String),
+    GeoPoint { latitude: f64, longitude: f64 },
+    Array(Vec<FirestoreValue>),
+    Map(HashMap<String, FirestoreValue>),
+}
+
+impl Serialize for FirestoreValue {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_firestore_json().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FirestoreValue {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        serde_json::Value::deserialize(deserializer).map(|value| Self::from_firestore_json(&value))
+    }
+}
+
 /// PostgreSQL table definition for normalized schema
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TableDefinition {
@@ -87,6 +145,8 @@ pub enum PostgreSQLType {
     Boolean,
     /// Timestamp with timezone
     Timestamp,
+    /// Binary data
+    Bytea,
     /// UUID
     Uuid,
     /// JSON Binary
@@ -132,10 +192,58 @@ pub struct SchemaAnalysis {
     pub relationships: Vec<DetectedRelationship>,
     /// Opportunities for normalization
     pub normalization_opportunities: Vec<NormalizationOpportunity>,
+    /// Functional dependencies between fields of a collection
+    #[serde(default)]
+    pub functional_dependencies: Vec<FunctionalDependency>,
+    /// How the documents were sampled, when not all of them were analyzed
+    #[serde(default)]
+    pub sampling: Option<SamplingSummary>,
     /// Analysis metadata
     pub metadata: AnalysisMetadata,
 }
 
+/// Documents analyzed when a schema analysis works from a sample
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SamplingSummary {
+    /// Share of each collection's documents drawn at random
+    pub sample_rate: f64,
+    /// Documents drawn from each collection however small the rate
+    pub min_sample_size: usize,
+    /// Documents kept for each field, so that rare fields are typed
+    pub rare_field_sample_size: usize,
+    /// Seed of the sample; the same seed draws the same documents
+    pub seed: u64,
+    /// Documents in the export
+    pub total_documents: u64,
+    /// Documents analyzed, rare-field documents included
+    pub sampled_documents: u64,
+    /// Per collection figures
+    pub collections: Vec<CollectionSampling>,
+}
+
+impl SamplingSummary {
+    /// Share of the export's documents analyzed
+    pub fn sampled_fraction(&self) -> f64 {
+        if self.total_documents == 0 {
+            return 1.0;
+        }
+        self.sampled_documents as f64 / self.total_documents as f64
+    }
+}
+
+/// Sample drawn from one collection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CollectionSampling {
+    /// Collection name
+    pub collection: String,
+    /// Documents of the collection in the export
+    pub document_count: u64,
+    /// Documents of the uniform sample
+    pub sampled_documents: u64,
+    /// Further documents kept for their rare fields
+    pub rare_field_documents: u64,
+}
+
 /// Analysis of a specific collection
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CollectionAnalysis {
@@ -147,10 +255,32 @@ pub struct CollectionAnalysis {
     pub field_names: Vec<String>,
     /// Average document size in bytes
     pub avg_document_size: f64,
+    /// Distribution of document storage sizes
+    #[serde(default)]
+    pub size_distribution: SizeDistribution,
     /// Nested subcollections found
     pub subcollections: Vec<String>,
 }
 
+/// Distribution of the storage sizes of a collection's documents
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SizeDistribution {
+    /// Smallest document size in bytes
+    pub min_bytes: u64,
+    /// Median document size in bytes
+    pub median_bytes: u64,
+    /// 95th percentile document size in bytes
+    pub p95_bytes: u64,
+    /// 99th percentile document size in bytes
+    pub p99_bytes: u64,
+    /// Largest document size in bytes
+    pub max_bytes: u64,
+    /// Sum of all document sizes in bytes
+    pub total_bytes: u64,
+    /// Documents of at least [`NEAR_LIMIT_DOCUMENT_SIZE`] bytes
+    pub near_limit_count: u64,
+}
+
 /// Analysis of field types across documents
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FieldTypeAnalysis {
@@ -164,6 +294,69 @@ pub struct FieldTypeAnalysis {
     pub presence_percentage: f64,
     /// Recommended PostgreSQL type
     pub recommended_type: PostgreSQLType,
+    /// How far the type figures can be trusted, when they come from a sample
+    #[serde(default)]
+    pub confidence: Option<FieldConfidence>,
+}
+
+/// Confidence in the type figures of a field analyzed from a sample.
+///
+/// The dominant type share is estimated from the sampled occurrences, with
+/// a 95% margin of error corrected for the finite number of occurrences.
+/// A type never seen in the sample can still make up to
+/// `max_unseen_type_share` of the occurrences (the rule of three).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldConfidence {
+    /// Occurrences of the field in the whole export
+    pub total_occurrences: u64,
+    /// Occurrences whose type was looked at
+    pub sampled_occurrences: u64,
+    /// Share of the sampled occurrences holding the most frequent type
+    pub dominant_type_share: f64,
+    /// 95% margin of error of `dominant_type_share`
+    pub margin_of_error: f64,
+    /// Upper 95% bound on the share of a type missing from the sample
+    pub max_unseen_type_share: f64,
+}
+
+impl FieldConfidence {
+    /// Confidence figures for a field seen `total` times, `sampled` of
+    /// which were looked at, `dominant` of those with the most frequent type
+    pub fn estimate(total: u64, sampled: u64, dominant: u64) -> Self {
+        let sampled = sampled.min(total);
+        if sampled == 0 {
+            return Self {
+                total_occurrences: total,
+                sampled_occurrences: 0,
+                dominant_type_share: 0.0,
+                margin_of_error: 1.0,
+                max_unseen_type_share: 1.0,
+            };
+        }
+        let share = dominant as f64 / sampled as f64;
+        let (margin_of_error, max_unseen_type_share) = if sampled >= total {
+            (0.0, 0.0)
+        } else {
+            let n = sampled as f64;
+            let finite_population = ((total - sampled) as f64 / (total - 1) as f64).sqrt();
+            (
+                1.96 * (share * (1.0 - share) / n).sqrt() * finite_population,
+                (3.0 / n).min(1.0),
+            )
+        };
+        Self {
+            total_occurrences: total,
+            sampled_occurrences: sampled,
+            dominant_type_share: share,
+            margin_of_error,
+            max_unseen_type_share,
+        }
+    }
+
+    /// Whether every occurrence was looked at
+    pub fn is_exact(&self) -> bool {
+        self.sampled_occurrences >= self.total_occurrences
+    }
 }
 
 /// Detected relationship between collections
@@ -179,14 +372,35 @@ pub struct DetectedRelationship {
     pub relationship_type: RelationshipType,
     /// Confidence score (0.0 to 1.0)
     pub confidence: f64,
+    /// What the relationship was detected from
+    #[serde(default)]
+    pub evidence: RelationshipEvidence,
+}
+
+/// What a detected relationship is based on
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum RelationshipEvidence {
+    /// Reference values or document path strings
+    #[default]
+    References,
+    /// Bare IDs: `matching_values` of the field's `distinct_values` are
+    /// document IDs of the target collection
+    InclusionDependency {
+        distinct_values: u64,
+        matching_values: u64,
+    },
 }
 
 /// Types of relationships between collections
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RelationshipType {
+    /// Each document references at most one target, and each target is
+    /// referenced by at most one document
     OneToOne,
     OneToMany,
+    /// Several documents reference the same target
     ManyToOne,
+    /// Documents hold several targets, as arrays of references
     ManyToMany,
 }
 
@@ -205,8 +419,39 @@ pub struct NormalizationOpportunity {
     pub impact: NormalizationImpact,
 }
 
+/// Functional dependency between the fields of a collection: documents with
+/// the same determinant value have the same values for every dependent field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionalDependency {
+    /// Collection name
+    pub collection: String,
+    /// Determining field, or `{parent}_id` for the parent document of
+    /// subcollection documents
+    pub determinant: String,
+    /// Fields determined by the determinant
+    pub dependents: Vec<String>,
+    /// Documents holding the determinant
+    pub document_count: u64,
+    /// Distinct determinant values
+    pub distinct_values: u64,
+    /// Second normal form for dependencies on the parent document (part of
+    /// the key of subcollection documents), third normal form otherwise
+    pub normalization_type: NormalizationType,
+}
+
+impl FunctionalDependency {
+    /// Share of the documents repeating dependent values already held by
+    /// another document with the same determinant value
+    pub fn redundancy(&self) -> f64 {
+        if self.document_count == 0 {
+            return 0.0;
+        }
+        (self.document_count - self.distinct_values) as f64 / self.document_count as f64
+    }
+}
+
 /// Types of database normalization
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum NormalizationType {
     /// First Normal Form - eliminate repeating groups
     FirstNormalForm,
@@ -359,14 +604,18 @@ impl FirestoreDocument {
                 created_at: None,
                 updated_at: None,
                 path,
+                parent_path: None,
+                namespace: None,
+                project: None,
+                database: None,
                 size_bytes: None,
             },
         }
     }
     
     /// Add data to the document
-    pub fn add_field(&mut self, key: String, value: serde_json::Value) {
-        self.data.insert(key, value);
+    pub fn add_field(&mut self, key: String, value: impl Into<FirestoreValue>) {
+        self.data.insert(key, value.into());
     }
     
     /// Add a subcollection document
@@ -378,6 +627,354 @@ impl FirestoreDocument {
     pub fn full_path(&self) -> String {
         format!("{}/{}", self.collection, self.id)
     }
+
+    /// Path of the document relative to the database root, e.g.
+    /// `users/u1/orders/o1`; identifies the document across shards and files
+    pub fn document_path(&self) -> String {
+        match relative_document_path(&self.metadata.path) {
+            "" | "unknown" => self.full_path(),
+            path => path.to_string(),
+        }
+    }
+
+    /// Collection name qualified by its ancestor collections, e.g.
+    /// `users_orders` for a document at `users/u1/orders/o1`. Documents of a
+    /// named Datastore namespace are prefixed with it (`billing__users_orders`).
+    pub fn qualified_collection(&self) -> String {
+        let qualified = match &self.metadata.parent_path {
+            Some(parent_path) => format!("{}_{}", qualified_collection_of(parent_path), self.collection),
+            None => self.collection.clone(),
+        };
+        self.namespaced(qualified)
+    }
+
+    /// Qualified collection and ID of the parent document, for documents in
+    /// subcollections and entities with ancestor keys
+    pub fn parent_reference(&self) -> Option<(String, String)> {
+        let parent_path = self.metadata.parent_path.as_ref()?;
+        let parent_id = parent_path.rsplit('/').next()?;
+        Some((self.namespaced(qualified_collection_of(parent_path)), parent_id.to_string()))
+    }
+
+    /// Storage size of the document following Firestore's storage size
+    /// rules: document name size, plus the size of each field name and
+    /// value, plus 32 bytes
+    pub fn storage_size(&self) -> u64 {
+        document_name_size(&self.document_path()) + self.fields_storage_size() + 32
+    }
+
+    /// Sum of the field name and field value sizes of the document
+    pub fn fields_storage_size(&self) -> u64 {
+        fields_storage_size(&self.data)
+    }
+
+    /// Prefix a qualified collection with the document's namespace, if any
+    fn namespaced(&self, qualified: String) -> String {
+        match self.metadata.namespace.as_deref().filter(|namespace| !namespace.is_empty()) {
+            Some(namespace) => format!("{}__{}", namespace_table_prefix(namespace), qualified),
+            None => qualified,
+        }
+    }
+}
+
+impl FirestoreValue {
+    /// Decode a value in the Firestore REST encoding (`{"stringValue": "a"}`).
+    /// Values that are not encoded that way are converted as plain JSON.
+    pub fn from_firestore_json(value: &serde_json::Value) -> Self {
+        let Some((type_key, inner)) = value.as_object()
+            .filter(|obj| obj.len() == 1)
+            .and_then(|obj| obj.iter().next())
+        else {
+            return Self::from(value.clone());
+        };
+
+        let decoded = match type_key.as_str() {
+            "nullValue" => Some(Self::Null),
+            "booleanValue" => inner.as_bool().map(Self::Boolean),
+            // The REST API encodes 64-bit integers as strings
+            "integerValue" => inner.as_i64()
+                .or_else(|| inner.as_str().and_then(|s| s.parse().ok()))
+                .map(Self::Integer),
+            "doubleValue" => inner.as_f64()
+                .or_else(|| inner.as_str().and_then(|s| s.parse().ok()))
+                .map(Self::Double),
+            "timestampValue" => inner.as_str()
+                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                .map(|dt| Self::Timestamp(dt.with_timezone(&Utc))),
+            "stringValue" => inner.as_str().map(|s| Self::String(s.to_string())),
+            "bytesValue" => inner.as_str()
+                .and_then(|s| base64::engine::general_purpose::STANDARD.decode(s).ok())
+                .map(Self::Bytes),
+            "referenceValue" => inner.as_str().map(|s| Self::Reference(s.to_string())),
+            "geoPointValue" => Some(Self::GeoPoint {
+                latitude: inner.get("latitude").and_then(|v| v.as_f64()).unwrap_or_default(),
+                longitude: inner.get("longitude").and_then(|v| v.as_f64()).unwrap_or_default(),
+            }),
+            "arrayValue" => Some(Self::Array(
+                inner.get("values")
+                    .and_then(|v| v.as_array())
+                    .map(|values| values.iter().map(Self::from_firestore_json).collect())
+                    .unwrap_or_default()
+            )),
+            "mapValue" => Some(Self::Map(
+                inner.get("fields")
+                    .and_then(|v| v.as_object())
+                    .map(|fields| fields.iter()
+                        .map(|(k, v)| (k.clone(), Self::from_firestore_json(v)))
+                        .collect())
+                    .unwrap_or_default()
+            )),
+            _ => None,
+        };
+
+        decoded.unwrap_or_else(|| Self::from(value.clone()))
+    }
+
+    /// Encode the value in the Firestore REST encoding read by
+    /// [`FirestoreValue::from_firestore_json`] (`{"integerValue": "1"}`,
+    /// `{"arrayValue": {"values": [...]}}`)
+    pub fn to_firestore_json(&self) -> serde_json::Value {
+        let (type_key, inner) = match self {
+            Self::Null => ("nullValue", serde_json::Value::Null),
+            Self::Boolean(b) => ("booleanValue", serde_json::Value::Bool(*b)),
+            // 64-bit integers do not survive JSON numbers in every reader
+            Self::Integer(i) => ("integerValue", serde_json::Value::String(i.to_string())),
+            Self::Double(d) => ("doubleValue", serde_json::Number::from_f64(*d)
+                .map(serde_json::Value::Number)
+                .unwrap_or_else(|| serde_json::Value::String(d.to_string()))),
+            Self::Timestamp(dt) => ("timestampValue", serde_json::Value::String(
+                dt.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)
+            )),
+            Self::String(s) => ("stringValue", serde_json::Value::String(s.clone())),
+            Self::Bytes(bytes) => ("bytesValue", serde_json::Value::String(
+                base64::engine::general_purpose::STANDARD.encode(bytes)
+            )),
+            Self::Reference(reference) => ("referenceValue", serde_json::Value::String(reference.clone())),
+            Self::GeoPoint { latitude, longitude } => ("geoPointValue", serde_json::json!({
+                "latitude": latitude,
+                "longitude": longitude,
+            })),
+            Self::Array(values) => ("arrayValue", serde_json::json!({
+                "values": values.iter().map(Self::to_firestore_json).collect::<Vec<_>>(),
+            })),
+            Self::Map(fields) => ("mapValue", serde_json::json!({
+                "fields": fields.iter()
+                    .map(|(k, v)| (k.clone(), v.to_firestore_json()))
+                    .collect::<serde_json::Map<_, _>>(),
+            })),
+        };
+        serde_json::json!({ type_key: inner })
+    }
+
+    /// Plain JSON rendering, as stored in JSONB columns. Timestamps become
+    /// RFC 3339 strings with nanoseconds, bytes become base64.
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Self::Null => serde_json::Value::Null,
+            Self::Boolean(b) => serde_json::Value::Bool(*b),
+            Self::Integer(i) => serde_json::Value::Number((*i).into()),
+            Self::Double(d) => serde_json::Number::from_f64(*d)
+                .map(serde_json::Value::Number)
+                // NaN and infinities have no JSON number representation
+                .unwrap_or_else(|| serde_json::Value::String(d.to_string())),
+            Self::Timestamp(dt) => serde_json::Value::String(
+                dt.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)
+            ),
+            Self::String(s) | Self::Reference(s) => serde_json::Value::String(s.clone()),
+            Self::Bytes(bytes) => serde_json::Value::String(
+                base64::engine::general_purpose::STANDARD.encode(bytes)
+            ),
+            Self::GeoPoint { latitude, longitude } => serde_json::json!({
+                "latitude": latitude,
+                "longitude": longitude,
+            }),
+            Self::Array(values) => serde_json::Value::Array(values.iter().map(Self::to_json).collect()),
+            Self::Map(fields) => serde_json::Value::Object(
+                fields.iter().map(|(k, v)| (k.clone(), v.to_json())).collect()
+            ),
+        }
+    }
+
+    /// Type name used in schema analysis (`integer`, `timestamp`, `reference`, ...)
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Null => "null",
+            Self::Boolean(_) => "boolean",
+            Self::Integer(_) => "integer",
+            Self::Double(_) => "number",
+            Self::Timestamp(_) => "timestamp",
+            Self::String(_) => "string",
+            Self::Bytes(_) => "bytes",
+            Self::Reference(_) => "reference",
+            Self::GeoPoint { .. } => "geopoint",
+            Self::Array(_) => "array",
+            Self::Map(_) => "object",
+        }
+    }
+
+    /// The string, if this is a string value
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The integer, if this is an integer value
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// The numeric value of an integer or double
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Integer(i) => Some(*i as f64),
+            Self::Double(d) => Some(*d),
+            _ => None,
+        }
+    }
+
+    /// Whether this is a null value
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    /// Storage size of the value following Firestore's storage size rules:
+    /// strings take their UTF-8 length plus one byte, integers, doubles and
+    /// timestamps 8 bytes, geo points 16, booleans and null 1, bytes their
+    /// length and references the size of the document name they point to.
+    /// Arrays and maps take the sum of their elements (and map keys).
+    pub fn storage_size(&self) -> u64 {
+        match self {
+            Self::Null | Self::Boolean(_) => 1,
+            Self::Integer(_) | Self::Double(_) | Self::Timestamp(_) => 8,
+            Self::GeoPoint { .. } => 16,
+            Self::String(s) => string_storage_size(s),
+            Self::Bytes(bytes) => bytes.len() as u64,
+            Self::Reference(reference) => document_name_size(reference),
+            Self::Array(values) => values.iter().map(Self::storage_size).sum(),
+            Self::Map(fields) => fields_storage_size(fields),
+        }
+    }
+
+    /// Collection and document ID a reference points to
+    /// (`users/u1/orders/o1` points to `orders`, `o1`)
+    pub fn reference_target(&self) -> Option<(&str, &str)> {
+        let Self::Reference(path) = self else { return None };
+        let mut segments = relative_document_path(path).rsplit('/');
+        let document_id = segments.next().filter(|s| !s.is_empty())?;
+        let collection = segments.next().filter(|s| !s.is_empty())?;
+        Some((collection, document_id))
+    }
+}
+
+/// Plain JSON has no timestamp, bytes, reference or geo point types, so
+/// strings stay strings; numbers become integers when they fit an `i64`
+impl From<serde_json::Value> for FirestoreValue {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => Self::Null,
+            serde_json::Value::Bool(b) => Self::Boolean(b),
+            serde_json::Value::Number(n) => match n.as_i64() {
+                Some(i) => Self::Integer(i),
+                None => Self::Double(n.as_f64().unwrap_or_default()),
+            },
+            serde_json::Value::String(s) => Self::String(s),
+            serde_json::Value::Array(values) => Self::Array(values.into_iter().map(Self::from).collect()),
+            serde_json::Value::Object(fields) => Self::Map(
+                fields.into_iter().map(|(k, v)| (k, Self::from(v))).collect()
+            ),
+        }
+    }
+}
+
+/// Storage size of a string: its UTF-8 length plus one byte
+pub fn string_storage_size(s: &str) -> u64 {
+    s.len() as u64 + 1
+}
+
+/// Storage size of a document name: the string size of each collection and
+/// document ID of the path relative to the database root, plus 16 bytes.
+/// `users/jeff/tasks/my_task_id` takes 6 + 5 + 6 + 11 + 16 = 44 bytes.
+pub fn document_name_size(path: &str) -> u64 {
+    relative_document_path(path)
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(string_storage_size)
+        .sum::<u64>()
+        + 16
+}
+
+/// Sum of the key and value sizes of a set of fields
+fn fields_storage_size(fields: &HashMap<String, FirestoreValue>) -> u64 {
+    fields.iter()
+        .map(|(name, value)| string_storage_size(name) + value.storage_size())
+        .sum()
+}
+
+/// Strip the `projects/{project}/databases/{database}/documents/` prefix of a
+/// document resource name, leaving the path relative to the database root
+pub fn relative_document_path(path: &str) -> &str {
+    match path.find("/documents/") {
+        Some(index) if path.starts_with("projects/") => &path[index + "/documents/".len()..],
+        _ => path,
+    }
+}
+
+/// The document path a string holds, if it looks like the path (or resource
+/// name) of a document in one of the given collections
+pub fn document_path_string<'a>(s: &'a str, collections: &HashSet<String>) -> Option<&'a str> {
+    let path = relative_document_path(s.trim_start_matches('/'));
+    let segments: Vec<&str> = path.split('/').collect();
+    if segments.len() < 2 || !segments.len().is_multiple_of(2) {
+        return None;
+    }
+    if segments.iter().any(|segment| segment.is_empty() || segment.chars().any(char::is_whitespace)) {
+        return None;
+    }
+    segments.iter().step_by(2).all(|collection| collections.contains(*collection)).then_some(path)
+}
+
+/// Project and database of a document resource name
+/// (`projects/{project}/databases/{database}/documents/...`)
+pub fn document_database(path: &str) -> Option<(String, String)> {
+    let rest = path.strip_prefix("projects/")?;
+    let (project, rest) = rest.split_once("/databases/")?;
+    let (database, _) = rest.split_once("/documents/")?;
+    if project.is_empty() || database.is_empty() || project.contains('/') || database.contains('/') {
+        return None;
+    }
+    Some((project.to_string(), database.to_string()))
+}
+
+/// Path of the parent document of a document path, if the document lives in
+/// a subcollection (`users/u1/orders/o1` has parent `users/u1`)
+pub fn parent_document_path(path: &str) -> Option<String> {
+    let segments: Vec<&str> = relative_document_path(path).split('/').collect();
+    if segments.len() < 4 || !segments.len().is_multiple_of(2) || segments.iter().any(|s| s.is_empty()) {
+        return None;
+    }
+    Some(segments[..segments.len() - 2].join("/"))
+}
+
+/// Namespace as used in table names: characters other than ASCII letters,
+/// digits and `_` (Datastore also allows `.` and `-`) become `_`
+pub fn namespace_table_prefix(namespace: &str) -> String {
+    namespace.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// Join the collection segments of a document path with `_`
+fn qualified_collection_of(document_path: &str) -> String {
+    relative_document_path(document_path)
+        .split('/')
+        .step_by(2)
+        .collect::<Vec<_>>()
+        .join("_")
 }
 
 impl TableDefinition {
@@ -462,6 +1059,7 @@ impl PostgreSQLType {
             PostgreSQLType::Numeric(None, None) => "NUMERIC".to_string(),
             PostgreSQLType::Boolean => "BOOLEAN".to_string(),
             PostgreSQLType::Timestamp => "TIMESTAMP WITH TIME ZONE".to_string(),
+            PostgreSQLType::Bytea => "BYTEA".to_string(),
             PostgreSQLType::Uuid => "UUID".to_string(),
             PostgreSQLType::Jsonb => "JSONB".to_string(),
             PostgreSQLType::Array(inner_type) => format!("{}[]", inner_type.to_sql()),
@@ -470,6 +1068,27 @@ impl PostgreSQLType {
     }
 }
 
+impl SizeDistribution {
+    /// Distribution of a set of document sizes; percentiles use the
+    /// nearest-rank method
+    pub fn from_sizes(mut sizes: Vec<u64>) -> Self {
+        if sizes.is_empty() {
+            return Self::default();
+        }
+        sizes.sort_unstable();
+        let percentile = |p: f64| sizes[((p * sizes.len() as f64).ceil() as usize).clamp(1, sizes.len()) - 1];
+        Self {
+            min_bytes: sizes[0],
+            median_bytes: percentile(0.5),
+            p95_bytes: percentile(0.95),
+            p99_bytes: percentile(0.99),
+            max_bytes: sizes[sizes.len() - 1],
+            total_bytes: sizes.iter().sum(),
+            near_limit_count: sizes.iter().filter(|&&size| size >= NEAR_LIMIT_DOCUMENT_SIZE).count() as u64,
+        }
+    }
+}
+
 impl SchemaAnalysis {
     /// Create a new schema analysis
     pub fn new() -> Self {
@@ -478,6 +1097,8 @@ impl SchemaAnalysis {
             field_types: Vec::new(),
             relationships: Vec::new(),
             normalization_opportunities: Vec::new(),
+            functional_dependencies: Vec::new(),
+            sampling: None,
             metadata: AnalysisMetadata {
                 total_documents: 0,
                 total_collections: 0,
@@ -513,6 +1134,23 @@ impl SchemaAnalysis {
     pub fn add_normalization_opportunity(&mut self, opportunity: NormalizationOpportunity) {
         self.normalization_opportunities.push(opportunity);
     }
+    
+    /// Add functional dependency
+    pub fn add_functional_dependency(&mut self, dependency: FunctionalDependency) {
+        self.functional_dependencies.push(dependency);
+    }
+    
+    /// The sampled field whose dominant type has the widest margin of error,
+    /// then the fewest sampled occurrences
+    pub fn least_confident_field(&self) -> Option<&FieldTypeAnalysis> {
+        self.field_types.iter()
+            .filter_map(|field| field.confidence.as_ref().map(|confidence| (field, confidence)))
+            .max_by(|(_, a), (_, b)| {
+                a.margin_of_error.total_cmp(&b.margin_of_error)
+                    .then_with(|| a.max_unseen_type_share.total_cmp(&b.max_unseen_type_share))
+            })
+            .map(|(field, _)| field)
+    }
 }
 
 impl TypeConflict {
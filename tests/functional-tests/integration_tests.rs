@@ -151,11 +151,11 @@ async fn test_document_collection_grouping() {
     ];
     
     // Add some variety to the data
-    test_documents[0].data.insert("role".to_string(), serde_json::json!("admin"));
-    test_documents[1].data.insert("role".to_string(), serde_json::json!("user"));
-    test_documents[2].data.insert("total".to_string(), serde_json::json!(99.99));
-    test_documents[3].data.insert("total".to_string(), serde_json::json!(149.50));
-    test_documents[4].data.insert("price".to_string(), serde_json::json!(29.99));
+    test_documents[0].add_field("role".to_string(), serde_json::json!("admin"));
+    test_documents[1].add_field("role".to_string(), serde_json::json!("user"));
+    test_documents[2].add_field("total".to_string(), serde_json::json!(99.99));
+    test_documents[3].add_field("total".to_string(), serde_json::json!(149.50));
+    test_documents[4].add_field("price".to_string(), serde_json::json!(29.99));
     
     // Test schema analysis with these documents
     let analyzer = DocumentStructureAnalyzer::new();
@@ -218,20 +218,20 @@ async fn test_type_inference_and_conflicts() {
     
     // Document 1: age as integer
     let mut doc1 = create_test_document("user1", "users");
-    doc1.data.insert("age".to_string(), serde_json::json!(25));
-    doc1.data.insert("score".to_string(), serde_json::json!(95.5));
+    doc1.add_field("age".to_string(), serde_json::json!(25));
+    doc1.add_field("score".to_string(), serde_json::json!(95.5));
     test_documents.push(doc1);
     
     // Document 2: age as string (type conflict)
     let mut doc2 = create_test_document("user2", "users");
-    doc2.data.insert("age".to_string(), serde_json::json!("thirty"));
-    doc2.data.insert("score".to_string(), serde_json::json!(87));
+    doc2.add_field("age".to_string(), serde_json::json!("thirty"));
+    doc2.add_field("score".to_string(), serde_json::json!(87));
     test_documents.push(doc2);
     
     // Document 3: age as integer again
     let mut doc3 = create_test_document("user3", "users");
-    doc3.data.insert("age".to_string(), serde_json::json!(30));
-    doc3.data.insert("score".to_string(), serde_json::json!(92.0));
+    doc3.add_field("age".to_string(), serde_json::json!(30));
+    doc3.add_field("score".to_string(), serde_json::json!(92.0));
     test_documents.push(doc3);
     
     let analyzer = DocumentStructureAnalyzer::new();
@@ -293,7 +293,7 @@ async fn test_nested_field_analysis() {
         let mut doc = create_test_document(&format!("user{}", i), "users");
         
         // Add nested profile data
-        doc.data.insert("profile".to_string(), serde_json::json!({
+        doc.add_field("profile".to_string(), serde_json::json!({
             "firstName": format!("User{}", i),
             "lastName": "Doe",
             "address": {
@@ -309,7 +309,7 @@ async fn test_nested_field_analysis() {
         }));
         
         // Add array data
-        doc.data.insert("tags".to_string(), serde_json::json!([
+        doc.add_field("tags".to_string(), serde_json::json!([
             "user", format!("level{}", i), "active"
         ]));
         
@@ -383,28 +383,28 @@ async fn test_large_document_set_performance() {
         // Add varied data based on collection
         match collection {
             "users" => {
-                doc.data.insert("email".to_string(), serde_json::json!(format!("user{}@example.com", i)));
-                doc.data.insert("age".to_string(), serde_json::json!(20 + (i % 50)));
-                doc.data.insert("premium".to_string(), serde_json::json!(i % 3 == 0));
+                doc.add_field("email".to_string(), serde_json::json!(format!("user{}@example.com", i)));
+                doc.add_field("age".to_string(), serde_json::json!(20 + (i % 50)));
+                doc.add_field("premium".to_string(), serde_json::json!(i % 3 == 0));
             }
             "orders" => {
-                doc.data.insert("total".to_string(), serde_json::json!((i as f64) * 10.99));
-                doc.data.insert("items".to_string(), serde_json::json!(i % 5 + 1));
-                doc.data.insert("status".to_string(), serde_json::json!(
+                doc.add_field("total".to_string(), serde_json::json!((i as f64) * 10.99));
+                doc.add_field("items".to_string(), serde_json::json!(i % 5 + 1));
+                doc.add_field("status".to_string(), serde_json::json!(
                     match i % 3 { 0 => "pending", 1 => "shipped", _ => "delivered" }
                 ));
             }
             "products" => {
-                doc.data.insert("price".to_string(), serde_json::json!((i as f64) * 2.99));
-                doc.data.insert("category".to_string(), serde_json::json!(
+                doc.add_field("price".to_string(), serde_json::json!((i as f64) * 2.99));
+                doc.add_field("category".to_string(), serde_json::json!(
                     match i % 4 { 0 => "electronics", 1 => "books", 2 => "clothing", _ => "home" }
                 ));
-                doc.data.insert("inStock".to_string(), serde_json::json!(i % 7 != 0));
+                doc.add_field("inStock".to_string(), serde_json::json!(i % 7 != 0));
             }
             "reviews" => {
-                doc.data.insert("rating".to_string(), serde_json::json!(1 + (i % 5)));
-                doc.data.insert("verified".to_string(), serde_json::json!(i % 2 == 0));
-                doc.data.insert("helpful".to_string(), serde_json::json!(i % 10));
+                doc.add_field("rating".to_string(), serde_json::json!(1 + (i % 5)));
+                doc.add_field("verified".to_string(), serde_json::json!(i % 2 == 0));
+                doc.add_field("helpful".to_string(), serde_json::json!(i % 10));
             }
             _ => {}
         }
@@ -1,7 +1,7 @@
 use std::path::PathBuf;
 use std::collections::HashMap;
 use serde_json::json;
-use fireup::types::{FirestoreDocument, DocumentMetadata};
+use fireup::types::{FirestoreDocument, FirestoreValue, DocumentMetadata};
 use fireup::leveldb_parser::parser::FirestoreDocumentParser;
 
 /// Test data directory path
@@ -36,10 +36,10 @@ pub fn ensure_monitoring_initialized() {
 
 /// Create a test Firestore document
 pub fn create_test_document(id: &str, collection: &str) -> FirestoreDocument {
-    let mut data = HashMap::new();
-    data.insert("name".to_string(), json!("Test Document"));
-    data.insert("value".to_string(), json!(42));
-    data.insert("active".to_string(), json!(true));
+    let mut data: HashMap<String, FirestoreValue> = HashMap::new();
+    data.insert("name".to_string(), json!("Test Document").into());
+    data.insert("value".to_string(), json!(42).into());
+    data.insert("active".to_string(), json!(true).into());
     
     FirestoreDocument {
         id: id.to_string(),
@@ -50,6 +50,10 @@ pub fn create_test_document(id: &str, collection: &str) -> FirestoreDocument {
             created_at: Some(chrono::Utc::now()),
             updated_at: Some(chrono::Utc::now()),
             path: format!("projects/test/databases/(default)/documents/{}/{}", collection, id),
+            parent_path: None,
+            namespace: None,
+            project: Some("test".to_string()),
+            database: Some("(default)".to_string()),
             size_bytes: Some(256),
         },
     }
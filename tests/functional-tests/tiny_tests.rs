@@ -1,6 +1,6 @@
 use super::test_utils::*;
 use fireup::leveldb_parser::parser::{FirestoreDocumentParser, LevelDBParser};
-use fireup::types::FirestoreDocument;
+use fireup::types::{FirestoreDocument, FirestoreValue};
 use tokio;
 
 /// Tiny test: Parse a single string value from emulator dump
@@ -31,7 +31,7 @@ async fn test_tiny_string_parsing() {
             let string_doc = result.documents.iter()
                 .find(|doc| {
                     doc.data.values().any(|v| {
-                        v.is_string() && !v.as_str().unwrap_or("").is_empty()
+                        !v.as_str().unwrap_or("").is_empty()
                     })
                 });
             
@@ -98,7 +98,7 @@ async fn test_tiny_number_parsing() {
             let number_doc = result.documents.iter()
                 .find(|doc| {
                     doc.data.values().any(|v| {
-                        v.is_number() || v.is_i64() || v.is_u64() || v.is_f64()
+                        matches!(v, FirestoreValue::Integer(_) | FirestoreValue::Double(_))
                     })
                 });
             
@@ -109,16 +109,10 @@ async fn test_tiny_number_parsing() {
                     // Find the number value
                     let number_value = doc.data.values()
                         .find_map(|v| {
-                            if v.is_i64() {
-                                Some(v.as_i64().unwrap().to_string())
-                            } else if v.is_u64() {
-                                Some(v.as_u64().unwrap().to_string())
-                            } else if v.is_f64() {
-                                Some(v.as_f64().unwrap().to_string())
-                            } else if v.is_number() {
-                                Some(v.as_number().unwrap().to_string())
-                            } else {
-                                None
+                            match v {
+                                FirestoreValue::Integer(n) => Some(n.to_string()),
+                                FirestoreValue::Double(n) => Some(n.to_string()),
+                                _ => None,
                             }
                         });
                     
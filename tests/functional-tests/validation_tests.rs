@@ -220,23 +220,23 @@ async fn test_data_quality_validation() {
     
     // Document with null values
     let mut null_doc = create_test_document("null1", "users");
-    null_doc.data.insert("nullable_field".to_string(), serde_json::Value::Null);
-    null_doc.data.insert("empty_string".to_string(), serde_json::json!(""));
+    null_doc.add_field("nullable_field".to_string(), serde_json::Value::Null);
+    null_doc.add_field("empty_string".to_string(), serde_json::json!(""));
     test_documents.push(null_doc);
     
     // Document with inconsistent types
     let mut inconsistent_doc = create_test_document("inconsistent1", "users");
-    inconsistent_doc.data.insert("mixed_field".to_string(), serde_json::json!("string_value"));
+    inconsistent_doc.add_field("mixed_field".to_string(), serde_json::json!("string_value"));
     test_documents.push(inconsistent_doc);
     
     let mut inconsistent_doc2 = create_test_document("inconsistent2", "users");
-    inconsistent_doc2.data.insert("mixed_field".to_string(), serde_json::json!(42));
+    inconsistent_doc2.add_field("mixed_field".to_string(), serde_json::json!(42));
     test_documents.push(inconsistent_doc2);
     
     // Document with very long strings
     let mut long_string_doc = create_test_document("long1", "users");
     let long_string = "x".repeat(10000);
-    long_string_doc.data.insert("long_field".to_string(), serde_json::json!(long_string));
+    long_string_doc.add_field("long_field".to_string(), serde_json::json!(long_string));
     test_documents.push(long_string_doc);
     
     // Analyze data quality
@@ -890,6 +890,7 @@ impl MockSchemaAnalyzer {
                     .count() as u64,
                 field_names: vec!["id".to_string(), "name".to_string()],
                 avg_document_size: 1024.0,
+                size_distribution: Default::default(),
                 subcollections: vec![],
             };
             analysis.add_collection(collection_analysis);
@@ -991,15 +992,15 @@ fn create_mock_backup_data() -> Vec<FirestoreDocument> {
                 let mut data = HashMap::new();
                 data.insert(
                     "name".to_string(),
-                    serde_json::Value::String("John Doe".to_string()),
+                    FirestoreValue::String("John Doe".to_string()),
                 );
                 data.insert(
                     "email".to_string(),
-                    serde_json::Value::String("john@example.com".to_string()),
+                    FirestoreValue::String("john@example.com".to_string()),
                 );
                 data.insert(
                     "age".to_string(),
-                    serde_json::Value::Number(serde_json::Number::from(30)),
+                    FirestoreValue::Integer(30),
                 );
                 data
             },
@@ -1008,6 +1009,10 @@ fn create_mock_backup_data() -> Vec<FirestoreDocument> {
                 created_at: Some(chrono::Utc::now()),
                 updated_at: Some(chrono::Utc::now()),
                 path: "users/user1".to_string(),
+                parent_path: None,
+                namespace: None,
+                project: None,
+                database: None,
                 size_bytes: Some(1024),
             },
         },
@@ -1018,15 +1023,15 @@ fn create_mock_backup_data() -> Vec<FirestoreDocument> {
                 let mut data = HashMap::new();
                 data.insert(
                     "title".to_string(),
-                    serde_json::Value::String("Test Post".to_string()),
+                    FirestoreValue::String("Test Post".to_string()),
                 );
                 data.insert(
                     "content".to_string(),
-                    serde_json::Value::String("This is a test post".to_string()),
+                    FirestoreValue::String("This is a test post".to_string()),
                 );
                 data.insert(
                     "author_id".to_string(),
-                    serde_json::Value::String("user1".to_string()),
+                    FirestoreValue::String("user1".to_string()),
                 );
                 data
             },
@@ -1035,6 +1040,10 @@ fn create_mock_backup_data() -> Vec<FirestoreDocument> {
                 created_at: Some(chrono::Utc::now()),
                 updated_at: Some(chrono::Utc::now()),
                 path: "posts/post1".to_string(),
+                parent_path: None,
+                namespace: None,
+                project: None,
+                database: None,
                 size_bytes: Some(512),
             },
         },